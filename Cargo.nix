@@ -1637,6 +1637,10 @@ rec {
             name = "fontdb";
             packageId = "fontdb";
           }
+          {
+            name = "futures";
+            packageId = "futures";
+          }
           {
             name = "fuzzy-matcher";
             packageId = "fuzzy-matcher";
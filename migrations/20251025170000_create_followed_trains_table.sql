@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS followed_trains (
+  message_id BIGINT NOT NULL,
+  channel_id BIGINT NOT NULL,
+  run_number INT NOT NULL,
+  stations TEXT [] NOT NULL,
+  started_at BIGINT NOT NULL,
+  last_seen_index INT NOT NULL DEFAULT 0,
+  missed_polls INT NOT NULL DEFAULT 0,
+  PRIMARY KEY(message_id)
+);
@@ -0,0 +1,16 @@
+-- Add migration script here
+ALTER TABLE guilds
+ADD COLUMN anomaly_alerts BOOLEAN;
+
+ALTER TABLE guilds
+ADD COLUMN anomaly_channel BIGINT;
+
+CREATE TABLE IF NOT EXISTS reported_anomalies (
+  route TEXT NOT NULL,
+  run_number INT NOT NULL,
+  anomaly_type TEXT NOT NULL,
+  first_seen BIGINT NOT NULL,
+  last_seen BIGINT NOT NULL,
+  published_to INT NOT NULL DEFAULT 0,
+  PRIMARY KEY(route, run_number, anomaly_type)
+);
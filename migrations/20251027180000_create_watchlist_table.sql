@@ -0,0 +1,13 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS watchlist (
+  watch_id SERIAL NOT NULL,
+  kind TEXT NOT NULL,
+  identifier TEXT NOT NULL,
+  label TEXT,
+  target_type TEXT NOT NULL,
+  target_id BIGINT NOT NULL,
+  in_service BOOLEAN NOT NULL DEFAULT false,
+  last_seen BIGINT,
+  PRIMARY KEY(watch_id),
+  UNIQUE(kind, identifier, target_type, target_id)
+);
@@ -0,0 +1,32 @@
+-- Add migration script here
+ALTER TABLE guilds
+ADD COLUMN gap_alerts BOOLEAN;
+
+ALTER TABLE guilds
+ADD COLUMN gap_channel BIGINT;
+
+CREATE TABLE IF NOT EXISTS service_gaps (
+  gap_id SERIAL PRIMARY KEY,
+  route TEXT NOT NULL,
+  direction SMALLINT NOT NULL,
+  toward TEXT NOT NULL,
+  station_names TEXT[] NOT NULL,
+  last_seen BIGINT NOT NULL,
+  usual_headway BIGINT NOT NULL,
+  detected_at BIGINT NOT NULL,
+  resolved_at BIGINT,
+  alert_id INT,
+  alert_headline TEXT,
+  alert_url TEXT
+);
+
+-- Only one unresolved gap per direction of a line.
+CREATE UNIQUE INDEX IF NOT EXISTS service_gaps_open_idx
+ON service_gaps(route, direction) WHERE resolved_at IS NULL;
+
+CREATE TABLE IF NOT EXISTS service_gap_messages (
+  gap_id INT NOT NULL REFERENCES service_gaps(gap_id) ON DELETE CASCADE,
+  channel_id BIGINT NOT NULL,
+  message_id BIGINT NOT NULL,
+  PRIMARY KEY(gap_id, channel_id)
+);
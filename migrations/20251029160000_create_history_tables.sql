@@ -0,0 +1,48 @@
+-- Add migration script here
+-- Both tables are partitioned by day. The recorder creates partitions ahead of time and drops
+-- them once they fall outside the retention period.
+CREATE TABLE IF NOT EXISTS train_position_history (
+  recorded_at BIGINT NOT NULL,
+  route TEXT NOT NULL,
+  run_number INT NOT NULL,
+  destination_station INT NOT NULL,
+  destination_name TEXT NOT NULL,
+  direction SMALLINT NOT NULL,
+  next_station_id INT NOT NULL,
+  next_stop_id INT NOT NULL,
+  next_station_name TEXT NOT NULL,
+  prediction_time BIGINT NOT NULL,
+  arrival_time BIGINT NOT NULL,
+  is_approaching BOOLEAN NOT NULL,
+  is_delayed BOOLEAN NOT NULL,
+  lat REAL NOT NULL,
+  lon REAL NOT NULL,
+  heading INT NOT NULL
+) PARTITION BY RANGE (recorded_at);
+
+CREATE INDEX IF NOT EXISTS train_position_history_run_idx
+ON train_position_history(route, run_number, recorded_at);
+
+CREATE INDEX IF NOT EXISTS train_position_history_station_idx
+ON train_position_history(next_station_id, recorded_at);
+
+CREATE TABLE IF NOT EXISTS bus_vehicle_history (
+  recorded_at BIGINT NOT NULL,
+  vid TEXT NOT NULL,
+  route TEXT NOT NULL,
+  pattern_id INT NOT NULL,
+  destination TEXT NOT NULL,
+  timestamp BIGINT NOT NULL,
+  lat REAL NOT NULL,
+  lon REAL NOT NULL,
+  heading INT NOT NULL,
+  pattern_distance INT,
+  is_delayed BOOLEAN,
+  tablockid TEXT NOT NULL
+) PARTITION BY RANGE (recorded_at);
+
+CREATE INDEX IF NOT EXISTS bus_vehicle_history_vehicle_idx
+ON bus_vehicle_history(vid, recorded_at);
+
+CREATE INDEX IF NOT EXISTS bus_vehicle_history_route_idx
+ON bus_vehicle_history(route, recorded_at);
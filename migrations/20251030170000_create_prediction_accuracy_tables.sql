@@ -0,0 +1,25 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS prediction_samples (
+  sample_id BIGSERIAL PRIMARY KEY,
+  route TEXT NOT NULL,
+  run_number INT NOT NULL,
+  station_id INT NOT NULL,
+  predicted_at BIGINT NOT NULL,
+  predicted_arrival BIGINT NOT NULL,
+  is_scheduled BOOLEAN NOT NULL,
+  actual_arrival BIGINT
+);
+
+CREATE INDEX IF NOT EXISTS prediction_samples_unmatched_idx
+ON prediction_samples(predicted_at) WHERE actual_arrival IS NULL;
+
+CREATE INDEX IF NOT EXISTS prediction_samples_route_idx
+ON prediction_samples(route, predicted_at);
+
+CREATE TABLE IF NOT EXISTS observed_arrivals (
+  route TEXT NOT NULL,
+  run_number INT NOT NULL,
+  station_id INT NOT NULL,
+  arrived_at BIGINT NOT NULL,
+  PRIMARY KEY(run_number, station_id, arrived_at)
+);
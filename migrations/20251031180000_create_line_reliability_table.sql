@@ -0,0 +1,13 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS line_reliability (
+  -- Start of the service day, which runs from 3 AM to 3 AM Chicago time.
+  service_day BIGINT NOT NULL,
+  route TEXT NOT NULL,
+  trips_scheduled INT NOT NULL,
+  trips_run INT NOT NULL,
+  -- Average seconds between trains at a station, and what the schedule implies for the same span.
+  avg_headway DOUBLE PRECISION,
+  scheduled_headway DOUBLE PRECISION,
+  delayed_share DOUBLE PRECISION NOT NULL,
+  PRIMARY KEY(service_day, route)
+);
@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS scheduled_trips (
+  route TEXT NOT NULL,
+  run_number INT NOT NULL,
+  scheduled_arrival BIGINT NOT NULL,
+  -- Whether the run never showed up as a tracked train.
+  ghost BOOLEAN NOT NULL,
+  PRIMARY KEY(route, run_number, scheduled_arrival)
+);
+
+CREATE INDEX IF NOT EXISTS scheduled_trips_arrival_idx ON scheduled_trips(scheduled_arrival);
@@ -0,0 +1,9 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS followed_buses (
+  message_id BIGINT NOT NULL,
+  channel_id BIGINT NOT NULL,
+  vid TEXT NOT NULL,
+  started_at BIGINT NOT NULL,
+  missed_polls INT NOT NULL DEFAULT 0,
+  PRIMARY KEY(message_id)
+);
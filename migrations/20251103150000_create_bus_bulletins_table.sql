@@ -0,0 +1,19 @@
+-- Add migration script here
+ALTER TABLE guilds
+ADD COLUMN bulletin_alerts BOOLEAN;
+
+ALTER TABLE guilds
+ADD COLUMN bulletin_channel BIGINT;
+
+ALTER TABLE guilds
+ADD COLUMN bus_routes TEXT[];
+
+CREATE TABLE IF NOT EXISTS bus_bulletins (
+  name TEXT NOT NULL,
+  subject TEXT NOT NULL,
+  routes TEXT[] NOT NULL,
+  published_to INT NOT NULL,
+  first_seen BIGINT NOT NULL,
+  last_seen BIGINT NOT NULL,
+  PRIMARY KEY(name)
+);
@@ -0,0 +1,10 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS bus_detours (
+  detour_id TEXT NOT NULL,
+  version INT NOT NULL,
+  routes TEXT[] NOT NULL,
+  published_to INT NOT NULL,
+  first_seen BIGINT NOT NULL,
+  last_seen BIGINT NOT NULL,
+  PRIMARY KEY(detour_id)
+);
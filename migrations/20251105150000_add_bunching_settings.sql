@@ -0,0 +1,6 @@
+-- Add migration script here
+ALTER TABLE guilds
+ADD COLUMN bunching_alerts BOOLEAN;
+
+ALTER TABLE guilds
+ADD COLUMN bunching_channel BIGINT;
@@ -0,0 +1,5 @@
+-- Bunching reports get their own routes instead of sharing the bulletin routes.
+ALTER TABLE guilds
+ADD COLUMN bunching_routes TEXT[];
+
+UPDATE guilds SET bunching_routes = bus_routes WHERE bunching_alerts IS NOT NULL;
@@ -0,0 +1,216 @@
+use svg::node::element::{Rectangle, Text};
+use svg::Document;
+
+use super::OFFSET;
+
+const WIDTH: usize = 1200;
+const HEADER_HEIGHT: usize = 65;
+const ROW_HEIGHT: usize = 100;
+const ROW_SPACING: usize = 105;
+const BADGE_SIZE: usize = 80;
+const TEXT_X: usize = 125;
+const ROW_COLOR: &str = "#2d2d2d";
+
+#[derive(Clone, Debug)]
+pub struct BusArrival {
+  pub route: String,
+  /// Route badge fill and text colors, as hex like "#565a5c".
+  pub route_color: String,
+  pub route_text_color: String,
+  pub direction: String,
+  pub destination_name: String,
+  pub vehicle: String,
+  pub countdown: String,
+  pub is_delayed: bool,
+  /// The bus starts its trip at this stop, so the countdown is to when it leaves rather than
+  /// when it arrives.
+  pub is_departure: bool,
+  /// How far the bus has to travel to the stop.
+  pub distance_feet: i32,
+  /// Stops the bus calls at on the way, counting this one, if its pattern is known.
+  pub stops_away: Option<usize>,
+  /// The bus is sitting at a terminal, or finishing another trip, before it starts this one. Its
+  /// countdown is then as much the schedule as how close it is.
+  pub at_terminal: bool,
+}
+
+/// Draws a bus arrival board, with a CTA style route badge on each row.
+#[allow(clippy::cast_precision_loss)]
+pub fn bus(stop_description: String, arrivals: &[BusArrival]) -> Document {
+  let canvas_height = HEADER_HEIGHT + arrivals.len() * ROW_SPACING;
+
+  let mut document = Document::new()
+    .set("viewBox", (0, 0, WIDTH, canvas_height))
+    .set("vertical-align", "top");
+  document = document
+    .add(
+      Rectangle::new()
+        .set("width", WIDTH)
+        .set("height", canvas_height)
+        .set("fill", "#1e1e1e")
+        .set("x", 0)
+        .set("y", 0),
+    )
+    .add(
+      Text::new(stop_description)
+        .set("x", 25)
+        .set("y", 30.0 + OFFSET)
+        .set("fill", "#ffffff")
+        .set("font-size", 25)
+        .set("font-weight", "bold")
+        .set("font-family", "Helvetica")
+        .set("vertical-align", "top"),
+    );
+
+  for (i, arr) in arrivals.iter().enumerate() {
+    let top = HEADER_HEIGHT + i * ROW_SPACING;
+    let badge_top = top + (ROW_HEIGHT - BADGE_SIZE) / 2;
+    document = document
+      .add(
+        Rectangle::new()
+          .set("width", WIDTH)
+          .set("height", ROW_HEIGHT)
+          .set("fill", ROW_COLOR)
+          .set("x", 0)
+          .set("y", top),
+      )
+      .add(
+        Rectangle::new()
+          .set("width", BADGE_SIZE)
+          .set("height", BADGE_SIZE)
+          .set("rx", 8)
+          .set("fill", arr.route_color.as_str())
+          .set("x", 25)
+          .set("y", badge_top),
+      )
+      .add(
+        Text::new(arr.route.clone())
+          .set("x", 25 + BADGE_SIZE / 2)
+          .set("y", (badge_top + BADGE_SIZE / 2) as f32 + 14.0)
+          .set("font-size", badge_font_size(&arr.route))
+          .set("font-weight", "bold")
+          .set("text-anchor", "middle")
+          .set("font-family", "Helvetica")
+          .set("fill", arr.route_text_color.as_str()),
+      )
+      .add(
+        Text::new(format!("{} bus #{} to", arr.direction, arr.vehicle))
+          .set("x", TEXT_X)
+          .set("y", (top + 10) as f32 + OFFSET)
+          .set("font-size", 20)
+          .set("font-family", "Helvetica")
+          .set("vertical-align", "top")
+          .set("fill", "#b0b0b0"),
+      )
+      .add(
+        Text::new(arr.destination_name.clone())
+          .set("x", TEXT_X)
+          .set("y", (top + 50) as f32 + OFFSET)
+          .set("font-size", 48)
+          .set("font-weight", "bold")
+          .set("font-family", "Helvetica")
+          .set("vertical-align", "top")
+          .set("fill", "#ffffff"),
+      )
+      .add(
+        Text::new(arr.countdown.clone())
+          .set("x", 1175)
+          .set("y", (top + 50) as f32 + OFFSET)
+          .set("font-size", 48)
+          .set("font-weight", "bold")
+          .set("text-anchor", "end")
+          .set("font-family", "Helvetica")
+          .set("vertical-align", "top")
+          .set("fill", "#ffffff"),
+      )
+      .add(
+        Text::new(progress(arr))
+          .set("x", 1175)
+          .set("y", top + 90)
+          .set("font-size", 18)
+          .set("text-anchor", "end")
+          .set("font-family", "Helvetica")
+          .set("fill", "#b0b0b0"),
+      );
+
+    document = add_badges(document, top, arr);
+  }
+  document
+}
+
+/// Adds the delay and departure badges above a row's countdown, stacked leftwards from the right
+/// edge.
+fn add_badges(mut document: Document, top: usize, arr: &BusArrival) -> Document {
+  let mut badge_right = 1175;
+  for (label, fill, text) in status_badges(arr) {
+    document = document
+      .add(
+        Rectangle::new()
+          .set("width", 110)
+          .set("height", 24)
+          .set("rx", 4)
+          .set("fill", fill)
+          .set("x", badge_right - 110)
+          .set("y", top + 6),
+      )
+      .add(
+        Text::new(label)
+          .set("x", badge_right - 55)
+          .set("y", top + 23)
+          .set("font-size", 15)
+          .set("font-weight", "bold")
+          .set("text-anchor", "middle")
+          .set("font-family", "Helvetica")
+          .set("fill", text),
+      );
+    badge_right -= 120;
+  }
+  document
+}
+
+/// Labels and colors of the badges shown on a row, rightmost first.
+fn status_badges(arr: &BusArrival) -> Vec<(&'static str, &'static str, &'static str)> {
+  let mut badges = Vec::new();
+  if arr.is_departure {
+    badges.push(("DEPARTS", "#ffffff", "#1e1e1e"));
+  }
+  if arr.is_delayed {
+    badges.push(("DELAYED", "#ffcc00", "#000000"));
+  }
+  badges
+}
+
+/// Where the bus is, like "3 stops, 4 blocks away".
+fn progress(arr: &BusArrival) -> String {
+  if arr.at_terminal {
+    return "Layover at terminal".to_string();
+  }
+  let distance = distance(arr.distance_feet);
+  match arr.stops_away {
+    Some(0) => "At the stop".to_string(),
+    Some(1) => format!("Next stop, {distance} away"),
+    Some(stops) => format!("{stops} stops, {distance} away"),
+    None => format!("{distance} away"),
+  }
+}
+
+/// Distance in Chicago blocks, eight to the mile, or in miles once it's further than that.
+#[allow(clippy::cast_precision_loss)]
+fn distance(feet: i32) -> String {
+  const FEET_PER_BLOCK: f32 = 660.0;
+  let blocks = (feet as f32 / FEET_PER_BLOCK).round().max(1.0);
+  if blocks < 8.0 {
+    format!("{blocks} block{}", if blocks > 1.0 { "s" } else { "" })
+  } else {
+    format!("{:.1} mi", feet as f32 / 5280.0)
+  }
+}
+
+/// Shrinks longer route names, like "J14" or "126", so they fit in the badge.
+fn badge_font_size(route: &str) -> usize {
+  match route.chars().count() {
+    0..=2 => 40,
+    3 => 32,
+    _ => 24,
+  }
+}
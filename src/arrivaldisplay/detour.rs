@@ -0,0 +1,195 @@
+use svg::node::element::{Circle, Path, Rectangle, Text};
+use svg::Document;
+
+use super::OFFSET;
+
+const WIDTH: f32 = 800.0;
+const HEADER_HEIGHT: f32 = 65.0;
+const MAP_HEIGHT: f32 = 600.0;
+const LEGEND_HEIGHT: f32 = 50.0;
+const MARGIN: f32 = 50.0;
+/// The map always spans at least this many degrees of latitude, about a third of a mile, so short
+/// detours aren't blown up past recognition.
+const MIN_SPAN: f64 = 0.005;
+const REGULAR_COLOR: &str = "#8a8a8a";
+const DETOUR_COLOR: &str = "#f9461c";
+const BACKGROUND_COLOR: &str = "#1e1e1e";
+
+#[derive(Clone, Debug)]
+pub struct MapStop {
+  pub name: String,
+  /// Latitude and longitude.
+  pub location: (f64, f64),
+}
+
+/// Draws regular route paths in gray with detoured paths over them, zoomed in on the detour.
+///
+/// Paths are lists of latitude and longitude pairs. The view takes in half again the detour's size
+/// on every side, so the map shows where buses leave the regular route and where they rejoin it.
+#[allow(clippy::too_many_lines)]
+pub fn detour_map(
+  title: String,
+  regular: &[Vec<(f64, f64)>],
+  detour: &[Vec<(f64, f64)>],
+  stops: &[MapStop],
+) -> Document {
+  let canvas_height = HEADER_HEIGHT + MAP_HEIGHT + LEGEND_HEIGHT;
+  let focus: Vec<(f64, f64)> = if detour.iter().all(Vec::is_empty) {
+    regular.iter().flatten().copied().collect()
+  } else {
+    detour.iter().flatten().copied().collect()
+  };
+  let project = projection(&focus);
+
+  let mut document = Document::new()
+    .set("viewBox", (0.0, 0.0, WIDTH, canvas_height))
+    .set("vertical-align", "top");
+  document = document.add(
+    Rectangle::new()
+      .set("width", WIDTH)
+      .set("height", canvas_height)
+      .set("fill", BACKGROUND_COLOR)
+      .set("x", 0)
+      .set("y", 0),
+  );
+
+  for (paths, color, width) in [(regular, REGULAR_COLOR, 6), (detour, DETOUR_COLOR, 8)] {
+    for path in paths.iter().filter(|p| p.len() > 1) {
+      document = document.add(
+        Path::new()
+          .set("d", path_data(path, &project))
+          .set("fill", "none")
+          .set("stroke", color)
+          .set("stroke-width", width)
+          .set("stroke-linecap", "round")
+          .set("stroke-linejoin", "round"),
+      );
+    }
+  }
+  for stop in stops {
+    let (x, y) = project(stop.location);
+    document = document
+      .add(
+        Circle::new()
+          .set("cx", x)
+          .set("cy", y)
+          .set("r", 7)
+          .set("fill", "#ffffff")
+          .set("stroke", DETOUR_COLOR)
+          .set("stroke-width", 4),
+      )
+      .add(
+        Text::new(stop.name.clone())
+          .set("x", x + 14.0)
+          .set("y", y + 6.0)
+          .set("fill", "#ffffff")
+          .set("font-size", 16)
+          .set("font-family", "Helvetica"),
+      );
+  }
+
+  // The header and legend are drawn last so paths running off the map are covered up.
+  document = document
+    .add(
+      Rectangle::new()
+        .set("width", WIDTH)
+        .set("height", HEADER_HEIGHT)
+        .set("fill", BACKGROUND_COLOR)
+        .set("x", 0)
+        .set("y", 0),
+    )
+    .add(
+      Text::new(title)
+        .set("x", 25)
+        .set("y", 30.0 + OFFSET)
+        .set("fill", "#ffffff")
+        .set("font-size", 25)
+        .set("font-weight", "bold")
+        .set("font-family", "Helvetica")
+        .set("vertical-align", "top"),
+    )
+    .add(
+      Rectangle::new()
+        .set("width", WIDTH)
+        .set("height", LEGEND_HEIGHT)
+        .set("fill", BACKGROUND_COLOR)
+        .set("x", 0)
+        .set("y", HEADER_HEIGHT + MAP_HEIGHT),
+    );
+  let legend_y = HEADER_HEIGHT + MAP_HEIGHT + LEGEND_HEIGHT / 2.0;
+  for (x, color, label) in [
+    (25.0, REGULAR_COLOR, "Regular route"),
+    (225.0, DETOUR_COLOR, "Detour"),
+  ] {
+    document = document
+      .add(
+        Path::new()
+          .set("d", format!("M{x} {legend_y} h40"))
+          .set("stroke", color)
+          .set("stroke-width", 6)
+          .set("stroke-linecap", "round"),
+      )
+      .add(
+        Text::new(label)
+          .set("x", x + 55.0)
+          .set("y", legend_y + 6.0)
+          .set("fill", "#b0b0b0")
+          .set("font-size", 18)
+          .set("font-family", "Helvetica"),
+      );
+  }
+  document
+}
+
+/// Maps latitude and longitude onto the canvas so that `focus`, padded out, fills the map area.
+/// Longitude is scaled down by the cosine of the latitude so streets keep their shape.
+#[allow(clippy::cast_possible_truncation)]
+fn projection(focus: &[(f64, f64)]) -> impl Fn((f64, f64)) -> (f32, f32) {
+  let (mut min_lat, mut max_lat, mut min_lon, mut max_lon) = focus.iter().fold(
+    (f64::MAX, f64::MIN, f64::MAX, f64::MIN),
+    |(min_lat, max_lat, min_lon, max_lon), (lat, lon)| {
+      (
+        min_lat.min(*lat),
+        max_lat.max(*lat),
+        min_lon.min(*lon),
+        max_lon.max(*lon),
+      )
+    },
+  );
+  if focus.is_empty() {
+    // Somewhere to look at, even with nothing to draw.
+    (min_lat, max_lat, min_lon, max_lon) = (41.88, 41.88, -87.63, -87.63);
+  }
+  let middle = (
+    f64::midpoint(min_lat, max_lat),
+    f64::midpoint(min_lon, max_lon),
+  );
+  let x_scale = middle.0.to_radians().cos();
+  let span_y = ((max_lat - min_lat) * 2.0).max(MIN_SPAN);
+  let span_x = ((max_lon - min_lon) * x_scale * 2.0).max(MIN_SPAN);
+  let map_width = f64::from(WIDTH - 2.0 * MARGIN);
+  let map_height = f64::from(MAP_HEIGHT - 2.0 * MARGIN);
+  let scale = (map_width / span_x).min(map_height / span_y);
+  let center = (
+    f64::from(WIDTH) / 2.0,
+    f64::from(HEADER_HEIGHT + MAP_HEIGHT / 2.0),
+  );
+  move |(lat, lon)| {
+    (
+      (center.0 + (lon - middle.1) * x_scale * scale) as f32,
+      (center.1 - (lat - middle.0) * scale) as f32,
+    )
+  }
+}
+
+fn path_data(path: &[(f64, f64)], project: &impl Fn((f64, f64)) -> (f32, f32)) -> String {
+  path
+    .iter()
+    .enumerate()
+    .map(|(i, point)| {
+      let (x, y) = project(*point);
+      format!("{}{x:.1} {y:.1}", if i == 0 { "M" } else { "L" })
+    })
+    .collect::<Vec<_>>()
+    .join(" ")
+}
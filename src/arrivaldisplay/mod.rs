@@ -9,8 +9,18 @@ use resvg::render;
 
 use crate::cta::traintracker::LRouteName;
 
+mod bus;
+mod detour;
+mod reliability;
+mod strip;
+pub use bus::{bus, BusArrival};
+pub use detour::{detour_map, MapStop};
+pub use reliability::reliability_chart;
+pub use strip::{strip_map, StripStop};
+
 const OFFSET: f32 = 17.101_563;
 
+#[allow(clippy::struct_excessive_bools)]
 #[derive(Clone, Debug)]
 pub struct Arrival {
   pub destination_name: String,
@@ -18,6 +28,11 @@ pub struct Arrival {
   pub train_number: i32,
   pub countdown: String,
   pub is_scheduled: bool,
+  /// Scheduled arrival on a line and hour where scheduled trips often don't run.
+  pub is_likely_ghost: bool,
+  pub is_delayed: bool,
+  /// `TrainTracker` flagged a possible fault with this prediction.
+  pub is_faulted: bool,
 }
 
 #[allow(clippy::cast_precision_loss)]
@@ -49,6 +64,8 @@ pub fn train(stop_description: String, arrivals: &[Arrival]) -> Document {
     .add(Path::new()
       .set("d", "M12.8,18C12.8,12.1,8,7.3,2.1,7.2l0,0c-0.3,0-0.6-0.1-0.7-0.4l0,0c-0.2-0.4,0-0.8,0.3-1l0,0c0,0-0.1,0,0.3-0.1l0,0c6.7,0,12.2,5.4,12.2,12.2l0,0c0,0.4-0.3,0.7-0.7,0.7l0,0C13.1,18.7,12.8,18.4,12.8,18L12.8,18z")));
 
+  document = document.add(ghost_symbol());
+
   document = document.add(
     Rectangle::new()
       .set("width", 1200)
@@ -68,16 +85,7 @@ pub fn train(stop_description: String, arrivals: &[Arrival]) -> Document {
       .set("vertical-align", "top"),
   );
   for (i, arr) in arrivals.iter().enumerate() {
-    let line_color = match arr.route {
-      LRouteName::Red => "#c60c30",
-      LRouteName::P => "#522398",
-      LRouteName::Y => "#f9e300",
-      LRouteName::Blue => "#00a1de",
-      LRouteName::Pink => "#e27ea6",
-      LRouteName::G => "#009b3a",
-      LRouteName::Org => "#f9461c",
-      LRouteName::Brn => "#62361b",
-    };
+    let line_color = arr.route.color();
     let inverse = ["Cottage Grove", "UIC-Halsted"].contains(&arr.destination_name.clone().as_str());
     let black_text = arr.route.eq(&LRouteName::Y);
     let bg = if inverse { "#ffffff" } else { line_color };
@@ -94,6 +102,7 @@ pub fn train(stop_description: String, arrivals: &[Arrival]) -> Document {
         .set("width", 1200)
         .set("height", 100)
         .set("fill", bg)
+        .set("fill-opacity", if arr.is_likely_ghost { 0.5 } else { 1.0 })
         .set("x", 0)
         .set("y", 65 + (i * 105)),
     );
@@ -111,17 +120,13 @@ pub fn train(stop_description: String, arrivals: &[Arrival]) -> Document {
     );
 
     document = document.add(
-      Text::new(format!(
-        "{} #{:0>3} to",
-        to_variant_name(&arr.route).unwrap(),
-        arr.train_number.to_string()
-      ))
-      .set("x", 25)
-      .set("y", 75.0 + ((i as f32) * 105.0) + OFFSET)
-      .set("font-size", 20)
-      .set("font-family", "Helvetica")
-      .set("vertical-align", "top")
-      .set("fill", fg),
+      Text::new(run_label(arr))
+        .set("x", 25)
+        .set("y", 75.0 + ((i as f32) * 105.0) + OFFSET)
+        .set("font-size", 20)
+        .set("font-family", "Helvetica")
+        .set("vertical-align", "top")
+        .set("fill", fg),
     );
 
     document = document.add(
@@ -136,16 +141,13 @@ pub fn train(stop_description: String, arrivals: &[Arrival]) -> Document {
         .set("fill", fg),
     );
 
+    if let Some(badge) = status_badge(arr) {
+      document = draw_status_badge(document, badge, i);
+    }
+
     document = document.add(
       Use::new()
-        .set(
-          "href",
-          if arr.is_scheduled {
-            "#clockSymbol"
-          } else {
-            "#trackingSymbol"
-          },
-        )
+        .set("href", arrival_symbol(arr))
         .set("width", 25)
         .set("height", 25)
         .set("fill", fg)
@@ -156,6 +158,81 @@ pub fn train(stop_description: String, arrivals: &[Arrival]) -> Document {
   document
 }
 
+/// A ghost, marking scheduled arrivals that often don't run.
+fn ghost_symbol() -> Symbol {
+  Symbol::new()
+    .set("viewBox", (0, 0, 20, 20))
+    .set("id", "ghostSymbol")
+    .add(Path::new()
+      .set("fill-rule", "evenodd")
+      .set("d", "M10,1.5c-4.1,0 -7,3.1 -7,7.2v9.8l2.3,-1.8l2.3,1.8l2.4,-1.8l2.4,1.8l2.3,-1.8l2.3,1.8v-9.8c0,-4.1 -2.9,-7.2 -7,-7.2zm-4.3,6.5a1.3,1.3 0 1,0 2.6,0a1.3,1.3 0 1,0 -2.6,0zm6,0a1.3,1.3 0 1,0 2.6,0a1.3,1.3 0 1,0 -2.6,0z"))
+}
+
+/// The line above the destination, like "Red #804 to".
+fn run_label(arr: &Arrival) -> String {
+  format!(
+    "{} #{:0>3}{} to",
+    to_variant_name(&arr.route).unwrap(),
+    arr.train_number,
+    if arr.is_likely_ghost {
+      " (may not run)"
+    } else {
+      ""
+    }
+  )
+}
+
+/// Which symbol goes next to the countdown: tracked, scheduled, or scheduled but likely a ghost.
+fn arrival_symbol(arr: &Arrival) -> &'static str {
+  if arr.is_likely_ghost {
+    "#ghostSymbol"
+  } else if arr.is_scheduled {
+    "#clockSymbol"
+  } else {
+    "#trackingSymbol"
+  }
+}
+
+/// Draws a badge from [`status_badge`] in the corner of row `row`.
+fn draw_status_badge(
+  document: Document,
+  (label, badge_fill, badge_text): (&str, &str, &str),
+  row: usize,
+) -> Document {
+  document
+    .add(
+      Rectangle::new()
+        .set("width", 110)
+        .set("height", 24)
+        .set("rx", 4)
+        .set("fill", badge_fill)
+        .set("x", 1065)
+        .set("y", 71 + (row * 105)),
+    )
+    .add(
+      Text::new(label)
+        .set("x", 1120)
+        .set("y", 88 + (row * 105))
+        .set("font-size", 15)
+        .set("font-weight", "bold")
+        .set("text-anchor", "middle")
+        .set("font-family", "Helvetica")
+        .set("fill", badge_text),
+    )
+}
+
+/// Label and colors of the badge marking a delayed or faulted arrival. Faults take precedence,
+/// since the countdown itself may be wrong.
+fn status_badge(arr: &Arrival) -> Option<(&'static str, &'static str, &'static str)> {
+  if arr.is_faulted {
+    Some(("FAULT", "#1e1e1e", "#ffffff"))
+  } else if arr.is_delayed {
+    Some(("DELAYED", "#ffcc00", "#000000"))
+  } else {
+    None
+  }
+}
+
 // pub fn m() {
 //   let doc = &train("Upcomining Arrivals at Halsted (Orange Line)".to_string(),
 //     Vec::<Arrival>::from([Arrival{
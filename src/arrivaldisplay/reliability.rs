@@ -0,0 +1,134 @@
+use svg::node::element::{Rectangle, Text};
+use svg::Document;
+
+use crate::cta::analysis::reliability::LineScore;
+use crate::cta::traintracker::{LRouteCode, LRouteName};
+
+use super::OFFSET;
+
+const WIDTH: f32 = 1200.0;
+const HEADER_HEIGHT: f32 = 110.0;
+const ROW_HEIGHT: f32 = 70.0;
+const NAME_X: f32 = 25.0;
+/// Left edges of the three bar columns: trips run, headway and delays.
+const COLUMNS: [f32; 3] = [300.0, 600.0, 900.0];
+const BAR_WIDTH: f32 = 270.0;
+const BAR_HEIGHT: f32 = 22.0;
+const TRACK_COLOR: &str = "#3a3a3a";
+
+/// Draws a scoreboard with one row per line, comparing service run against the schedule.
+///
+/// Bars show trips run out of trips scheduled, how the average headway compares with the
+/// scheduled one (the bar is full at twice the scheduled headway), and the share of trains
+/// flagged as delayed.
+#[allow(
+  clippy::cast_precision_loss,
+  clippy::cast_possible_truncation,
+  clippy::too_many_lines
+)]
+pub fn reliability_chart(title: String, scores: &[LineScore]) -> Document {
+  let canvas_height = HEADER_HEIGHT + scores.len() as f32 * ROW_HEIGHT + 15.0;
+  let mut document = Document::new()
+    .set("viewBox", (0.0, 0.0, WIDTH, canvas_height))
+    .set("vertical-align", "top");
+
+  document = document.add(
+    Rectangle::new()
+      .set("width", WIDTH)
+      .set("height", canvas_height)
+      .set("fill", "#1e1e1e")
+      .set("x", 0)
+      .set("y", 0),
+  );
+  document = document.add(
+    Text::new(title)
+      .set("x", NAME_X)
+      .set("y", 30.0 + OFFSET)
+      .set("fill", "#ffffff")
+      .set("font-size", 25)
+      .set("font-weight", "bold")
+      .set("font-family", "Helvetica")
+      .set("vertical-align", "top"),
+  );
+  for (x, label) in COLUMNS
+    .iter()
+    .zip(["Trips run", "Time between trains", "Trains delayed"])
+  {
+    document = document.add(
+      Text::new(label)
+        .set("x", *x)
+        .set("y", 95)
+        .set("fill", "#b0b0b0")
+        .set("font-size", 20)
+        .set("font-family", "Helvetica"),
+    );
+  }
+
+  for (i, score) in scores.iter().enumerate() {
+    let y = HEADER_HEIGHT + i as f32 * ROW_HEIGHT;
+    let line = score.route.parse::<LRouteCode>().ok().map(LRouteName::from);
+    let color = line.as_ref().map_or("#ffffff", LRouteName::color);
+    document = document
+      .add(
+        Rectangle::new()
+          .set("x", NAME_X)
+          .set("y", y + 15.0)
+          .set("width", 10)
+          .set("height", 40)
+          .set("fill", color),
+      )
+      .add(
+        Text::new(line.map_or_else(|| score.route.clone(), |l| l.to_string()))
+          .set("x", NAME_X + 25.0)
+          .set("y", y + 44.0)
+          .set("fill", "#ffffff")
+          .set("font-size", 26)
+          .set("font-weight", "bold")
+          .set("font-family", "Helvetica"),
+      );
+
+    let bars = [
+      score
+        .trips_run_share
+        .map(|share| (share as f32, format!("{:.0}% of scheduled", share * 100.0))),
+      score
+        .headway_ratio
+        .map(|ratio| (ratio as f32 / 2.0, format!("{ratio:.2}x scheduled"))),
+      Some((
+        score.delayed_share as f32,
+        format!("{:.1}%", score.delayed_share * 100.0),
+      )),
+    ];
+    for (x, bar) in COLUMNS.iter().zip(bars) {
+      document = document.add(
+        Rectangle::new()
+          .set("x", *x)
+          .set("y", y + 12.0)
+          .set("width", BAR_WIDTH)
+          .set("height", BAR_HEIGHT)
+          .set("rx", 4)
+          .set("fill", TRACK_COLOR),
+      );
+      let (fill, label) = bar.unwrap_or((0.0, "No schedule".to_string()));
+      document = document
+        .add(
+          Rectangle::new()
+            .set("x", *x)
+            .set("y", y + 12.0)
+            .set("width", BAR_WIDTH * fill.clamp(0.0, 1.0))
+            .set("height", BAR_HEIGHT)
+            .set("rx", 4)
+            .set("fill", color),
+        )
+        .add(
+          Text::new(label)
+            .set("x", *x)
+            .set("y", y + 58.0)
+            .set("fill", "#ffffff")
+            .set("font-size", 18)
+            .set("font-family", "Helvetica"),
+        );
+    }
+  }
+  document
+}
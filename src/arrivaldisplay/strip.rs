@@ -0,0 +1,144 @@
+use svg::node::element::{Circle, Path, Rectangle, Text};
+use svg::Document;
+
+use crate::cta::traintracker::LRouteName;
+
+use super::OFFSET;
+
+const WIDTH: f32 = 800.0;
+const HEADER_HEIGHT: f32 = 65.0;
+const ROW_HEIGHT: f32 = 50.0;
+const LINE_X: f32 = 60.0;
+const PASSED_COLOR: &str = "#5a5a5a";
+
+#[derive(Clone, Debug)]
+pub struct StripStop {
+  pub name: String,
+  /// Countdown text shown to the right of the stop, if the train still has to reach it.
+  pub eta: Option<String>,
+  pub passed: bool,
+}
+
+/// Draws a vertical strip map of `stops` in travel order, with the train drawn at `train_position`.
+///
+/// `train_position` is measured in stops: `1.5` is halfway between the second and third stop, and
+/// negative values place the train before the first stop on the map.
+#[allow(clippy::cast_precision_loss)]
+pub fn strip_map(
+  title: String,
+  route: &LRouteName,
+  stops: &[StripStop],
+  train_position: Option<f32>,
+) -> Document {
+  let line_color = route.color();
+  let first_row = train_position.unwrap_or(0.0).min(0.0);
+  let row_y = |row: f32| HEADER_HEIGHT + (row - first_row) * ROW_HEIGHT + ROW_HEIGHT / 2.0;
+  let canvas_height = row_y(stops.len() as f32 - 1.0) + ROW_HEIGHT / 2.0 + 15.0;
+
+  let mut document = Document::new()
+    .set("viewBox", (0.0, 0.0, WIDTH, canvas_height))
+    .set("vertical-align", "top");
+
+  document = document.add(
+    Rectangle::new()
+      .set("width", WIDTH)
+      .set("height", canvas_height)
+      .set("fill", "#1e1e1e")
+      .set("x", 0)
+      .set("y", 0),
+  );
+  document = document.add(
+    Text::new(title)
+      .set("x", 25)
+      .set("y", 30.0 + OFFSET)
+      .set("fill", "#ffffff")
+      .set("font-size", 25)
+      .set("font-weight", "bold")
+      .set("font-family", "Helvetica")
+      .set("vertical-align", "top"),
+  );
+
+  // Track already covered by the train is greyed out, the rest is drawn in the line color.
+  let split = train_position
+    .unwrap_or(first_row)
+    .min(stops.len() as f32 - 1.0)
+    .max(first_row);
+  document = document
+    .add(track(row_y(first_row), row_y(split), PASSED_COLOR))
+    .add(track(
+      row_y(split),
+      row_y(stops.len() as f32 - 1.0),
+      line_color,
+    ));
+
+  for (i, stop) in stops.iter().enumerate() {
+    let y = row_y(i as f32);
+    let (stroke, text_fill) = if stop.passed {
+      (PASSED_COLOR, "#8a8a8a")
+    } else {
+      (line_color, "#ffffff")
+    };
+    document = document.add(
+      Circle::new()
+        .set("cx", LINE_X)
+        .set("cy", y)
+        .set("r", 11)
+        .set("fill", if stop.passed { "#8a8a8a" } else { "#ffffff" })
+        .set("stroke", stroke)
+        .set("stroke-width", 4),
+    );
+    document = document.add(
+      Text::new(stop.name.clone())
+        .set("x", LINE_X + 35.0)
+        .set("y", y + 8.0)
+        .set("font-size", 24)
+        .set("font-family", "Helvetica")
+        .set("fill", text_fill),
+    );
+    if let Some(eta) = &stop.eta {
+      document = document.add(
+        Text::new(eta.clone())
+          .set("x", WIDTH - 30.0)
+          .set("y", y + 8.0)
+          .set("font-size", 24)
+          .set("font-weight", "bold")
+          .set("text-anchor", "end")
+          .set("font-family", "Helvetica")
+          .set("fill", text_fill),
+      );
+    }
+  }
+
+  if let Some(position) = train_position {
+    let y = row_y(position);
+    document = document.add(
+      Path::new()
+        .set(
+          "d",
+          format!(
+            "M{},{} L{},{} L{},{} Z",
+            LINE_X - 16.0,
+            y - 12.0,
+            LINE_X + 16.0,
+            y - 12.0,
+            LINE_X,
+            y + 16.0
+          ),
+        )
+        .set("fill", "#ffffff")
+        .set("stroke", line_color)
+        .set("stroke-width", 4)
+        .set("stroke-linejoin", "round"),
+    );
+  }
+  document
+}
+
+fn track(from_y: f32, to_y: f32, color: &str) -> Rectangle {
+  Rectangle::new()
+    .set("x", LINE_X - 6.0)
+    .set("y", from_y)
+    .set("width", 12)
+    .set("height", (to_y - from_y).max(0.0))
+    .set("fill", color)
+}
@@ -0,0 +1,145 @@
+use crate::cta::analysis::accuracy::{lead_bucket_label, LEAD_BUCKETS};
+use crate::cta::traintracker::{LRouteCode, LRouteName};
+use crate::{db, CTAShared};
+use serenity::all::{
+  Context, CreateCommandOption, CreateEmbed, CreateEmbedFooter, CreateInteractionResponseMessage,
+  InteractionResponseFlags,
+};
+use serenity::builder::CreateCommand;
+use serenity::model::application::{ResolvedOption, ResolvedValue};
+
+const PERIOD_DAYS: i64 = 7;
+/// Stations need this many matched predictions before they're ranked.
+const MIN_STATION_SAMPLES: i64 = 30;
+
+pub async fn run<'a>(
+  ctx: &Context,
+  options: &'a [ResolvedOption<'a>],
+) -> CreateInteractionResponseMessage {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  let Some(ResolvedOption {
+    value: ResolvedValue::String(val),
+    ..
+  }) = options.first()
+  else {
+    return CreateInteractionResponseMessage::new().content("Options not provided.".to_string());
+  };
+  let Ok(route) = val.parse::<LRouteCode>() else {
+    return CreateInteractionResponseMessage::new()
+      .content(format!("Unknown line: {val}"))
+      .flags(InteractionResponseFlags::EPHEMERAL);
+  };
+  let line = LRouteName::from(route);
+  let since = chrono::Utc::now().timestamp() - PERIOD_DAYS * 24 * 60 * 60;
+
+  let buckets = match db::get_lead_accuracy(&data.db, &route.to_string(), since, &LEAD_BUCKETS)
+    .await
+  {
+    Ok(buckets) if buckets.is_empty() => {
+      return CreateInteractionResponseMessage::new().content(format!(
+        "There isn't enough data on the {line} yet. Check back once the bot has been running for a while."
+      ));
+    }
+    Ok(buckets) => buckets,
+    Err(e) => {
+      println!("Error reading prediction accuracy: {e}");
+      return CreateInteractionResponseMessage::new()
+        .content("Error reading prediction accuracy. Please try again later.".to_string())
+        .flags(InteractionResponseFlags::EPHEMERAL);
+    }
+  };
+  let stations =
+    db::get_least_accurate_stations(&data.db, &route.to_string(), since, MIN_STATION_SAMPLES, 5)
+      .await
+      .inspect_err(|e| println!("Error reading station accuracy: {e}"))
+      .unwrap_or_default();
+
+  // Judge by the countdowns riders lean on most: trains a few minutes out.
+  let verdict = match buckets
+    .iter()
+    .find(|b| b.lead_minutes >= 2)
+    .or(buckets.first())
+  {
+    Some(b) if b.within_minute >= 0.8 => {
+      "Countdowns are reliable. Most are right to within a minute."
+    }
+    Some(b) if b.within_minute >= 0.6 => {
+      "Countdowns are usually close, but leave an extra minute or two."
+    }
+    _ => "Treat countdowns as rough estimates.",
+  };
+
+  let mut embed = CreateEmbed::new()
+    .title(format!("How accurate are {line} countdowns?"))
+    .color(line.color_value())
+    .description(verdict)
+    .footer(CreateEmbedFooter::new(format!(
+      "Based on arrival predictions from the last {PERIOD_DAYS} days"
+    )));
+  for bucket in &buckets {
+    embed = embed.field(
+      lead_bucket_label(bucket.lead_minutes),
+      format!(
+        "Typically off by {}\n{:.0}% within a minute\nTrains come {} on average\n{} predictions",
+        duration_label(bucket.median_abs_error),
+        bucket.within_minute * 100.0,
+        if bucket.mean_error >= 0.0 {
+          format!("{} late", duration_label(bucket.mean_error))
+        } else {
+          format!("{} early", duration_label(-bucket.mean_error))
+        },
+        bucket.samples
+      ),
+      true,
+    );
+  }
+  if !stations.is_empty() {
+    embed = embed.field(
+      "Least accurate stations",
+      stations
+        .iter()
+        .map(|s| {
+          format!(
+            "{}: off by {} ({} predictions)",
+            data
+              .stations
+              .get_stop_name(s.station_id)
+              .unwrap_or_else(|| s.station_id.to_string()),
+            duration_label(s.median_abs_error),
+            s.samples
+          )
+        })
+        .collect::<Vec<_>>()
+        .join("\n"),
+      false,
+    );
+  }
+  CreateInteractionResponseMessage::new().add_embed(embed)
+}
+
+fn duration_label(secs: f64) -> String {
+  if secs < 90.0 {
+    format!("{secs:.0} sec")
+  } else {
+    format!("{:.1} min", secs / 60.0)
+  }
+}
+
+pub fn register() -> CreateCommand {
+  let line_option = LRouteCode::ALL.iter().fold(
+    CreateCommandOption::new(
+      serenity::all::CommandOptionType::String,
+      "line",
+      "Rail line",
+    )
+    .required(true),
+    |option, code| option.add_string_choice(LRouteName::from(*code).to_string(), code.to_string()),
+  );
+  CreateCommand::new("accuracy")
+    .add_option(line_option)
+    .add_integration_type(serenity::all::InstallationContext::Guild)
+    .add_integration_type(serenity::all::InstallationContext::User)
+    .description("Shows how accurate arrival countdowns have been on a line.")
+}
@@ -1,91 +1,92 @@
 use chrono::NaiveTime;
 use gtfs_structures::RouteType;
-use serenity::all::{
-  Context, CreateEmbed, CreateInteractionResponseMessage,
-};
+use serenity::all::{Context, CreateInteractionResponseMessage};
 use serenity::builder::CreateCommand;
 use serenity::model::application::ResolvedOption;
 
+use crate::commands::pagination::{self, Listing};
 use crate::cta::alerts::{AlertsError, AlertsOptions};
-use crate::{cta, CTAShared};
+use crate::{cta, CTAShared, CTASharedData};
 
 pub async fn run<'a>(
   ctx: &Context,
-  options: &'a [ResolvedOption<'a>],
+  _options: &'a [ResolvedOption<'a>],
 ) -> CreateInteractionResponseMessage {
   let data = ctx.data.read().await;
   let data = data.get::<CTAShared>().expect("no shared data");
+  pagination::respond(data, "alerts", "").await
+}
 
-  let alerts = get_alerts(ctx).await;
-  match alerts {
-    Ok(alerts_list) => {
-      let alerts_texts: Vec<String> = alerts_list
-        .iter()
-        .map(|a| {
-          let start_time: i64 = match a.event_start {
-            cta::alerts::DateOrDateTime::DateTime(naive_date_time) => naive_date_time
-              .and_local_timezone(chrono_tz::America::Chicago)
-              .earliest()
-              .unwrap()
-              .timestamp(),
-            cta::alerts::DateOrDateTime::Date(naive_date) => naive_date
-              .and_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap())
-              .and_local_timezone(chrono_tz::America::Chicago)
-              .earliest()
-              .unwrap()
-              .timestamp(),
-          };
-          let end_time: Option<i64> = match a.event_end {
-            Some(cta::alerts::DateOrDateTime::DateTime(naive_date_time)) => Some(
-              naive_date_time
-                .and_local_timezone(chrono_tz::America::Chicago)
-                .earliest()
-                .unwrap()
-                .timestamp(),
-            ),
-            Some(cta::alerts::DateOrDateTime::Date(naive_date)) => Some(
-              naive_date
-                .and_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap())
-                .and_local_timezone(chrono_tz::America::Chicago)
-                .earliest()
-                .unwrap()
-                .timestamp(),
-            ),
-            None => None,
-          };
-          // match a.event_end {
-          //   Some(dateTime) => {
-          //     Some(dateTime
-          //         .and_local_timezone(chrono_tz::America::Chicago)
-          //         .earliest()
-          //         .unwrap()
-          //         .timestamp_millis())
-          //   },
-          //   None => None
-          // };
-          let end_fmt = format!("<t:{}:f>", end_time.clone().unwrap_or(0));
-          format!(
-            "**{}**\n(<t:{}:f> - {})\n{}",
-            a.headline,
-            start_time,
-            if a.tbd { "TBD" } else { &end_fmt },
-            a.short_description
-          )
-        })
-        .collect();
-      CreateInteractionResponseMessage::new().add_embed(
-        CreateEmbed::new()
-          .title(format!("Active CTA Alerts"))
-          .description(alerts_texts.join("\n\n")),
+/// Active rail alerts, for [`pagination`].
+pub async fn alerts_listing(data: &CTASharedData) -> Result<Listing, String> {
+  let alerts_list = match get_alerts(data).await {
+    Ok(alerts_list) => alerts_list,
+    Err(AlertsError::NoAlerts) => Vec::new(),
+    Err(e) => return Err(format!("Error getting alerts: {e}")),
+  };
+  let alerts_texts: Vec<String> = alerts_list
+    .iter()
+    .map(|a| {
+      let start_time: i64 = match a.event_start {
+        cta::alerts::DateOrDateTime::DateTime(naive_date_time) => naive_date_time
+          .and_local_timezone(chrono_tz::America::Chicago)
+          .earliest()
+          .unwrap()
+          .timestamp(),
+        cta::alerts::DateOrDateTime::Date(naive_date) => naive_date
+          .and_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap())
+          .and_local_timezone(chrono_tz::America::Chicago)
+          .earliest()
+          .unwrap()
+          .timestamp(),
+      };
+      let end_time: Option<i64> = match a.event_end {
+        Some(cta::alerts::DateOrDateTime::DateTime(naive_date_time)) => Some(
+          naive_date_time
+            .and_local_timezone(chrono_tz::America::Chicago)
+            .earliest()
+            .unwrap()
+            .timestamp(),
+        ),
+        Some(cta::alerts::DateOrDateTime::Date(naive_date)) => Some(
+          naive_date
+            .and_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap())
+            .and_local_timezone(chrono_tz::America::Chicago)
+            .earliest()
+            .unwrap()
+            .timestamp(),
+        ),
+        None => None,
+      };
+      // match a.event_end {
+      //   Some(dateTime) => {
+      //     Some(dateTime
+      //         .and_local_timezone(chrono_tz::America::Chicago)
+      //         .earliest()
+      //         .unwrap()
+      //         .timestamp_millis())
+      //   },
+      //   None => None
+      // };
+      let end_fmt = format!("<t:{}:f>", end_time.clone().unwrap_or(0));
+      format!(
+        "**{}**\n(<t:{}:f> - {})\n{}",
+        a.headline,
+        start_time,
+        if a.tbd { "TBD" } else { &end_fmt },
+        a.short_description
       )
-    }
-    Err(AlertsError::NoAlerts) => {
-      CreateInteractionResponseMessage::new().content(format!("No Current Alerts").to_string())
-    }
-    Err(e) => CreateInteractionResponseMessage::new()
-      .content(format!("Error getting alerts: {e}").to_string())
-      .ephemeral(true),
-  }
+    })
+    .collect();
+  Ok(Listing {
+    title: "Active CTA Alerts".to_string(),
+    items: alerts_texts,
+    separator: "\n\n",
+    footer: None,
+    color: None,
+    empty: "No Current Alerts".to_string(),
+    sorts: &[],
+  })
 }
 
 pub fn register() -> CreateCommand {
@@ -95,9 +96,7 @@ pub fn register() -> CreateCommand {
     .add_integration_type(serenity::all::InstallationContext::Guild)
 }
 
-async fn get_alerts(ctx: &Context) -> Result<Vec<cta::alerts::Alert>, AlertsError> {
-  let data = ctx.data.read().await;
-  let data = data.get::<CTAShared>().expect("no shared data");
+async fn get_alerts(data: &CTASharedData) -> Result<Vec<cta::alerts::Alert>, AlertsError> {
   let route_ids: Vec<String> = data
     .gtfs
     .gtfs_data
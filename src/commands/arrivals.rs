@@ -1,7 +1,9 @@
 use crate::arrivaldisplay::{self, Arrival, ArrivalDisplayError};
+use crate::cta::analysis::ghosts::GhostRates;
 use crate::cta::traintracker::ArrivalsParameters;
-use crate::{cta, util, CTAShared};
+use crate::{cta, db, util, CTAShared};
 
+use chrono::Timelike;
 use gtfs_structures::Stop;
 use serenity::all::{
   ComponentInteraction, ComponentInteractionDataKind, Context, CreateAttachment, CreateButton,
@@ -11,6 +13,9 @@ use serenity::all::{
 use serenity::builder::CreateCommand;
 use serenity::model::application::{ResolvedOption, ResolvedValue};
 
+/// How far back ghost rates are looked up when marking likely ghosts.
+const GHOST_LOOKBACK_SECS: i64 = 14 * 24 * 60 * 60;
+
 pub async fn run<'a>(
   ctx: &Context,
   options: &'a [ResolvedOption<'a>],
@@ -138,9 +143,26 @@ async fn arrivals_command(ctx: &Context, search: &str) -> CreateInteractionRespo
     .await;
   match predictions {
     Ok(prds) => {
+      let ghost_rates = if prds.iter().any(|prd| prd.is_scheduled) {
+        db::get_ghost_rates(
+          &data.db,
+          chrono::Utc::now().timestamp() - GHOST_LOOKBACK_SECS,
+        )
+        .await
+        .map(|rows| GhostRates::new(&rows))
+        .inspect_err(|e| println!("Error reading ghost rates: {e}"))
+        .unwrap_or_default()
+      } else {
+        GhostRates::default()
+      };
       let arrivals: Vec<Arrival> = prds
         .into_iter()
         .map(|prd| Arrival {
+          is_likely_ghost: prd.is_scheduled
+            && ghost_rates.likely_ghost(
+              prd.route,
+              i32::try_from(prd.arrival_time.hour()).unwrap_or_default(),
+            ),
           destination_name: prd.destination_name,
           route: prd.route.into(),
           countdown: util::countdown(util::minutes_until(
@@ -150,6 +172,8 @@ async fn arrivals_command(ctx: &Context, search: &str) -> CreateInteractionRespo
               .unwrap(),
           )),
           is_scheduled: prd.is_scheduled,
+          is_delayed: prd.is_delayed,
+          is_faulted: prd.is_faulted,
           train_number: prd.run_number,
         })
         .collect();
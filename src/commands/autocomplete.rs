@@ -6,114 +6,33 @@ use serenity::all::{
   Interaction, ResolvedOption, ResolvedValue,
 };
 
-use crate::CTAShared;
+use gtfs_structures::Route;
+
+use crate::cta::gtfs::route_color_emoji;
+use crate::{util, CTAShared};
 
 pub async fn handle(ctx: &Context, interaction: &CommandInteraction) -> CreateInteractionResponse {
-  match &interaction.data {
-    CommandData {
-      name: command_name,
-      kind: CommandType::ChatInput,
-      options: opts,
-      ..
-    } if command_name == "arrivals" => {
-      if let Some(CommandDataOption {
-        value:
-          CommandDataOptionValue::Autocomplete {
-            kind: CommandOptionType::String,
-            value: search_string,
-          },
-        ..
-      }) = opts.first()
-      {
-        return CreateInteractionResponse::Autocomplete(
-          CreateAutocompleteResponse::new().set_choices(
-            search_stations(ctx, search_string)
-              .await
-              .iter()
-              .map(|res| AutocompleteChoice::new(res, res.clone()))
-              .collect(),
-          ),
-        );
-      }
-      // return CreateInteractionResponse::Autocomplete(stations(ctx, interaction).await);
-    }
-    CommandData {
-      name: command_name,
-      kind: CommandType::ChatInput,
-      options: opts,
-      ..
-    } if command_name == "bus" => {
-      if let Some(CommandDataOption {
-        value: CommandDataOptionValue::SubCommand(sub_data),
-        ..
-      }) = opts.first()
-      {
-        if let Some(CommandDataOption {
-          name: opt_name,
-          value:
-            CommandDataOptionValue::Autocomplete {
-              kind: CommandOptionType::String,
-              value: search_string,
-            },
-          ..
-        }) = sub_data.first()
-        {
-          if opt_name.as_str() == "stop_name" {
-            return CreateInteractionResponse::Autocomplete(
-              CreateAutocompleteResponse::new().set_choices(
-                search_bus_stops(ctx, search_string)
-                  .await
-                  .iter()
-                  .map(|res| AutocompleteChoice::new(res, res.clone()))
-                  .collect(),
-              ),
-            );
-          }
-        }
-      }
-      return CreateInteractionResponse::Autocomplete(CreateAutocompleteResponse::new());
-    }
-    CommandData {
-      name: command_name,
-      kind: CommandType::ChatInput,
-      options: opts,
-      ..
-    } if command_name == "alerts" => {
-      if let Some(CommandDataOption {
-        value: CommandDataOptionValue::SubCommand(sub_data),
-        ..
-      }) = opts.first()
-      {
-        if let Some(CommandDataOption {
-          name: opt_name,
-          value:
-            CommandDataOptionValue::Autocomplete {
-              kind: CommandOptionType::String,
-              value: search_string,
-            },
-          ..
-        }) = sub_data.first()
-        {
-          if opt_name.as_str() == "route_ids" {
-            return CreateInteractionResponse::Autocomplete(
-              CreateAutocompleteResponse::new().set_choices(
-                search_route_ids(ctx, search_string)
-                  .await
-                  .iter()
-                  .map(|res| AutocompleteChoice::new(res, res.clone()))
-                  .collect(),
-              ),
-            );
-          }
-        }
-      }
-      return CreateInteractionResponse::Autocomplete(CreateAutocompleteResponse::new());
-    }
+  let CommandData {
+    name: command_name,
+    kind: CommandType::ChatInput,
+    options: opts,
+    ..
+  } = &interaction.data
+  else {
+    println!("Unknown autocomplete command: {}", interaction.data.name);
+    return CreateInteractionResponse::Autocomplete(CreateAutocompleteResponse::new());
+  };
+  let response = match command_name.as_str() {
+    "arrivals" => arrivals_choices(ctx, opts).await,
+    "traveltime" => traveltime_choices(ctx, opts).await,
+    "bus" => bus_choices(ctx, opts).await,
+    "alerts" => alerts_choices(ctx, opts).await,
     _ => {
-      println!("Unknown autocomplete command: {}", interaction.data.name);
+      println!("Unknown autocomplete command: {command_name}");
+      CreateAutocompleteResponse::new()
     }
   };
-  CreateInteractionResponse::Autocomplete(CreateAutocompleteResponse::new())
+  CreateInteractionResponse::Autocomplete(response)
   // // dbg!(interaction.data.name.as_str());
   //   if interaction.data.name.as_str() == "arrivals" {
   //     // dbg!(interaction);
@@ -138,6 +57,110 @@ pub async fn handle(ctx: &Context, interaction: &CommandInteraction) -> CreateIn
   //   }
 }
 
+/// Choices shown as they'll be filled in.
+fn choices(values: &[String]) -> CreateAutocompleteResponse {
+  CreateAutocompleteResponse::new().set_choices(
+    values
+      .iter()
+      .map(|res| AutocompleteChoice::new(res, res.clone()))
+      .collect(),
+  )
+}
+
+async fn arrivals_choices(ctx: &Context, opts: &[CommandDataOption]) -> CreateAutocompleteResponse {
+  if let Some(CommandDataOption {
+    value:
+      CommandDataOptionValue::Autocomplete {
+        kind: CommandOptionType::String,
+        value: search_string,
+      },
+    ..
+  }) = opts.first()
+  {
+    return choices(&search_stations(ctx, search_string).await);
+  }
+  CreateAutocompleteResponse::new()
+}
+
+async fn traveltime_choices(
+  ctx: &Context,
+  opts: &[CommandDataOption],
+) -> CreateAutocompleteResponse {
+  // Only the option being typed in is sent as an autocomplete value.
+  if let Some(search_string) = opts.iter().find_map(|opt| match &opt.value {
+    CommandDataOptionValue::Autocomplete {
+      kind: CommandOptionType::String,
+      value,
+    } => Some(value),
+    _ => None,
+  }) {
+    return choices(&search_stations(ctx, search_string).await);
+  }
+  CreateAutocompleteResponse::new()
+}
+
+async fn bus_choices(ctx: &Context, opts: &[CommandDataOption]) -> CreateAutocompleteResponse {
+  let Some(CommandDataOption {
+    name: sub_name,
+    value: CommandDataOptionValue::SubCommand(sub_data),
+    ..
+  }) = opts.first()
+  else {
+    return CreateAutocompleteResponse::new();
+  };
+  let Some(CommandDataOption {
+    name: opt_name,
+    value:
+      CommandDataOptionValue::Autocomplete {
+        kind: CommandOptionType::String,
+        value: search_string,
+      },
+    ..
+  }) = sub_data
+    .iter()
+    .find(|opt| matches!(opt.value, CommandDataOptionValue::Autocomplete { .. }))
+  else {
+    return CreateAutocompleteResponse::new();
+  };
+  if opt_name == "route" && sub_name == "vehicles" {
+    return CreateAutocompleteResponse::new().set_choices(
+      search_bus_routes(ctx, search_string)
+        .await
+        .into_iter()
+        .map(|(name, value)| AutocompleteChoice::new(name, value))
+        .collect(),
+    );
+  }
+  if matches!(opt_name.as_str(), "stop_name" | "stop") {
+    return choices(&search_bus_stops(ctx, search_string).await);
+  }
+  CreateAutocompleteResponse::new()
+}
+
+async fn alerts_choices(ctx: &Context, opts: &[CommandDataOption]) -> CreateAutocompleteResponse {
+  if let Some(CommandDataOption {
+    value: CommandDataOptionValue::SubCommand(sub_data),
+    ..
+  }) = opts.first()
+  {
+    if let Some(CommandDataOption {
+      name: opt_name,
+      value:
+        CommandDataOptionValue::Autocomplete {
+          kind: CommandOptionType::String,
+          value: search_string,
+        },
+      ..
+    }) = sub_data.first()
+    {
+      if opt_name.as_str() == "route_ids" {
+        return choices(&search_route_ids(ctx, search_string).await);
+      }
+    }
+  }
+  CreateAutocompleteResponse::new()
+}
+
 async fn search_stations(ctx: &Context, search: &str) -> Vec<String> {
   let data = ctx.data.read().await;
   let data = data.get::<CTAShared>().expect("no shared data");
@@ -201,6 +224,74 @@ async fn search_bus_stops(ctx: &Context, search: &str) -> Vec<String> {
     .collect()
 }
 
+/// Suggests bus routes for the last entry in a comma separated list, keeping the routes before it.
+/// Each suggestion is shown with the route's name and color, and fills in the whole list.
+async fn search_bus_routes(ctx: &Context, search: &str) -> Vec<(String, String)> {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+  let matcher = SkimMatcherV2::default();
+
+  let (picked, current) = match search.rsplit_once(',') {
+    Some((picked, current)) => (picked, current.trim()),
+    None => ("", search.trim()),
+  };
+  let picked: Vec<&str> = picked
+    .split(',')
+    .map(str::trim)
+    .filter(|route| !route.is_empty())
+    .collect();
+
+  let mut scored_suggestions: Vec<(&Route, i64)> = data
+    .gtfs
+    .bus_routes()
+    .into_iter()
+    .filter(|route| !picked.iter().any(|p| p.eq_ignore_ascii_case(&route.id)))
+    .filter_map(|route| {
+      if current.is_empty() {
+        return Some((route, 0));
+      }
+      let name = route.long_name.as_deref().unwrap_or_default();
+      // Typing the route number exactly should always put that route first.
+      let score = if route.id.eq_ignore_ascii_case(current) {
+        i64::MAX
+      } else {
+        matcher
+          .fuzzy_match(&format!("{} {name}", route.id), current)
+          .unwrap_or_default()
+      };
+      (score != 0).then_some((route, score))
+    })
+    .collect();
+  // Stable, so ties stay in route number order.
+  scored_suggestions.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+  scored_suggestions
+    .into_iter()
+    .take(25)
+    .map(|(route, _)| {
+      let value = picked
+        .iter()
+        .copied()
+        .chain([route.id.as_str()])
+        .collect::<Vec<_>>()
+        .join(",");
+      let name = format!(
+        "{} {} {}",
+        route_color_emoji(route),
+        route.id,
+        route.long_name.as_deref().unwrap_or_default()
+      );
+      let name = if picked.is_empty() {
+        name
+      } else {
+        format!("{}, {name}", picked.join(", "))
+      };
+      // Discord cuts off choices at 100 characters.
+      (util::truncate(&name, 100), value)
+    })
+    .filter(|(_, value)| value.len() <= 100)
+    .collect()
+}
+
 async fn search_route_ids(ctx: &Context, search: &str) -> Vec<String> {
   let data = ctx.data.read().await;
   let data = data.get::<CTAShared>().expect("no shared data");
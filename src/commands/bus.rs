@@ -1,16 +1,45 @@
+use futures::future::join_all;
 use serenity::all::{
-  ComponentInteraction, ComponentInteractionDataKind, Context, CreateButton, CreateCommandOption,
-  CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
-  CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, InteractionResponseFlags,
-  ResolvedValue,
+  ButtonStyle, ComponentInteraction, ComponentInteractionDataKind, Context, CreateActionRow,
+  CreateAttachment, CreateButton, CreateCommandOption, CreateEmbed, CreateEmbedFooter,
+  CreateInteractionResponse, CreateInteractionResponseMessage, CreateSelectMenu,
+  CreateSelectMenuKind, CreateSelectMenuOption, InteractionResponseFlags, ResolvedValue,
 };
 use serenity::builder::CreateCommand;
 use serenity::model::application::ResolvedOption;
 use std::collections::HashMap;
-use std::fmt::Write;
 
-use crate::cta::bustracker::{BusTracker, PredictionsParameters, VehiclesParameters, VidOrRt};
-use crate::{cta, CTAShared};
+use crate::arrivaldisplay::{self, BusArrival, MapStop};
+use crate::commands::get_train::FollowStatus;
+use crate::commands::pagination::{self, Listing};
+use crate::cta::analysis::bunching::{self, Bunch, PatternSpacing};
+use crate::cta::analysis::garages;
+use crate::cta::bustracker::{
+  BusTracker, BusTrackerError, Detour, DetoursParameters, Pattern, PatternPointType,
+  PatternsParameters, PidOrRt, Prediction, PredictionType, PredictionsParameters, ServiceBulletin,
+  ServiceBulletinsParameters, StpidOrVid, Vehicle, VehiclesParameters, VidOrRt,
+};
+use crate::cta::gtfs::bus_stop_direction;
+use crate::db::{self, DBFollowedBus};
+use crate::{cta, util, CTAShared, CTASharedData};
+use gtfs_structures::Stop;
+
+/// Most stops picked at once from the stop select menu.
+const MAX_SELECTED_STOPS: usize = 4;
+/// Bus Tracker takes at most this many vehicle, pattern or route IDs per request.
+pub const MAX_REQUEST_IDS: usize = 10;
+/// Most arrivals drawn on a single bus stop board.
+const MAX_BOARD_ARRIVALS: usize = 8;
+/// Most upcoming stops listed for a single bus.
+const MAX_UPCOMING_STOPS: usize = 10;
+/// Bus Tracker only takes this many stop IDs per bulletin request.
+const MAX_BULLETIN_STOPS: usize = 10;
+/// Discord allows at most this many fields on an embed.
+const MAX_FIELDS: usize = 25;
+/// Discord allows at most this many embeds on a message.
+const MAX_EMBEDS: usize = 10;
+/// How far back recorded history is searched for buses that ran other routes.
+const ROUTE_HISTORY_SECS: i64 = 12 * 60 * 60;
 
 pub async fn run<'a>(
   ctx: &Context,
@@ -19,8 +48,6 @@ pub async fn run<'a>(
   let data = ctx.data.read().await;
   let data = data.get::<CTAShared>().expect("no shared data");
 
-  let bt = &data.bustracker;
-  let gtfs = &data.gtfs;
   if let Some(
     option @ ResolvedOption {
       value: ResolvedValue::SubCommand(opts),
@@ -29,68 +56,36 @@ pub async fn run<'a>(
   ) = options.first()
   {
     match option.name {
-      "vehicles" => {
-        let first_option = opts.first().expect("no first option");
-        if first_option.name.eq("route") {
-          if let ResolvedOption {
-            value: ResolvedValue::String(val),
-            ..
-          } = first_option
-          {
-            let route_codes: Vec<String> = (*val)
-              .split(',')
-              .map(std::string::ToString::to_string)
-              .collect();
-            let vehicles = (bt)
-              .get_vehicles(VehiclesParameters {
-                search: VidOrRt::Rt {
-                  route_codes: route_codes.clone(),
-                },
-              })
-              .await;
-            match vehicles {
-              Ok(veh) => {
-                let mut msg: String = veh.into_iter().fold(String::new(), |mut acc, vehicle| {
-                  writeln!(
-                    acc,
-                    "Bus {} Route {} ({}) to {}. {}",
-                    vehicle.vid,
-                    vehicle.rt,
-                    gtfs.get_route_name(&vehicle.rt),
-                    vehicle.des,
-                    String::from(cta::bustracker::BusTracker::tablockid_to_garage(
-                      &vehicle.tablockid
-                    ))
-                  )
-                  .unwrap();
-                  acc
-                });
-                let mut truncated = false;
-                if msg.len() > 4096 {
-                  msg = msg.split_at(4096).0.to_string();
-                  truncated = true;
-                }
-                return CreateInteractionResponseMessage::new().add_embed(
-                  CreateEmbed::new()
-                    .description(msg)
-                    .title(format!("Routes: {}", route_codes.join(", ")))
-                    .footer(if truncated {
-                      CreateEmbedFooter::new("This response has been truncated.".to_string())
-                    } else {
-                      CreateEmbedFooter::new(String::new())
-                    }),
-                );
-              }
-              Err(err) => {
-                return CreateInteractionResponseMessage::new()
-                  .content(format!("Error getting vehicles: {err}"))
-                  .flags(InteractionResponseFlags::EPHEMERAL);
-              }
-            }
-          }
+      "vehicles" => return vehicles_command(data, opts).await,
+      "vehicle" => {
+        if let Some(ResolvedOption {
+          value: ResolvedValue::String(vid),
+          ..
+        }) = opts.first()
+        {
+          return vehicle_command(data, vid.trim()).await;
+        }
+      }
+      "bulletins" => return bulletins_command(data, opts).await,
+      "detours" => {
+        if let Some(ResolvedOption {
+          value: ResolvedValue::String(route),
+          ..
+        }) = opts.first()
+        {
+          return detours_command(data, route.trim()).await;
         }
-        if opts.first().expect("no first option").name.eq("buses") {}
       }
+      "bunching" => {
+        if let Some(ResolvedOption {
+          value: ResolvedValue::String(route),
+          ..
+        }) = opts.first()
+        {
+          return bunching_command(data, route.trim()).await;
+        }
+      }
+      "garages" => return garages_command(data, opts).await,
       "arrivals" => {
         let first_option = opts.first().expect("no first option");
         if first_option.name.eq("stop_name") {
@@ -111,14 +106,172 @@ pub async fn run<'a>(
     .flags(InteractionResponseFlags::EPHEMERAL)
 }
 
+async fn vehicles_command(
+  data: &CTASharedData,
+  opts: &[ResolvedOption<'_>],
+) -> CreateInteractionResponseMessage {
+  let delayed_only = opts
+    .iter()
+    .any(|o| o.name == "delayed_only" && matches!(o.value, ResolvedValue::Boolean(true)));
+  let Some(input) = opts.iter().find_map(|o| match o.value {
+    ResolvedValue::String(route) if o.name == "route" => Some(route),
+    _ => None,
+  }) else {
+    return CreateInteractionResponseMessage::new()
+      .content("Pick at least one route.".to_string())
+      .flags(InteractionResponseFlags::EPHEMERAL);
+  };
+  let route_codes = match valid_routes(data, input).await {
+    Ok(routes) => routes,
+    Err(message) => {
+      return CreateInteractionResponseMessage::new()
+        .content(message)
+        .flags(InteractionResponseFlags::EPHEMERAL);
+    }
+  };
+  let args = format!("{}/{}", u8::from(delayed_only), route_codes.join(","));
+  pagination::respond(data, "bus_vehicles", &args).await
+}
+
+/// Buses on some routes, for [`pagination`]. `args` is "1/20,49" for only the delayed buses on
+/// Routes 20 and 49, or "0/20,49" for all of them.
+pub async fn vehicles_listing(
+  data: &CTASharedData,
+  args: &str,
+  sort: &str,
+) -> Result<Listing, String> {
+  let (delayed_only, routes) = args.split_once('/').unwrap_or(("0", args));
+  let delayed_only = delayed_only == "1";
+  let route_codes: Vec<String> = routes.split(',').map(ToString::to_string).collect();
+  let mut vehicles: Vec<Vehicle> = data
+    .bustracker
+    .get_vehicles(VehiclesParameters {
+      search: VidOrRt::Rt {
+        route_codes: route_codes.clone(),
+      },
+    })
+    .await
+    .map_err(|err| format!("Error getting vehicles: {err}"))?
+    .into_iter()
+    .filter(|vehicle| !delayed_only || vehicle.dly == Some(true))
+    .collect();
+  let vid_order = |v: &Vehicle| (v.vid.parse::<u32>().unwrap_or(u32::MAX), v.vid.clone());
+  match sort {
+    "destination" => vehicles.sort_by_key(|v| (v.des.clone(), vid_order(v))),
+    "garage" => {
+      vehicles.sort_by_key(|v| (BusTracker::tablockid_to_garage(&v.tablockid), vid_order(v)));
+    }
+    _ => vehicles.sort_by_key(vid_order),
+  }
+
+  let items = vehicles
+    .iter()
+    .map(|vehicle| {
+      format!(
+        "Bus {}{} Route {}{} to {}. {}{}",
+        vehicle.vid,
+        data
+          .roster
+          .lookup(&vehicle.vid)
+          .map(|series| format!(" ({})", series.description()))
+          .unwrap_or_default(),
+        vehicle.rt,
+        data
+          .gtfs
+          .get_route_name(&vehicle.rt)
+          .map(|name| format!(" ({name})"))
+          .unwrap_or_default(),
+        vehicle.des,
+        String::from(BusTracker::tablockid_to_garage(&vehicle.tablockid)),
+        if vehicle.dly == Some(true) {
+          " :warning: Delayed"
+        } else {
+          ""
+        }
+      )
+    })
+    .collect();
+  Ok(Listing {
+    title: if delayed_only {
+      format!("Delayed buses on routes: {}", route_codes.join(", "))
+    } else {
+      format!("Routes: {}", route_codes.join(", "))
+    },
+    items,
+    separator: "\n",
+    footer: None,
+    color: None,
+    empty: if delayed_only {
+      format!("No buses are delayed on routes {}.", route_codes.join(", "))
+    } else {
+      format!(
+        "No buses are running on routes {} right now.",
+        route_codes.join(", ")
+      )
+    },
+    sorts: &[
+      ("vehicle", "Bus number"),
+      ("destination", "Destination"),
+      ("garage", "Garage"),
+    ],
+  })
+}
+
+/// Splits a comma separated list of routes and checks each one against Bus Tracker's routes, or
+/// the GTFS bus routes if Bus Tracker can't be reached. Gives a line for each bad route otherwise.
+async fn valid_routes(data: &CTASharedData, input: &str) -> Result<Vec<String>, String> {
+  let mut routes: Vec<String> = Vec::new();
+  for route in input.split(',').map(|route| route.trim().to_uppercase()) {
+    if !route.is_empty() && !routes.contains(&route) {
+      routes.push(route);
+    }
+  }
+  if routes.is_empty() {
+    return Err("Pick at least one route.".to_string());
+  }
+  if routes.len() > MAX_REQUEST_IDS {
+    return Err(format!(
+      "Only {MAX_REQUEST_IDS} routes can be looked up at once."
+    ));
+  }
+  let known: Vec<String> = match data.bustracker.get_routes().await {
+    Ok(found) => found.into_iter().map(|r| r.rt).collect(),
+    Err(e) => {
+      println!("Error getting bus routes, checking against GTFS instead: {e}");
+      data
+        .gtfs
+        .bus_routes()
+        .into_iter()
+        .map(|r| r.id.clone())
+        .collect()
+    }
+  };
+  let unknown: Vec<String> = routes
+    .iter()
+    .filter(|route| !known.contains(route))
+    .map(|route| format!("'{route}' isn't a CTA bus route."))
+    .collect();
+  if unknown.is_empty() {
+    Ok(routes)
+  } else {
+    Err(unknown.join("\n"))
+  }
+}
+
 pub async fn arrivals_select(
   ctx: &Context,
   component: &ComponentInteraction,
 ) -> CreateInteractionResponse {
   if let ComponentInteractionDataKind::StringSelect { values } = &component.data.kind {
-    CreateInteractionResponse::UpdateMessage(
-      arrivals_command(ctx, values.first().unwrap_or(&String::new()).as_str()).await,
-    )
+    // Menus posted before options carried stop IDs offer stop names to search for again.
+    if !component.data.custom_id.contains("/stops") {
+      return CreateInteractionResponse::UpdateMessage(
+        arrivals_command(ctx, values.first().map_or("", String::as_str)).await,
+      );
+    }
+    let data = ctx.data.read().await;
+    let data = data.get::<CTAShared>().expect("no shared data");
+    CreateInteractionResponse::UpdateMessage(stop_arrivals(data, values).await)
   } else {
     CreateInteractionResponse::Message(
       CreateInteractionResponseMessage::new()
@@ -149,128 +302,1124 @@ pub async fn arrivals_refresh(
         .content("Please wait at least 30 seconds before refreshing."),
     );
   }
-  let stop_name = component
-    .data
-    .custom_id
-    .split_once("/stopName/")
-    .unwrap_or(("", ""))
-    .1;
-  CreateInteractionResponse::UpdateMessage(arrivals_command(ctx, stop_name).await)
+  let custom_id = component.data.custom_id.as_str();
+  // Boards posted before buttons carried stop IDs refresh by searching the name again.
+  if let Some((_, stop_name)) = custom_id.split_once("/stopName/") {
+    return CreateInteractionResponse::UpdateMessage(arrivals_command(ctx, stop_name).await);
+  }
+  let stop_ids: Vec<String> = custom_id
+    .split_once("/stops/")
+    .map(|(_, ids)| ids.split(',').map(ToString::to_string).collect())
+    .unwrap_or_default();
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+  CreateInteractionResponse::UpdateMessage(stop_arrivals(data, &stop_ids).await)
 }
+
 async fn arrivals_command(ctx: &Context, search: &str) -> CreateInteractionResponseMessage {
   let data = ctx.data.read().await;
   let data = data.get::<CTAShared>().expect("no shared data");
 
-  let bt = &data.bustracker;
-  let gtfs = &data.gtfs;
-
-  let found_stop_ids: Vec<String> = gtfs.search_stops(search).unwrap_or_default();
-  let mut stops: HashMap<String, Vec<String>> = HashMap::new();
-  for stp in found_stop_ids {
-    let stop = gtfs
-      .gtfs_data
-      .get_stop(stp.as_str())
-      .expect("Search result is invalid.");
-    if str::parse::<i32>(&stop.id).expect("Couldn't parse Stop ID as i32.") > 30000 {
-      continue;
-    }
-    let name = stop.name.as_deref().unwrap_or_default();
-    let orig_val = stops.get(name);
-    if orig_val.is_some() {
-      let mut new_val = orig_val.expect("Somehow orig_val is none.").to_owned();
-      new_val.push(stp);
-      stops.insert(name.to_string(), new_val.clone());
-    } else {
-      let new_val: Vec<String> = vec![stp];
-      // new_val.push(stp);
-      stops.insert(name.to_string(), new_val);
+  let mut stops = data.gtfs.search_bus_stops(search);
+  match stops.len() {
+    0 => CreateInteractionResponseMessage::new()
+      .content("No stops found for that search, please try again.")
+      .ephemeral(true),
+    1 => stop_arrivals(data, &[stops[0].id.clone()]).await,
+    2..=25 => {
+      stops.sort_by_key(|stop| {
+        (
+          stop.name.clone(),
+          bus_stop_direction(stop),
+          stop.id.parse::<i32>().unwrap_or_default(),
+        )
+      });
+      let select_menu_options: Vec<CreateSelectMenuOption> = stops
+        .iter()
+        .map(|stop| CreateSelectMenuOption::new(stop_label(stop), stop.id.clone()))
+        .collect();
+      CreateInteractionResponseMessage::new()
+        .content("Multiple stops found for that query. Please select one or more.")
+        .select_menu(
+          CreateSelectMenu::new(
+            "bus_arrivals:select/stops",
+            CreateSelectMenuKind::String {
+              options: select_menu_options,
+            },
+          )
+          .min_values(1)
+          .max_values(u8::try_from(stops.len().min(MAX_SELECTED_STOPS)).unwrap_or(1)),
+        )
     }
+    _ => CreateInteractionResponseMessage::new()
+      .content("Too many results found for that stop name. Please narrow your search.".to_string())
+      .flags(InteractionResponseFlags::EPHEMERAL),
   }
-  if stops.keys().len() > 25 {
+}
+
+/// Arrival boards for each of `stop_ids`, with a refresh button that remembers them.
+async fn stop_arrivals(
+  data: &CTASharedData,
+  stop_ids: &[String],
+) -> CreateInteractionResponseMessage {
+  let stops = data.gtfs.get_bus_stops(stop_ids);
+  if stops.is_empty() {
     return CreateInteractionResponseMessage::new()
-      .content("Too many results found for that stop name. Please narrow your search.".to_string())
+      .content("That stop is no longer in the schedule. Please search for it again.")
       .flags(InteractionResponseFlags::EPHEMERAL);
-  } else if stops.keys().len() > 2 {
-    let select_menu_options: Vec<CreateSelectMenuOption> = stops
-      .keys()
-      .map(|stop_name| CreateSelectMenuOption::new(stop_name, stop_name))
-      .collect();
-    return CreateInteractionResponseMessage::new()
-      .content("Multiple stops found for that query. Please select one")
-      .select_menu(
-        CreateSelectMenu::new(
-          "bus_arrivals:select",
-          CreateSelectMenuKind::String {
-            options: select_menu_options,
-          },
-        )
-        .min_values(1)
-        .max_values(1),
-      );
-  } else if stops.is_empty() {
-    return CreateInteractionResponseMessage::new()
-      .content("No stops found for that search, please try again.")
-      .ephemeral(true);
   }
-  let stop_ids: Vec<String> = stops.values().flat_map(|v| v.iter().cloned()).collect();
-  let predictions = bt
+  let stop_ids: Vec<String> = stops.iter().map(|stop| stop.id.clone()).collect();
+  let predictions = data
+    .bustracker
     .get_predictions(PredictionsParameters {
       search: cta::bustracker::StpidOrVid::StpId {
-        stpid: stop_ids,
+        stpid: stop_ids.clone(),
         rt: None,
       },
     })
     .await;
   match predictions {
     Ok(prds) => {
-      let response = CreateInteractionResponseMessage::new();
-      return stops.keys().fold(response, |response, stop_name| {
-        let mut desc = String::new();
-        prds
-          .clone()
-          .into_iter()
-          .filter(|f| f.stpnm.eq(stop_name))
-          .for_each(|prd| {
-            let predicted_time = match BusTracker::parse_bustime(prd.prdtm.as_str()) {
-              Ok(time) => time.timestamp(),
-              Err(e) => {
-                println!("Error getting time from API: {e}");
-                chrono::Utc::now().timestamp()
-              }
-            };
-            desc.push_str(
-              format!(
-                "Route {} {} to {} <t:{}:R> (Bus #{})\n",
-                prd.rt, prd.rtdir, prd.des, predicted_time, prd.vid
-              )
-              .as_str(),
-            );
-          });
-        response
-          .add_embed(
-            CreateEmbed::new()
-              .title(format!("Upcoming Arrivals for {stop_name}"))
-              .description(desc),
-          )
-          .content(format!(
-            "Arrival Board Generated <t:{}:R>",
-            chrono::Local::now().timestamp()
-          ))
-          .components(Vec::new())
-          .button(
-            CreateButton::new(format!("bus_arrivals:refresh/stopName/{stop_name}"))
-              .style(serenity::all::ButtonStyle::Primary)
-              .label("Refresh"),
+      let response = CreateInteractionResponseMessage::new()
+        .content(format!(
+          "Arrival Board Generated <t:{}:R>",
+          chrono::Local::now().timestamp()
+        ))
+        .components(Vec::new())
+        .button(
+          CreateButton::new(format!("bus_arrivals:refresh/stops/{}", stop_ids.join(",")))
+            .style(serenity::all::ButtonStyle::Primary)
+            .label("Refresh"),
+        );
+      let boards: Vec<(&Stop, Vec<&Prediction>)> = stops
+        .iter()
+        .map(|stop| {
+          (
+            *stop,
+            prds
+              .iter()
+              .filter(|prd| stop.id.parse() == Ok(prd.stpid))
+              .take(MAX_BOARD_ARRIVALS)
+              .collect(),
           )
-      });
+        })
+        .collect();
+      let shown: Vec<&Prediction> = boards.iter().flat_map(|(_, prds)| prds.clone()).collect();
+      let (vehicles, patterns) = bus_positions(data, &shown).await;
+      boards
+        .iter()
+        .enumerate()
+        .fold(response, |response, (i, (stop, prds))| {
+          let arrivals: Vec<BusArrival> = prds
+            .iter()
+            .map(|prd| bus_arrival(data, prd, vehicles.get(&prd.vid), &patterns))
+            .collect();
+          add_arrival_board(response, i, &stop_title(stop), &arrivals)
+        })
     }
     Err(e) => CreateInteractionResponseMessage::new()
       .content(format!("Error getting arrivals: {e}"))
       .flags(InteractionResponseFlags::EPHEMERAL),
   }
 }
+
+/// A stop's name and the way buses are heading, like "Clark & Belmont (Southbound)".
+fn stop_title(stop: &Stop) -> String {
+  let name = stop.name.as_deref().unwrap_or(&stop.id);
+  match bus_stop_direction(stop) {
+    Some(direction) => format!("{name} ({direction})"),
+    None => name.to_string(),
+  }
+}
+
+/// Tells apart stops sharing a name, like "Clark & Belmont (Southbound, stop 1234)".
+fn stop_label(stop: &Stop) -> String {
+  let name = stop.name.as_deref().unwrap_or_default();
+  match bus_stop_direction(stop) {
+    Some(direction) => format!("{name} ({direction}, stop {})", stop.id),
+    None => format!("{name} (stop {})", stop.id),
+  }
+}
+
+/// Renders a stop's arrival board and attaches it in its own embed.
+fn add_arrival_board(
+  response: CreateInteractionResponseMessage,
+  index: usize,
+  stop_name: &str,
+  arrivals: &[BusArrival],
+) -> CreateInteractionResponseMessage {
+  let filename = format!("arrivals-{index}.png");
+  let board = arrivaldisplay::bus(format!("Upcoming Arrivals for {stop_name}"), arrivals);
+  let embed = CreateEmbed::new().title(format!("Arrivals for {stop_name}"));
+  match arrivaldisplay::render_doc(&board) {
+    Ok(png) => response
+      .add_embed(embed.image(format!("attachment://{filename}")))
+      .add_file(CreateAttachment::bytes(png, filename)),
+    Err(e) => {
+      println!("Error creating bus arrival board: {e}");
+      response.add_embed(embed.description("Error creating Arrival Board. Please try again later."))
+    }
+  }
+}
+
+fn prediction_timestamp(prd: &Prediction) -> i64 {
+  match BusTracker::parse_bustime(prd.prdtm.as_str()) {
+    Ok(time) => time.timestamp(),
+    Err(e) => {
+      println!("Error getting time from API: {e}");
+      chrono::Utc::now().timestamp()
+    }
+  }
+}
+
+/// The buses behind `predictions`, and the patterns they're running, to tell how far along their
+/// trips they are. Buses that can't be looked up are left out.
+async fn bus_positions(
+  data: &CTASharedData,
+  predictions: &[&Prediction],
+) -> (HashMap<String, Vehicle>, HashMap<i32, Pattern>) {
+  let mut vids: Vec<String> = predictions.iter().map(|prd| prd.vid.clone()).collect();
+  vids.sort();
+  vids.dedup();
+  let mut vehicles = HashMap::new();
+  for chunk in vids.chunks(MAX_REQUEST_IDS) {
+    match data
+      .bustracker
+      .get_vehicles(VehiclesParameters {
+        search: VidOrRt::Vid {
+          vehicle_ids: chunk.to_vec(),
+        },
+      })
+      .await
+    {
+      Ok(found) => vehicles.extend(found.into_iter().map(|v| (v.vid.clone(), v))),
+      Err(e) => println!("Error getting buses for arrivals: {e}"),
+    }
+  }
+  let patterns = patterns_by_id(data, vehicles.values().map(|v| v.pid).collect()).await;
+  (vehicles, patterns)
+}
+
+/// Looks up patterns by ID. Patterns that can't be fetched are left out.
+pub async fn patterns_by_id(data: &CTASharedData, mut pids: Vec<i32>) -> HashMap<i32, Pattern> {
+  pids.sort_unstable();
+  pids.dedup();
+  let mut patterns = HashMap::new();
+  for chunk in pids.chunks(MAX_REQUEST_IDS) {
+    match data
+      .bustracker
+      .get_patterns(PatternsParameters {
+        search: PidOrRt::Pid {
+          pid: chunk.to_vec(),
+        },
+      })
+      .await
+    {
+      Ok(found) => patterns.extend(found.into_iter().map(|p| (p.pid, p))),
+      Err(e) => println!("Error getting patterns: {e}"),
+    }
+  }
+  patterns
+}
+
+/// Positions of every bus on `routes`, along with the routes that couldn't be fetched.
+pub async fn route_vehicles(
+  data: &CTASharedData,
+  routes: &[String],
+) -> (Vec<Vehicle>, Vec<String>) {
+  // Every route in the system takes over a dozen requests, so they're sent all at once.
+  let requests = routes.chunks(MAX_REQUEST_IDS).map(|chunk| async move {
+    let result = data
+      .bustracker
+      .get_vehicles(VehiclesParameters {
+        search: VidOrRt::Rt {
+          route_codes: chunk.to_vec(),
+        },
+      })
+      .await;
+    (chunk, result)
+  });
+  let mut vehicles = Vec::new();
+  let mut failed = Vec::new();
+  for (chunk, result) in join_all(requests).await {
+    match result {
+      Ok(found) => vehicles.extend(found),
+      Err(e) => {
+        println!("Error getting buses on routes {}: {e}", chunk.join(","));
+        failed.extend_from_slice(chunk);
+      }
+    }
+  }
+  (vehicles, failed)
+}
+
+fn bus_arrival(
+  data: &CTASharedData,
+  prd: &Prediction,
+  vehicle: Option<&Vehicle>,
+  patterns: &HashMap<i32, Pattern>,
+) -> BusArrival {
+  // Routes missing from GTFS get the gray CTA uses for most bus routes.
+  let (route_color, route_text_color) = data.gtfs.gtfs_data.get_route(&prd.rt).map_or_else(
+    |_| ("#565a5c".to_string(), "#ffffff".to_string()),
+    |r| {
+      (
+        format!("#{:02x}{:02x}{:02x}", r.color.r, r.color.g, r.color.b),
+        format!(
+          "#{:02x}{:02x}{:02x}",
+          r.text_color.r, r.text_color.g, r.text_color.b
+        ),
+      )
+    },
+  );
+  // A bus on an earlier trip than the predicted one will turn around, and maybe lay over, before
+  // it gets here. One that hasn't moved along its pattern is waiting at the start of it.
+  let at_terminal = vehicle.is_some_and(|v| {
+    v.tatripid
+      .as_ref()
+      .is_some_and(|trip| *trip != prd.tatripid)
+      || v.pdist == Some(0)
+  });
+  BusArrival {
+    route: prd.rt.clone(),
+    route_color,
+    route_text_color,
+    direction: prd.rtdir.clone(),
+    destination_name: prd.des.clone(),
+    vehicle: prd.vid.clone(),
+    countdown: prediction_countdown(prd),
+    is_delayed: prd.dly == Some(true),
+    is_departure: matches!(prd.typ, PredictionType::D),
+    distance_feet: prd.dstp,
+    stops_away: vehicle.filter(|_| !at_terminal).and_then(|v| {
+      patterns
+        .get(&v.pid)?
+        .stops_until(f64::from(v.pdist?), prd.stpid)
+    }),
+    at_terminal,
+  }
+}
+
+/// Bus Tracker's own countdown is minutes, "DUE", or "DLY" when the bus is delayed, in which case
+/// the predicted time is counted down from instead.
+fn prediction_countdown(prd: &Prediction) -> String {
+  match prd.prdctdn.as_str() {
+    "DUE" => "Due".to_string(),
+    countdown => countdown.parse().map_or_else(
+      |_| {
+        util::countdown(
+          i32::try_from((prediction_timestamp(prd) - chrono::Utc::now().timestamp()) / 60)
+            .unwrap_or_default(),
+        )
+      },
+      |mins: i32| format!("{mins} min"),
+    ),
+  }
+}
+
+async fn vehicle_command(data: &CTASharedData, vid: &str) -> CreateInteractionResponseMessage {
+  match vehicle_embed(data, vid).await {
+    Ok(Some(embed)) => CreateInteractionResponseMessage::new().embed(embed).button(
+      CreateButton::new(format!("bus:follow/vid/{vid}"))
+        .style(ButtonStyle::Primary)
+        .label("Follow"),
+    ),
+    Ok(None) => CreateInteractionResponseMessage::new()
+      .content(format!("Bus #{vid} isn't in service right now."))
+      .flags(InteractionResponseFlags::EPHEMERAL),
+    Err(e) => CreateInteractionResponseMessage::new()
+      .content(format!("Error getting bus #{vid}: {e}"))
+      .flags(InteractionResponseFlags::EPHEMERAL),
+  }
+}
+
+/// Describes a bus and the stops it's due at next, or `None` if Bus Tracker isn't tracking it.
+pub async fn vehicle_embed(
+  data: &CTASharedData,
+  vid: &str,
+) -> Result<Option<CreateEmbed>, BusTrackerError> {
+  Ok(
+    vehicle_details(data, &[vid.to_string()])
+      .await?
+      .into_values()
+      .next()
+      .map(|(vehicle, predictions)| describe_vehicle(data, &vehicle, &predictions)),
+  )
+}
+
+/// Positions and upcoming stops of up to [`MAX_REQUEST_IDS`] buses, by vehicle ID, in one
+/// request for each. Buses Bus Tracker isn't tracking are left out.
+pub async fn vehicle_details(
+  data: &CTASharedData,
+  vids: &[String],
+) -> Result<HashMap<String, (Vehicle, Vec<Prediction>)>, BusTrackerError> {
+  let vehicles = data
+    .bustracker
+    .get_vehicles(VehiclesParameters {
+      search: VidOrRt::Vid {
+        vehicle_ids: vids.to_vec(),
+      },
+    })
+    .await?;
+  if vehicles.is_empty() {
+    return Ok(HashMap::new());
+  }
+  let predictions = data
+    .bustracker
+    .get_predictions(PredictionsParameters {
+      search: StpidOrVid::Vid {
+        vid: vehicles.iter().map(|v| v.vid.clone()).collect(),
+      },
+    })
+    .await
+    .inspect_err(|e| {
+      println!(
+        "Error getting predictions for buses {}: {e}",
+        vids.join(",")
+      );
+    })
+    .unwrap_or_default();
+  Ok(
+    vehicles
+      .into_iter()
+      .map(|vehicle| {
+        let upcoming = predictions
+          .iter()
+          .filter(|prd| prd.vid == vehicle.vid)
+          .cloned()
+          .collect();
+        (vehicle.vid.clone(), (vehicle, upcoming))
+      })
+      .collect(),
+  )
+}
+
+/// Describes a bus and the stops it's due at next.
+pub fn describe_vehicle(
+  data: &CTASharedData,
+  vehicle: &Vehicle,
+  predictions: &[Prediction],
+) -> CreateEmbed {
+  let route_name = data
+    .gtfs
+    .gtfs_data
+    .get_route(&vehicle.rt)
+    .ok()
+    .and_then(|route| route.long_name.clone())
+    .map(|name| format!(" ({name})"))
+    .unwrap_or_default();
+  let stops = predictions
+    .iter()
+    .take(MAX_UPCOMING_STOPS)
+    .map(|prd| format!("{} <t:{}:R>", prd.stpnm, prediction_timestamp(prd)))
+    .collect::<Vec<_>>()
+    .join("\n");
+  let updated = BusTracker::parse_bustime_secs(&vehicle.tmstmp)
+    .map(|time| format!("Position as of {}", time.format("%-I:%M:%S %p")))
+    .unwrap_or_default();
+  CreateEmbed::new()
+    .title(format!("Bus #{}", vehicle.vid))
+    .description(format!(
+      "Route {}{route_name} to {}{}",
+      vehicle.rt,
+      vehicle.des,
+      if vehicle.dly == Some(true) {
+        "\n:warning: Delayed"
+      } else {
+        ""
+      }
+    ))
+    .field(
+      "Model",
+      data.roster.lookup(&vehicle.vid).map_or_else(
+        || "Not in the fleet roster".to_string(),
+        |series| format!("{} ({})", series.description(), series.propulsion),
+      ),
+      true,
+    )
+    .field("Pattern", vehicle.pid.to_string(), true)
+    .field(
+      "Heading",
+      format!(
+        "{} ({}°)",
+        util::compass_direction(vehicle.hdg),
+        vehicle.hdg
+      ),
+      true,
+    )
+    .field(
+      "Garage",
+      String::from(BusTracker::tablockid_to_garage(&vehicle.tablockid)),
+      true,
+    )
+    .field("Block", vehicle.tablockid.clone(), true)
+    .field(
+      "Upcoming stops",
+      if stops.is_empty() {
+        "No predictions right now".to_string()
+      } else {
+        stops
+      },
+      false,
+    )
+    .footer(CreateEmbedFooter::new(updated))
+}
+
+/// Turns a `/bus vehicle` response into a live tracker that the follow watcher keeps up to date.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn follow(ctx: &Context, component: &ComponentInteraction) -> CreateInteractionResponse {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  let Some((_, vid)) = component.data.custom_id.split_once("/vid/") else {
+    return CreateInteractionResponse::Message(
+      CreateInteractionResponseMessage::new()
+        .ephemeral(true)
+        .content("An error occured while attempting to follow the bus."),
+    );
+  };
+  let Ok(Some(embed)) = vehicle_embed(data, vid).await else {
+    return CreateInteractionResponse::Message(
+      CreateInteractionResponseMessage::new()
+        .ephemeral(true)
+        .content(format!("Bus #{vid} is no longer being tracked.")),
+    );
+  };
+  let follow = DBFollowedBus {
+    message_id: component.message.id.get() as i64,
+    channel_id: component.channel_id.get() as i64,
+    vid: vid.to_string(),
+    started_at: chrono::Utc::now().timestamp(),
+    missed_polls: 0,
+  };
+  if let Err(why) = db::add_followed_bus(&data.db, &follow).await {
+    println!("Error saving followed bus: {why}");
+    return CreateInteractionResponse::Message(
+      CreateInteractionResponseMessage::new()
+        .ephemeral(true)
+        .content("An error occured while attempting to follow the bus."),
+    );
+  }
+  CreateInteractionResponse::UpdateMessage(
+    CreateInteractionResponseMessage::new()
+      .embed(embed)
+      .content(follow_content(vid, FollowStatus::Live))
+      .components(follow_components(FollowStatus::Live)),
+  )
+}
+
+#[allow(clippy::cast_possible_wrap)]
+pub async fn unfollow(
+  ctx: &Context,
+  component: &ComponentInteraction,
+) -> CreateInteractionResponse {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  if let Err(why) = db::remove_followed_bus(&data.db, component.message.id.get() as i64).await {
+    println!("Error removing followed bus: {why}");
+  }
+  CreateInteractionResponse::UpdateMessage(
+    CreateInteractionResponseMessage::new()
+      .content(format!(
+        "{}\n{}",
+        component.message.content.lines().next().unwrap_or_default(),
+        status_line(FollowStatus::Stopped)
+      ))
+      .components(follow_components(FollowStatus::Stopped)),
+  )
+}
+
+pub fn follow_content(vid: &str, status: FollowStatus) -> String {
+  format!("Following Bus #{vid}\n{}", status_line(status))
+}
+
+fn status_line(status: FollowStatus) -> String {
+  match status {
+    FollowStatus::Live => format!("Last updated <t:{}:R>", chrono::Utc::now().timestamp()),
+    FollowStatus::Finished | FollowStatus::Lost => "This bus is no longer in service.".to_string(),
+    FollowStatus::TimedOut => "Stopped following after the time limit was reached.".to_string(),
+    FollowStatus::Stopped => "Stopped following.".to_string(),
+  }
+}
+
+pub fn follow_components(status: FollowStatus) -> Vec<CreateActionRow> {
+  match status {
+    FollowStatus::Live => vec![CreateActionRow::Buttons(vec![CreateButton::new(
+      "bus:unfollow",
+    )
+    .style(ButtonStyle::Secondary)
+    .label("Stop following")])],
+    _ => Vec::new(),
+  }
+}
+
+async fn bulletins_command(
+  data: &CTASharedData,
+  opts: &[ResolvedOption<'_>],
+) -> CreateInteractionResponseMessage {
+  let option = |name: &str| {
+    opts.iter().find_map(|o| match o.value {
+      ResolvedValue::String(val) if o.name == name => Some(val.trim()),
+      _ => None,
+    })
+  };
+  let (route, stop) = (option("route"), option("stop"));
+  if route.is_none() && stop.is_none() {
+    return CreateInteractionResponseMessage::new()
+      .content("Pick a route, a stop, or both.")
+      .flags(InteractionResponseFlags::EPHEMERAL);
+  }
+  let mut stop_ids = Vec::new();
+  if let Some(stop) = stop {
+    let stops = data.gtfs.search_bus_stops(stop);
+    if stops.is_empty() {
+      return CreateInteractionResponseMessage::new()
+        .content(format!("No stops found for {stop}."))
+        .flags(InteractionResponseFlags::EPHEMERAL);
+    } else if stops.len() > MAX_BULLETIN_STOPS {
+      return CreateInteractionResponseMessage::new()
+        .content(format!(
+          "Too many stops match {stop}. Please pick one from the suggestions."
+        ))
+        .flags(InteractionResponseFlags::EPHEMERAL);
+    }
+    stop_ids = stops.iter().map(|s| s.id.clone()).collect();
+  }
+
+  let bulletins = match data
+    .bustracker
+    .get_service_bulletins(ServiceBulletinsParameters {
+      rt: route.iter().map(ToString::to_string).collect(),
+      rtdir: None,
+      stpid: stop_ids,
+    })
+    .await
+  {
+    Ok(bulletins) => bulletins,
+    Err(e) => {
+      return CreateInteractionResponseMessage::new()
+        .content(format!("Error getting service bulletins: {e}"))
+        .flags(InteractionResponseFlags::EPHEMERAL);
+    }
+  };
+  let searched = [route.map(|r| format!("Route {r}")), stop.map(String::from)]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join(" at ");
+  if bulletins.is_empty() {
+    return CreateInteractionResponseMessage::new()
+      .content(format!("There are no service bulletins for {searched}."));
+  }
+  let mut response = CreateInteractionResponseMessage::new().embeds(
+    bulletins
+      .iter()
+      .take(MAX_EMBEDS)
+      .map(bulletin_embed)
+      .collect(),
+  );
+  if bulletins.len() > MAX_EMBEDS {
+    response = response.content(format!(
+      "Showing {MAX_EMBEDS} of {} bulletins for {searched}.",
+      bulletins.len()
+    ));
+  }
+  response
+}
+
+/// Formats a Bus Tracker service bulletin, colored by its priority.
+pub fn bulletin_embed(bulletin: &ServiceBulletin) -> CreateEmbed {
+  let detail = util::strip_html(&bulletin.dtl);
+  let mut embed = CreateEmbed::new()
+    .title(util::truncate(&bulletin.sbj, 256))
+    .description(util::truncate(
+      if detail.is_empty() {
+        &bulletin.brf
+      } else {
+        &detail
+      },
+      4096,
+    ))
+    .color(match bulletin.prty.as_str() {
+      "High" => 0x00C6_0C30,
+      "Medium" => 0x00F9_461C,
+      _ => 0x0056_5A5C,
+    })
+    .footer(CreateEmbedFooter::new(format!(
+      "{} priority bus service bulletin",
+      bulletin.prty
+    )));
+  let mut routes: Vec<&str> = Vec::new();
+  let mut stops: Vec<&str> = Vec::new();
+  for service in &bulletin.srvc {
+    if !service.rt.is_empty() && !routes.contains(&service.rt.as_str()) {
+      routes.push(&service.rt);
+    }
+    if !service.stpnm.is_empty() && !stops.contains(&service.stpnm.as_str()) {
+      stops.push(&service.stpnm);
+    }
+  }
+  if !routes.is_empty() {
+    embed = embed.field("Routes", routes.join(", "), true);
+  }
+  if !stops.is_empty() {
+    embed = embed.field("Stops", util::truncate(&stops.join("\n"), 1024), true);
+  }
+  match bulletin.url.as_deref() {
+    Some(url) if !url.is_empty() => embed.url(url),
+    _ => embed,
+  }
+}
+
+async fn bunching_command(data: &CTASharedData, route: &str) -> CreateInteractionResponseMessage {
+  let vehicles = match data
+    .bustracker
+    .get_vehicles(VehiclesParameters {
+      search: VidOrRt::Rt {
+        route_codes: vec![route.to_string()],
+      },
+    })
+    .await
+  {
+    Ok(vehicles) => vehicles,
+    Err(e) => {
+      return CreateInteractionResponseMessage::new()
+        .content(format!("Error getting buses on Route {route}: {e}"))
+        .flags(InteractionResponseFlags::EPHEMERAL);
+    }
+  };
+  if vehicles.is_empty() {
+    return CreateInteractionResponseMessage::new()
+      .content(format!("No buses are running on Route {route} right now."));
+  }
+  let patterns = patterns_by_id(data, vehicles.iter().map(|v| v.pid).collect()).await;
+  let lengths: HashMap<i32, f64> = patterns.iter().map(|(pid, p)| (*pid, p.ln)).collect();
+  let spacing = bunching::spacing(&vehicles, &lengths);
+
+  let summaries: Vec<String> = spacing
+    .iter()
+    .flat_map(|pattern| {
+      pattern
+        .bunches
+        .iter()
+        .map(|bunch| bunch_summary(bunch, patterns.get(&pattern.pid)))
+    })
+    .collect();
+  let route_name = data
+    .gtfs
+    .gtfs_data
+    .get_route(route)
+    .ok()
+    .and_then(|r| r.long_name.clone())
+    .map(|name| format!(" ({name})"))
+    .unwrap_or_default();
+  let mut embed = CreateEmbed::new()
+    .title(format!("Bus spacing on Route {route}{route_name}"))
+    .description(if summaries.is_empty() {
+      "No buses are bunched right now.".to_string()
+    } else {
+      summaries.join("\n")
+    })
+    .footer(CreateEmbedFooter::new(format!(
+      "Buses within {} of each other with a long gap behind them count as bunched.",
+      miles(f64::from(bunching::BUNCHED_FEET))
+    )));
+  for pattern in spacing.iter().take(MAX_FIELDS) {
+    let (name, value) = spacing_field(pattern, patterns.get(&pattern.pid));
+    embed = embed.field(name, value, false);
+  }
+  CreateInteractionResponseMessage::new().add_embed(embed)
+}
+
+/// Lists the buses on a pattern in running order, with the space between them.
+fn spacing_field(pattern: &PatternSpacing, info: Option<&Pattern>) -> (String, String) {
+  let lines: Vec<String> = pattern
+    .buses
+    .iter()
+    .map(|(bus, gap)| {
+      let place = info
+        .zip(bus.pdist)
+        .and_then(|(info, pdist)| info.stop_at(f64::from(pdist)))
+        .map(|stop| format!(" near {stop}"))
+        .unwrap_or_default();
+      format!(
+        "`#{}`{place}{}{}",
+        bus.vid,
+        gap
+          .map(|gap| format!(", {} behind", miles(f64::from(gap))))
+          .unwrap_or_default(),
+        if pattern.is_bunched(&bus.vid) {
+          " **(bunched)**"
+        } else {
+          ""
+        }
+      )
+    })
+    .collect();
+  (
+    format!(
+      "{} to {}, ideally {} apart",
+      info.map_or("Pattern", |p| p.rtdir.as_str()),
+      pattern.buses[0].0.des,
+      miles(pattern.even_spacing)
+    ),
+    util::truncate(&lines.join("\n"), 1024),
+  )
+}
+
+/// Describes a bunch, like "3 buses together near Clark & Belmont (#1, #2, #3), then a 2.1 mi gap".
+pub fn bunch_summary(bunch: &Bunch, pattern: Option<&Pattern>) -> String {
+  let place = pattern
+    .zip(bunch.buses[0].pdist)
+    .and_then(|(pattern, pdist)| pattern.stop_at(f64::from(pdist)))
+    .map(|stop| format!(" near {stop}"))
+    .unwrap_or_default();
+  format!(
+    "{} buses together{place} ({}), then a {} gap",
+    bunch.buses.len(),
+    bunch
+      .buses
+      .iter()
+      .map(|bus| format!("#{}", bus.vid))
+      .collect::<Vec<_>>()
+      .join(", "),
+    miles(f64::from(bunch.gap_behind))
+  )
+}
+
+fn miles(feet: f64) -> String {
+  format!("{:.1} mi", feet / 5280.0)
+}
+
+async fn garages_command(
+  data: &CTASharedData,
+  opts: &[ResolvedOption<'_>],
+) -> CreateInteractionResponseMessage {
+  let route = opts.iter().find_map(|o| match o.value {
+    ResolvedValue::String(route) if o.name == "route" => Some(route.trim()),
+    _ => None,
+  });
+  let Some(route) = route else {
+    return systemwide_garages(data).await;
+  };
+  match valid_routes(data, route).await {
+    Ok(routes) => match routes.as_slice() {
+      [route] => route_garages(data, route).await,
+      _ => CreateInteractionResponseMessage::new()
+        .content("Pick one route at a time.".to_string())
+        .flags(InteractionResponseFlags::EPHEMERAL),
+    },
+    Err(message) => CreateInteractionResponseMessage::new()
+      .content(message)
+      .flags(InteractionResponseFlags::EPHEMERAL),
+  }
+}
+
+/// Active buses per garage across every route.
+async fn systemwide_garages(data: &CTASharedData) -> CreateInteractionResponseMessage {
+  let routes = match data.bustracker.get_routes().await {
+    Ok(routes) => routes,
+    Err(e) => {
+      return CreateInteractionResponseMessage::new()
+        .content(format!("Error getting bus routes: {e}"))
+        .flags(InteractionResponseFlags::EPHEMERAL);
+    }
+  };
+  let routes: Vec<String> = routes.into_iter().map(|r| r.rt).collect();
+  let (vehicles, failed) = route_vehicles(data, &routes).await;
+  if vehicles.is_empty() {
+    return if failed.is_empty() {
+      CreateInteractionResponseMessage::new().content("No buses are running right now.".to_string())
+    } else {
+      CreateInteractionResponseMessage::new()
+        .content("Error getting buses from Bus Tracker.".to_string())
+        .flags(InteractionResponseFlags::EPHEMERAL)
+    };
+  }
+  let mut embed = CreateEmbed::new()
+    .title(format!("{} buses running systemwide", vehicles.len()))
+    .footer(CreateEmbedFooter::new(
+      "Garages are worked out from each bus's block number.",
+    ));
+  if !failed.is_empty() {
+    embed = embed.description(format!(
+      ":warning: Couldn't get buses on routes {}, so these totals are partial.",
+      failed.join(", ")
+    ));
+  }
+  for (garage, count) in garages::garage_counts(&vehicles) {
+    embed = embed.field(String::from(garage), count.to_string(), true);
+  }
+  CreateInteractionResponseMessage::new().add_embed(embed)
+}
+
+/// Which garages are running a route, and which of its buses are interlined with other routes.
+async fn route_garages(data: &CTASharedData, route: &str) -> CreateInteractionResponseMessage {
+  let vehicles = match data
+    .bustracker
+    .get_vehicles(VehiclesParameters {
+      search: VidOrRt::Rt {
+        route_codes: vec![route.to_string()],
+      },
+    })
+    .await
+  {
+    Ok(vehicles) => vehicles,
+    Err(e) => {
+      return CreateInteractionResponseMessage::new()
+        .content(format!("Error getting buses on Route {route}: {e}"))
+        .flags(InteractionResponseFlags::EPHEMERAL);
+    }
+  };
+  if vehicles.is_empty() {
+    return CreateInteractionResponseMessage::new()
+      .content(format!("No buses are running on Route {route} right now."));
+  }
+
+  let description: Vec<String> = garages::garage_counts(&vehicles)
+    .into_iter()
+    .map(|(garage, count)| format!("**{count}** from {}", String::from(garage)))
+    .collect();
+  let interlines = garages::interlined(&vehicles, |block_route| {
+    data.gtfs.gtfs_data.get_route(block_route).is_ok()
+  });
+  let vids: Vec<String> = vehicles.iter().map(|v| v.vid.clone()).collect();
+  let since = chrono::Utc::now().timestamp() - ROUTE_HISTORY_SECS;
+  let history = db::get_bus_route_history(&data.db, &vids, since)
+    .await
+    .unwrap_or_else(|e| {
+      println!("Error getting bus route history: {e}");
+      Vec::new()
+    });
+
+  let route_name = data
+    .gtfs
+    .gtfs_data
+    .get_route(route)
+    .ok()
+    .and_then(|r| r.long_name.clone())
+    .map(|name| format!(" ({name})"))
+    .unwrap_or_default();
+  let mut embed = CreateEmbed::new()
+    .title(format!("Garages running Route {route}{route_name}"))
+    .description(description.join("\n"))
+    .footer(CreateEmbedFooter::new(
+      "Garages are worked out from each bus's block number. \
+      Earlier routes only cover routes with recorded history.",
+    ));
+  if !interlines.is_empty() {
+    let lines: Vec<String> = interlines
+      .iter()
+      .map(|i| {
+        format!(
+          "`#{}` on a Route {} block (`{}`)",
+          i.vehicle.vid, i.block_route, i.vehicle.tablockid
+        )
+      })
+      .collect();
+    embed = embed.field(
+      "Interlined with other routes",
+      util::truncate(&lines.join("\n"), 1024),
+      false,
+    );
+  }
+  if !history.is_empty() {
+    let lines: Vec<String> = history
+      .iter()
+      .map(|bus| format!("`#{}`: {}", bus.vid, bus.routes.join(" → ")))
+      .collect();
+    embed = embed.field(
+      format!(
+        "Ran other routes in the last {} hours",
+        ROUTE_HISTORY_SECS / 3600
+      ),
+      util::truncate(&lines.join("\n"), 1024),
+      false,
+    );
+  }
+  CreateInteractionResponseMessage::new().add_embed(embed)
+}
+
+async fn detours_command(data: &CTASharedData, route: &str) -> CreateInteractionResponseMessage {
+  let detours = match data
+    .bustracker
+    .get_detours(DetoursParameters {
+      rt: Some(route.to_string()),
+      rtdir: None,
+    })
+    .await
+  {
+    Ok(detours) => detours,
+    Err(e) => {
+      return CreateInteractionResponseMessage::new()
+        .content(format!("Error getting detours: {e}"))
+        .flags(InteractionResponseFlags::EPHEMERAL);
+    }
+  };
+  let active: Vec<&Detour> = detours.iter().filter(|d| d.is_active()).collect();
+  if active.is_empty() {
+    return CreateInteractionResponseMessage::new()
+      .content(format!("There are no detours on Route {route} right now."));
+  }
+  let mut response = CreateInteractionResponseMessage::new();
+  for detour in active.iter().take(MAX_EMBEDS) {
+    let (embed, map) = detour_message(data, detour).await;
+    response = response.add_embed(embed);
+    if let Some(map) = map {
+      response = response.add_file(map);
+    }
+  }
+  if active.len() > MAX_EMBEDS {
+    response = response.content(format!(
+      "Showing {MAX_EMBEDS} of {} detours on Route {route}.",
+      active.len()
+    ));
+  }
+  response
+}
+
+/// Builds the embed for a detour. A map of the regular and detoured paths is attached when the
+/// detour's patterns can be fetched.
+pub async fn detour_message(
+  data: &CTASharedData,
+  detour: &Detour,
+) -> (CreateEmbed, Option<CreateAttachment>) {
+  let mut routes: Vec<&str> = Vec::new();
+  for rtdir in &detour.rtdirs {
+    if !routes.contains(&rtdir.rt.as_str()) {
+      routes.push(&rtdir.rt);
+    }
+  }
+  let time = |bustime: &str| {
+    BusTracker::parse_bustime(bustime)
+      .or_else(|_| BusTracker::parse_bustime_secs(bustime))
+      .map_or_else(
+        |_| "Until further notice".to_string(),
+        |time| format!("<t:{}:f>", time.timestamp()),
+      )
+  };
+  let embed = CreateEmbed::new()
+    .title(util::truncate(&detour.desc, 256))
+    .color(0x00F9_461C)
+    .field(
+      "Affects",
+      detour
+        .rtdirs
+        .iter()
+        .map(|d| format!("Route {} {}", d.rt, d.dir))
+        .collect::<Vec<_>>()
+        .join("\n"),
+      false,
+    )
+    .field("Starts", time(&detour.startdt), true)
+    .field("Ends", time(&detour.enddt), true)
+    .footer(CreateEmbedFooter::new(format!(
+      "Detour {} (version {})",
+      detour.id, detour.ver
+    )));
+
+  let (regular, detoured, stops) = detour_paths(data, detour, &routes).await;
+  if detoured.is_empty() {
+    return (embed, None);
+  }
+  let map = arrivaldisplay::detour_map(
+    format!("Route {} detour", routes.join(", ")),
+    &regular,
+    &detoured,
+    &stops,
+  );
+  match arrivaldisplay::render_doc(&map) {
+    Ok(png) => {
+      let name = format!(
+        "detour-{}.png",
+        detour
+          .id
+          .chars()
+          .filter(char::is_ascii_alphanumeric)
+          .collect::<String>()
+      );
+      (
+        embed.image(format!("attachment://{name}")),
+        Some(CreateAttachment::bytes(png, name)),
+      )
+    }
+    Err(e) => {
+      println!("Error rendering detour map: {e}");
+      (embed, None)
+    }
+  }
+}
+
+/// Regular and detoured paths of every pattern a detour changes, along with stops only served by
+/// the detour.
+async fn detour_paths(
+  data: &CTASharedData,
+  detour: &Detour,
+  routes: &[&str],
+) -> (Vec<Vec<(f64, f64)>>, Vec<Vec<(f64, f64)>>, Vec<MapStop>) {
+  let mut regular = Vec::new();
+  let mut detoured = Vec::new();
+  let mut stops: Vec<MapStop> = Vec::new();
+  for rt in routes {
+    let patterns = match data.bustracker.get_detour_patterns(rt).await {
+      Ok(patterns) => patterns,
+      Err(e) => {
+        println!("Error getting patterns for detour {}: {e}", detour.id);
+        continue;
+      }
+    };
+    for pattern in patterns
+      .iter()
+      .filter(|p| p.dtrid.as_deref() == Some(detour.id.as_str()))
+    {
+      // Detoured patterns carry the new path in `dtrpt`, and usually the regular one in `pt`.
+      let (path, original) = if pattern.dtrpt.is_empty() {
+        (&pattern.pt, None)
+      } else {
+        (
+          &pattern.dtrpt,
+          Some(&pattern.pt).filter(|pt| !pt.is_empty()),
+        )
+      };
+      let original = original.or_else(|| {
+        patterns
+          .iter()
+          .filter(|p| p.dtrid.is_none() && p.rtdir == pattern.rtdir)
+          .max_by_key(|p| (p.pid == pattern.pid, p.pt.len()))
+          .map(|p| &p.pt)
+      });
+      detoured.push(path.iter().map(|p| (p.lat, p.lon)).collect::<Vec<_>>());
+      for point in path.iter().filter(|p| p.typ == PatternPointType::S) {
+        let on_regular_route =
+          original.is_some_and(|original| original.iter().any(|p| p.stpid == point.stpid));
+        if let (false, Some(name)) = (on_regular_route, &point.stpnm) {
+          if !stops.iter().any(|s| &s.name == name) {
+            stops.push(MapStop {
+              name: name.clone(),
+              location: (point.lat, point.lon),
+            });
+          }
+        }
+      }
+      if let Some(original) = original {
+        regular.push(original.iter().map(|p| (p.lat, p.lon)).collect::<Vec<_>>());
+      }
+    }
+  }
+  (regular, detoured, stops)
+}
+
 //  CreateInteractionResponseMessage::new().content("Pong!".to_string())
 
+fn route_option() -> CreateCommandOption {
+  CreateCommandOption::new(
+    serenity::all::CommandOptionType::String,
+    "route",
+    "Bus route number, ex: '22'",
+  )
+}
+
 pub fn register() -> CreateCommand {
   CreateCommand::new("bus")
     .description("Get bus information.")
@@ -280,12 +1429,75 @@ pub fn register() -> CreateCommand {
         "vehicles",
         "Get vehicle information",
       )
+      .add_sub_option(
+        CreateCommandOption::new(
+          serenity::all::CommandOptionType::String,
+          "route",
+          "Bus route numbers, separated by commas. ex: '20,49,X49,62'",
+        )
+        .set_autocomplete(true),
+      )
       .add_sub_option(CreateCommandOption::new(
-        serenity::all::CommandOptionType::String,
-        "route",
-        "Bus route numbers, separated by commas. ex: '20,49,X49,62'",
+        serenity::all::CommandOptionType::Boolean,
+        "delayed_only",
+        "Only list delayed buses",
       )),
     )
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::SubCommand,
+        "vehicle",
+        "Get details on a single bus",
+      )
+      .add_sub_option(
+        CreateCommandOption::new(
+          serenity::all::CommandOptionType::String,
+          "id",
+          "Bus number, ex: '8312'",
+        )
+        .required(true),
+      ),
+    )
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::SubCommand,
+        "bulletins",
+        "Get service bulletins for a route or stop",
+      )
+      .add_sub_option(route_option())
+      .add_sub_option(
+        CreateCommandOption::new(
+          serenity::all::CommandOptionType::String,
+          "stop",
+          "Stop Name search",
+        )
+        .set_autocomplete(true),
+      ),
+    )
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::SubCommand,
+        "detours",
+        "Get current detours on a route, with maps",
+      )
+      .add_sub_option(route_option().required(true)),
+    )
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::SubCommand,
+        "bunching",
+        "See how buses are spaced along a route, and where they're bunched",
+      )
+      .add_sub_option(route_option().required(true)),
+    )
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::SubCommand,
+        "garages",
+        "See which garages are running buses, systemwide or on one route",
+      )
+      .add_sub_option(route_option()),
+    )
     .add_option(
       CreateCommandOption::new(
         serenity::all::CommandOptionType::SubCommand,
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use crate::cta::bustracker::{VehiclesParameters, VidOrRt};
+use crate::cta::roster::Propulsion;
+use crate::CTAShared;
+use serenity::all::{
+  Context, CreateCommandOption, CreateEmbed, CreateEmbedFooter, CreateInteractionResponseMessage,
+  InteractionResponseFlags,
+};
+use serenity::builder::CreateCommand;
+use serenity::model::application::{ResolvedOption, ResolvedValue};
+
+pub async fn run<'a>(
+  ctx: &Context,
+  options: &'a [ResolvedOption<'a>],
+) -> CreateInteractionResponseMessage {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  let Some(ResolvedOption {
+    value: ResolvedValue::String(route),
+    ..
+  }) = options.first()
+  else {
+    return CreateInteractionResponseMessage::new().content("Options not provided.".to_string());
+  };
+  let route = route.trim();
+  let vehicles = match data
+    .bustracker
+    .get_vehicles(VehiclesParameters {
+      search: VidOrRt::Rt {
+        route_codes: vec![route.to_string()],
+      },
+    })
+    .await
+  {
+    Ok(vehicles) => vehicles,
+    Err(e) => {
+      return CreateInteractionResponseMessage::new()
+        .content(format!("Error getting buses on Route {route}: {e}"))
+        .flags(InteractionResponseFlags::EPHEMERAL);
+    }
+  };
+  if vehicles.is_empty() {
+    return CreateInteractionResponseMessage::new()
+      .content(format!("No buses are running on Route {route} right now."));
+  }
+
+  let mut models: HashMap<String, usize> = HashMap::new();
+  let mut propulsion: HashMap<Propulsion, usize> = HashMap::new();
+  let mut unknown = 0;
+  for vehicle in &vehicles {
+    match data.roster.lookup(&vehicle.vid) {
+      Some(series) => {
+        *models.entry(series.description()).or_default() += 1;
+        *propulsion.entry(series.propulsion).or_default() += 1;
+      }
+      None => unknown += 1,
+    }
+  }
+  let mut models: Vec<(String, usize)> = models.into_iter().collect();
+  models.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+  let mut description = models
+    .iter()
+    .map(|(model, count)| format!("**{count}** × {model}"))
+    .collect::<Vec<_>>();
+  if unknown > 0 {
+    description.push(format!("**{unknown}** not in the fleet roster"));
+  }
+
+  let route_name = data
+    .gtfs
+    .gtfs_data
+    .get_route(route)
+    .ok()
+    .and_then(|r| r.long_name.clone())
+    .map(|name| format!(" ({name})"))
+    .unwrap_or_default();
+  let mut embed = CreateEmbed::new()
+    .title(format!(
+      "{} buses on Route {route}{route_name}",
+      vehicles.len()
+    ))
+    .description(description.join("\n"))
+    .footer(CreateEmbedFooter::new(
+      "Models are looked up by bus number and may lag behind new deliveries.",
+    ));
+  for kind in Propulsion::ALL {
+    if let Some(count) = propulsion.get(&kind) {
+      embed = embed.field(kind.to_string(), count.to_string(), true);
+    }
+  }
+  CreateInteractionResponseMessage::new().add_embed(embed)
+}
+
+pub fn register() -> CreateCommand {
+  CreateCommand::new("fleet")
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::String,
+        "route",
+        "Bus route, ex: '22'",
+      )
+      .required(true),
+    )
+    .add_integration_type(serenity::all::InstallationContext::Guild)
+    .add_integration_type(serenity::all::InstallationContext::User)
+    .description("Shows the makes and models of buses running on a route.")
+}
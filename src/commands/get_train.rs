@@ -1,11 +1,29 @@
-use crate::CTAShared;
+use crate::arrivaldisplay::{self, StripStop};
+use crate::cta::traintracker::{LRouteName, TTFollowEta, TTPosition, TrainTracker};
+use crate::db::{self, DBFollowedTrain};
+use crate::{util, CTAShared};
 use chrono::TimeZone;
 use chrono_tz::America::Chicago;
-use serenity::all::{Context, CreateCommandOption, CreateInteractionResponseMessage};
+use serenity::all::{
+  ButtonStyle, ComponentInteraction, Context, CreateActionRow, CreateAttachment, CreateButton,
+  CreateCommandOption, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+};
 use serenity::builder::CreateCommand;
 use serenity::model::application::{ResolvedOption, ResolvedValue};
 use std::fmt::Write;
 
+pub const STRIP_MAP_FILE: &str = "strip.png";
+
+/// The state of a followed train, as shown at the top of its message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FollowStatus {
+  Live,
+  Finished,
+  Lost,
+  TimedOut,
+  Stopped,
+}
+
 #[allow(clippy::cast_possible_truncation)]
 pub async fn run<'a>(
   ctx: &Context,
@@ -31,17 +49,34 @@ pub async fn run<'a>(
             chrono::DateTime::timestamp(&Chicago.from_local_datetime(&sta.arrival_time).unwrap())
           )
           .unwrap();
-          // embed = embed.field(, format!("<t:{}:R>", , true);
-          // .chrono::TimeZoneDateTime::<chrono::Local>::from_naive_utc_and_offset(sta.arrT.checked_sub_offset(chrono::FixedOffset::west_opt(6*3600).unwrap()).expect("Time is gone I guess"), chrono::FixedOffset::west_opt(6*3600).unwrap()).timestamp()), true);
           acc
         });
-        return CreateInteractionResponseMessage::new()
+        let stations: Vec<String> = val.iter().map(|eta| eta.station_name.clone()).collect();
+        let position = current_position(tt, *run as i32, &val).await;
+        let response = match strip_map(*run as i32, &stations, 0, &val, position.as_ref()) {
+          Some(attachment) => CreateInteractionResponseMessage::new()
+            .add_file(attachment)
+            .embed(
+              embed
+                .description(desc)
+                .image(format!("attachment://{STRIP_MAP_FILE}")),
+            ),
+          None => CreateInteractionResponseMessage::new().embed(embed.description(desc)),
+        };
+        return response
           .content(format!(
-            "Found {} upcoming stations for Train #{}",
+            "Found {} upcoming stations for Train #{}{}",
             val.len(),
-            run
+            run,
+            train_status(&val, position.as_ref())
+              .map(|status| format!(" | :warning: {status}"))
+              .unwrap_or_default()
           ))
-          .embed(embed.description(desc));
+          .button(
+            CreateButton::new(format!("get_train:follow/run/{run}"))
+              .style(ButtonStyle::Primary)
+              .label("Follow"),
+          );
       }
       Err(err) => {
         return CreateInteractionResponseMessage::new()
@@ -52,6 +87,228 @@ pub async fn run<'a>(
   CreateInteractionResponseMessage::new().content("Options not provided.".to_string())
 }
 
+/// Turns a `/get_train` response into a live tracker that the follow watcher keeps up to date.
+#[allow(clippy::cast_possible_wrap)]
+pub async fn follow(ctx: &Context, component: &ComponentInteraction) -> CreateInteractionResponse {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  let Some(run) = component
+    .data
+    .custom_id
+    .split_once("/run/")
+    .and_then(|(_, run)| run.parse::<i32>().ok())
+  else {
+    return CreateInteractionResponse::Message(
+      CreateInteractionResponseMessage::new()
+        .ephemeral(true)
+        .content("An error occured while attempting to follow the train."),
+    );
+  };
+  let etas = match data.traintracker.follow_train(run).await {
+    Ok(etas) if !etas.is_empty() => etas,
+    _ => {
+      return CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+          .ephemeral(true)
+          .content(format!("Train #{run} is no longer being tracked.")),
+      );
+    }
+  };
+  let follow = DBFollowedTrain {
+    message_id: component.message.id.get() as i64,
+    channel_id: component.channel_id.get() as i64,
+    run_number: run,
+    stations: etas.iter().map(|eta| eta.station_name.clone()).collect(),
+    started_at: chrono::Utc::now().timestamp(),
+    last_seen_index: 0,
+    missed_polls: 0,
+  };
+  if let Err(why) = db::add_followed_train(&data.db, &follow).await {
+    println!("Error saving followed train: {why}");
+    return CreateInteractionResponse::Message(
+      CreateInteractionResponseMessage::new()
+        .ephemeral(true)
+        .content("An error occured while attempting to follow the train."),
+    );
+  }
+  let position = current_position(&data.traintracker, run, &etas).await;
+  let embed = follow_embed(&follow.stations, 0, &etas);
+  let response = match strip_map(run, &follow.stations, 0, &etas, position.as_ref()) {
+    Some(attachment) => CreateInteractionResponseMessage::new()
+      .add_file(attachment)
+      .embed(embed.image(format!("attachment://{STRIP_MAP_FILE}"))),
+    None => CreateInteractionResponseMessage::new().embed(embed),
+  };
+  CreateInteractionResponse::UpdateMessage(
+    response
+      .content(follow_content(run, FollowStatus::Live))
+      .components(follow_components(FollowStatus::Live)),
+  )
+}
+
+#[allow(clippy::cast_possible_wrap)]
+pub async fn unfollow(
+  ctx: &Context,
+  component: &ComponentInteraction,
+) -> CreateInteractionResponse {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  if let Err(why) = db::remove_followed_train(&data.db, component.message.id.get() as i64).await {
+    println!("Error removing followed train: {why}");
+  }
+  CreateInteractionResponse::UpdateMessage(
+    CreateInteractionResponseMessage::new()
+      .content(format!(
+        "{}\n{}",
+        component.message.content.lines().next().unwrap_or_default(),
+        status_line(FollowStatus::Stopped)
+      ))
+      .components(follow_components(FollowStatus::Stopped)),
+  )
+}
+
+/// How many of the originally listed stations the train has already passed.
+pub fn passed_count(stations: &[String], etas: &[TTFollowEta]) -> Option<usize> {
+  let next = etas.first()?;
+  stations.iter().position(|s| s.eq(&next.station_name))
+}
+
+/// Looks up the followed run in the positions feed for its line.
+pub async fn current_position(
+  tt: &TrainTracker,
+  run: i32,
+  etas: &[TTFollowEta],
+) -> Option<TTPosition> {
+  let route = etas.first()?.route.clone();
+  tt.fleet(&[route.into()]).await.ok()?.train(run).cloned()
+}
+
+/// Where the train sits on the strip map, measured in stations from the start of `stations`.
+#[allow(clippy::cast_precision_loss)]
+pub fn train_position(stations: &[String], passed: usize, position: Option<&TTPosition>) -> f32 {
+  let next = position
+    .and_then(|p| stations.iter().position(|s| s.eq(&p.next_station_name)))
+    .unwrap_or(passed);
+  let approaching = position.is_some_and(|p| p.is_approaching);
+  next as f32 - if approaching { 0.25 } else { 0.6 }
+}
+
+/// Whether a run is delayed or has a possible fault, going by its position and next prediction.
+pub fn train_status(etas: &[TTFollowEta], position: Option<&TTPosition>) -> Option<&'static str> {
+  let next = etas.first();
+  if next.is_some_and(|eta| eta.is_faulted) {
+    Some("Possible fault")
+  } else if next.is_some_and(|eta| eta.is_delayed) || position.is_some_and(|p| p.is_delayed) {
+    Some("Delayed")
+  } else {
+    None
+  }
+}
+
+/// Renders the strip map for a run, returning `None` if there is nothing to draw or rendering fails.
+pub fn strip_map(
+  run: i32,
+  stations: &[String],
+  passed: usize,
+  etas: &[TTFollowEta],
+  position: Option<&TTPosition>,
+) -> Option<CreateAttachment> {
+  let first = etas.first()?;
+  let route: LRouteName = first.route.clone();
+  let stops: Vec<StripStop> = stations
+    .iter()
+    .enumerate()
+    .map(|(i, name)| StripStop {
+      name: name.clone(),
+      eta: etas
+        .iter()
+        .find(|eta| i >= passed && eta.station_name.eq(name))
+        .and_then(|eta| eta.arrival_time.and_local_timezone(Chicago).earliest())
+        .map(|arrival| util::countdown(util::minutes_until(arrival))),
+      passed: i < passed,
+    })
+    .collect();
+  let doc = arrivaldisplay::strip_map(
+    format!(
+      "{route} #{run} to {}{}",
+      first.destination_name,
+      train_status(etas, position)
+        .map(|status| format!(" ({status})"))
+        .unwrap_or_default()
+    ),
+    &route,
+    &stops,
+    Some(train_position(stations, passed, position)),
+  );
+  match arrivaldisplay::render_doc(&doc) {
+    Ok(data) => Some(CreateAttachment::bytes(data, STRIP_MAP_FILE)),
+    Err(err) => {
+      println!("Error rendering strip map for Train #{run}: {err}");
+      None
+    }
+  }
+}
+
+pub fn follow_content(run: i32, status: FollowStatus) -> String {
+  format!("Following Train #{run}\n{}", status_line(status))
+}
+
+fn status_line(status: FollowStatus) -> String {
+  match status {
+    FollowStatus::Live => format!("Last updated <t:{}:R>", chrono::Utc::now().timestamp()),
+    FollowStatus::Finished => "This run has reached its terminal.".to_string(),
+    FollowStatus::Lost => "This train is no longer being tracked.".to_string(),
+    FollowStatus::TimedOut => "Stopped following after the time limit was reached.".to_string(),
+    FollowStatus::Stopped => "Stopped following.".to_string(),
+  }
+}
+
+pub fn follow_embed(stations: &[String], passed: usize, etas: &[TTFollowEta]) -> CreateEmbed {
+  let desc = stations
+    .iter()
+    .enumerate()
+    .fold(String::new(), |mut acc, (i, station)| {
+      if i < passed {
+        writeln!(acc, "~~{station}~~").unwrap();
+      } else if let Some(eta) = etas.iter().find(|eta| eta.station_name.eq(station)) {
+        let arrival = Chicago
+          .from_local_datetime(&eta.arrival_time)
+          .earliest()
+          .map_or(0, |t| t.timestamp());
+        writeln!(
+          acc,
+          "{}{station}: <t:{arrival}:R>{}",
+          if i == passed { "**Next:** " } else { "" },
+          if eta.is_faulted {
+            " (possible fault)"
+          } else if eta.is_delayed {
+            " (delayed)"
+          } else {
+            ""
+          }
+        )
+        .unwrap();
+      } else {
+        writeln!(acc, "{station}").unwrap();
+      }
+      acc
+    });
+  CreateEmbed::new().description(desc)
+}
+
+pub fn follow_components(status: FollowStatus) -> Vec<CreateActionRow> {
+  match status {
+    FollowStatus::Live => vec![CreateActionRow::Buttons(vec![CreateButton::new(
+      "get_train:unfollow",
+    )
+    .style(ButtonStyle::Secondary)
+    .label("Stop following")])],
+    _ => Vec::new(),
+  }
+}
+
 pub fn register() -> CreateCommand {
   let run_option = CreateCommandOption::new(
     serenity::all::CommandOptionType::Integer,
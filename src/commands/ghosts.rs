@@ -0,0 +1,189 @@
+use crate::cta::analysis::ghosts::{GhostRates, TIME_PERIODS};
+use crate::cta::traintracker::{LRouteCode, LRouteName};
+use crate::{db, CTAShared};
+use serenity::all::{
+  Context, CreateCommandOption, CreateEmbed, CreateEmbedFooter, CreateInteractionResponseMessage,
+  InteractionResponseFlags,
+};
+use serenity::builder::CreateCommand;
+use serenity::model::application::{ResolvedOption, ResolvedValue};
+
+const PERIOD_DAYS: i64 = 7;
+
+pub async fn run<'a>(
+  ctx: &Context,
+  options: &'a [ResolvedOption<'a>],
+) -> CreateInteractionResponseMessage {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  let route = match options.first() {
+    Some(ResolvedOption {
+      value: ResolvedValue::String(val),
+      ..
+    }) => match val.parse::<LRouteCode>() {
+      Ok(route) => Some(route),
+      Err(_) => {
+        return CreateInteractionResponseMessage::new()
+          .content(format!("Unknown line: {val}"))
+          .flags(InteractionResponseFlags::EPHEMERAL);
+      }
+    },
+    _ => None,
+  };
+
+  let since = chrono::Utc::now().timestamp() - PERIOD_DAYS * 24 * 60 * 60;
+  let rates = match db::get_ghost_rates(&data.db, since).await {
+    Ok(rows) if rows.is_empty() => {
+      return CreateInteractionResponseMessage::new().content(
+        "There isn't enough data on scheduled trips yet. Check back once the bot has been running for a while."
+          .to_string(),
+      );
+    }
+    Ok(rows) => GhostRates::new(&rows),
+    Err(e) => {
+      println!("Error reading ghost rates: {e}");
+      return CreateInteractionResponseMessage::new()
+        .content("Error reading ghost train figures. Please try again later.".to_string())
+        .flags(InteractionResponseFlags::EPHEMERAL);
+    }
+  };
+
+  let embed = match route {
+    Some(route) => line_embed(&rates, route),
+    None => overview_embed(&rates),
+  };
+  CreateInteractionResponseMessage::new().add_embed(embed.footer(CreateEmbedFooter::new(format!(
+    "Ghosts are trips shown from the schedule whose train never appeared. Last {PERIOD_DAYS} days."
+  ))))
+}
+
+fn overview_embed(rates: &GhostRates) -> CreateEmbed {
+  let mut lines: Vec<(LRouteCode, i64, i64)> = LRouteCode::ALL
+    .iter()
+    .map(|route| {
+      let (trips, ghosts) = rates.totals(*route, 0..24);
+      (*route, trips, ghosts)
+    })
+    .filter(|(_, trips, _)| *trips > 0)
+    .collect();
+  // Worst lines first.
+  lines.sort_by(|a, b| (b.2 * a.1).cmp(&(a.2 * b.1)));
+  CreateEmbed::new()
+    .title("Ghost trains by line")
+    .description(
+      lines
+        .iter()
+        .map(|(route, trips, ghosts)| {
+          let worst = TIME_PERIODS
+            .iter()
+            .filter_map(|(name, hours)| Some((name, rates.rate(*route, hours.clone())?)))
+            .filter(|(_, rate)| *rate > 0.0)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(name, rate)| {
+              format!(", worst in the {} ({})", name.to_lowercase(), percent(rate))
+            })
+            .unwrap_or_default();
+          format!(
+            "**{}**: {} of {trips} scheduled trips{worst}",
+            LRouteName::from(*route),
+            percent(share(*ghosts, *trips))
+          )
+        })
+        .collect::<Vec<_>>()
+        .join("\n"),
+    )
+}
+
+fn line_embed(rates: &GhostRates, route: LRouteCode) -> CreateEmbed {
+  let line = LRouteName::from(route);
+  let (trips, ghosts) = rates.totals(route, 0..24);
+  let mut embed = CreateEmbed::new()
+    .title(format!("Ghost trains on the {line}"))
+    .color(line.color_value());
+  if trips == 0 {
+    return embed.description(format!(
+      "No scheduled-only trips on the {line} have been seen."
+    ));
+  }
+  embed = embed.description(format!(
+    "{} of {trips} scheduled trips never ran ({ghosts} ghosts).",
+    percent(share(ghosts, trips))
+  ));
+  for (name, hours) in TIME_PERIODS {
+    let (trips, ghosts) = rates.totals(route, hours.clone());
+    embed = embed.field(
+      format!("{name} ({})", hour_range(hours.start, hours.end)),
+      if trips == 0 {
+        "No scheduled trips seen".to_string()
+      } else {
+        format!("{} of {trips}", percent(share(ghosts, trips)))
+      },
+      true,
+    );
+  }
+  let mut hours: Vec<(i32, f64)> = (0..24)
+    .filter_map(|hour| Some((hour, rates.rate(route, hour..hour + 1)?)))
+    .filter(|(_, rate)| *rate > 0.0)
+    .collect();
+  hours.sort_by(|a, b| b.1.total_cmp(&a.1));
+  if !hours.is_empty() {
+    embed = embed.field(
+      "Worst hours",
+      hours
+        .iter()
+        .take(3)
+        .map(|(hour, rate)| format!("{}: {}", hour_range(*hour, hour + 1), percent(*rate)))
+        .collect::<Vec<_>>()
+        .join("\n"),
+      false,
+    );
+  }
+  embed
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn share(ghosts: i64, trips: i64) -> f64 {
+  if trips == 0 {
+    0.0
+  } else {
+    ghosts as f64 / trips as f64
+  }
+}
+
+fn percent(rate: f64) -> String {
+  format!("{:.0}%", rate * 100.0)
+}
+
+/// Formats Chicago hours like "5-9 AM" or "3-7 PM".
+fn hour_range(start: i32, end: i32) -> String {
+  let label = |hour: i32| match hour % 24 {
+    0 => (12, "AM"),
+    h @ 1..=11 => (h, "AM"),
+    12 => (12, "PM"),
+    h => (h - 12, "PM"),
+  };
+  let (start_hour, start_half) = label(start);
+  let (end_hour, end_half) = label(end);
+  if start_half == end_half && start_hour != 12 {
+    format!("{start_hour}-{end_hour} {end_half}")
+  } else {
+    format!("{start_hour} {start_half}-{end_hour} {end_half}")
+  }
+}
+
+pub fn register() -> CreateCommand {
+  let line_option = LRouteCode::ALL.iter().fold(
+    CreateCommandOption::new(
+      serenity::all::CommandOptionType::String,
+      "line",
+      "Rail line to break down by time of day",
+    ),
+    |option, code| option.add_string_choice(LRouteName::from(*code).to_string(), code.to_string()),
+  );
+  CreateCommand::new("ghosts")
+    .add_option(line_option)
+    .add_integration_type(serenity::all::InstallationContext::Guild)
+    .add_integration_type(serenity::all::InstallationContext::User)
+    .description("Shows how often scheduled trains never turn up.")
+}
@@ -0,0 +1,120 @@
+use crate::commands::pagination::{self, Listing};
+use crate::cta::analysis::headways::{self, DirectionHeadways, Spacing};
+use crate::cta::analysis::running_times::RunningTimes;
+use crate::cta::traintracker::{LRouteCode, LRouteName};
+use crate::{CTAShared, CTASharedData};
+use serenity::all::{
+  Context, CreateCommandOption, CreateInteractionResponseMessage, InteractionResponseFlags,
+};
+use serenity::builder::CreateCommand;
+use serenity::model::application::{ResolvedOption, ResolvedValue};
+
+pub async fn run<'a>(
+  ctx: &Context,
+  options: &'a [ResolvedOption<'a>],
+) -> CreateInteractionResponseMessage {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  let Some(ResolvedOption {
+    value: ResolvedValue::String(val),
+    ..
+  }) = options.first()
+  else {
+    return CreateInteractionResponseMessage::new().content("Options not provided.".to_string());
+  };
+  let Ok(route) = val.parse::<LRouteCode>() else {
+    return CreateInteractionResponseMessage::new()
+      .content(format!("Unknown line: {val}"))
+      .flags(InteractionResponseFlags::EPHEMERAL);
+  };
+
+  pagination::respond(data, "headways", &route.to_string()).await
+}
+
+/// Spacing between trains on a line, one direction after the other. `args` is the line.
+pub async fn headways_listing(data: &CTASharedData, args: &str) -> Result<Listing, String> {
+  let route = args
+    .parse::<LRouteCode>()
+    .map_err(|_| format!("Unknown line: {args}"))?;
+  let line = LRouteName::from(route);
+  let fleet = data
+    .traintracker
+    .fleet(&[route])
+    .await
+    .map_err(|err| format!("Error getting train positions: {err}"))?;
+  let running_times = RunningTimes::new(data.gtfs.stop_patterns(&route.to_string()));
+  let directions = headways::headways(&fleet, route, &running_times);
+  Ok(Listing {
+    title: format!("Spacing between {line} trains"),
+    items: directions.iter().flat_map(direction_items).collect(),
+    separator: "\n",
+    footer: Some(format!(
+      "Positions as of {}",
+      fleet.timestamp.format("%-I:%M %p")
+    )),
+    color: Some(line.color_value()),
+    empty: format!("There are no active trains on the {line}."),
+    sorts: &[],
+  })
+}
+
+/// A heading for the direction, then a line for each pair of trains.
+fn direction_items(direction: &DirectionHeadways) -> Vec<String> {
+  let count = |spacing: Spacing| {
+    direction
+      .headways
+      .iter()
+      .filter(|h| h.spacing == spacing)
+      .count()
+  };
+  let mut items = vec![format!(
+    "**{} trains toward {}** | Typical spacing: {} | {} bunched, {} gaps",
+    direction.trains.len(),
+    direction.toward,
+    direction
+      .median_minutes
+      .map_or("n/a".to_string(), |m| format!("{m:.0} min")),
+    count(Spacing::Bunched),
+    count(Spacing::Gap)
+  )];
+  if direction.headways.is_empty() {
+    items.push(format!(
+      "Only one train is running toward {}.",
+      direction.toward
+    ));
+  }
+  items.extend(direction.headways.iter().map(|headway| {
+    format!(
+      "**{:.0} min** #{:0>3} ({}) to #{:0>3} ({}){}",
+      headway.minutes,
+      headway.ahead.run_number,
+      headway.ahead.next_station_name,
+      headway.behind.run_number,
+      headway.behind.next_station_name,
+      match headway.spacing {
+        Spacing::Normal => "",
+        Spacing::Bunched => " :red_circle: Bunched",
+        Spacing::Gap => " :warning: Service gap",
+      }
+    )
+  }));
+  items
+}
+
+pub fn register() -> CreateCommand {
+  let line_option = LRouteCode::ALL.iter().fold(
+    CreateCommandOption::new(
+      serenity::all::CommandOptionType::String,
+      "line",
+      "Rail line",
+    )
+    .required(true),
+    |option, code| option.add_string_choice(LRouteName::from(*code).to_string(), code.to_string()),
+  );
+  CreateCommand::new("headways")
+    .add_option(line_option)
+    .add_integration_type(serenity::all::InstallationContext::Guild)
+    .add_integration_type(serenity::all::InstallationContext::User)
+    .description("Shows the spacing between trains on a line and points out service gaps.")
+}
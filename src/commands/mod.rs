@@ -1,17 +1,30 @@
+use std::future::Future;
 use std::str::FromStr;
 
 use serenity::all::Command;
+use serenity::all::CommandInteraction;
 use serenity::all::Context;
+use serenity::all::EditInteractionResponse;
 use serenity::all::Guild;
 use serenity::all::GuildId;
+pub mod accuracy;
 pub mod alerts;
 pub mod arrivals;
 pub mod autocomplete;
 pub mod broadcast;
 pub mod bus;
+pub mod fleet;
 pub mod get_train;
+pub mod ghosts;
+pub mod headways;
+pub mod pagination;
 pub mod ping;
+pub mod reliability;
 pub mod route_name;
+pub mod settings;
+pub mod trains;
+pub mod traveltime;
+pub mod watch;
 
 pub struct BotCommand {}
 
@@ -26,6 +39,15 @@ pub async fn initialize(context: Context) {
       get_train::register(),
       bus::register(),
       arrivals::register(),
+      trains::register(),
+      headways::register(),
+      accuracy::register(),
+      reliability::register(),
+      ghosts::register(),
+      traveltime::register(),
+      fleet::register(),
+      settings::register(),
+      watch::register(),
     ],
   )
   .await
@@ -61,3 +83,19 @@ pub async fn initialize(context: Context) {
   }
   println!("Finished registering global commands");
 }
+
+/// Answers a command whose reply takes longer than the 3 seconds Discord waits for one. Discord is
+/// told a reply is coming, then it's filled in once `reply` is ready.
+pub async fn respond_later(
+  ctx: &Context,
+  command: &CommandInteraction,
+  reply: impl Future<Output = EditInteractionResponse>,
+) {
+  if let Err(why) = command.defer(&ctx.http).await {
+    println!("Cannot defer slash command: {why}");
+    return;
+  }
+  if let Err(why) = command.edit_response(&ctx.http, reply.await).await {
+    println!("Cannot respond to slash command: {why}");
+  }
+}
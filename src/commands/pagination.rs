@@ -0,0 +1,231 @@
+use serenity::all::{
+  ButtonStyle, ComponentInteraction, ComponentInteractionDataKind, Context, CreateActionRow,
+  CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+  CreateInteractionResponseMessage, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption,
+  InteractionResponseFlags,
+};
+
+use crate::commands::{alerts, bus, headways, trains};
+use crate::{util, CTAShared, CTASharedData};
+
+/// Discord allows at most this many characters in an embed description.
+const MAX_PAGE_CHARS: usize = 4096;
+/// Most items shown on one page, so short lines don't make for a wall of text.
+const MAX_PAGE_ITEMS: usize = 25;
+
+/// A long list shown a page at a time, with buttons to move between pages.
+///
+/// Nothing is kept between page turns. The custom IDs on the buttons say which list it is, how it
+/// is sorted, which page to show and whatever the list needs to build itself again, so each turn
+/// fetches the list fresh.
+pub struct Listing {
+  pub title: String,
+  pub items: Vec<String>,
+  /// Goes between items, like a blank line between longer ones.
+  pub separator: &'static str,
+  pub footer: Option<String>,
+  /// Embed color, like a rail line's.
+  pub color: Option<u32>,
+  /// Shown instead of an embed when there are no items.
+  pub empty: String,
+  /// Ways the list can be sorted, as key and label. The first is used when none is picked.
+  pub sorts: &'static [(&'static str, &'static str)],
+}
+
+/// Builds the list a custom ID refers to. Lists are named here so page turns can find them.
+async fn listing(
+  data: &CTASharedData,
+  list: &str,
+  args: &str,
+  sort: &str,
+) -> Result<Listing, String> {
+  match list {
+    "alerts" => alerts::alerts_listing(data).await,
+    "bus_vehicles" => bus::vehicles_listing(data, args, sort).await,
+    "headways" => headways::headways_listing(data, args).await,
+    "trains" => trains::trains_listing(data, args).await,
+    _ => Err("This list can't be shown anymore.".to_string()),
+  }
+}
+
+/// First page of a list, for replying to a command.
+///
+/// `args` is whatever the list needs to build itself again. It goes last in the custom IDs, which
+/// Discord caps at 100 characters, so it has to be short.
+pub async fn respond(
+  data: &CTASharedData,
+  list: &str,
+  args: &str,
+) -> CreateInteractionResponseMessage {
+  match listing(data, list, args, "").await {
+    Ok(listing) => page_message(&listing, list, args, "", 0),
+    Err(message) => CreateInteractionResponseMessage::new()
+      .content(message)
+      .flags(InteractionResponseFlags::EPHEMERAL),
+  }
+}
+
+/// Handles the page buttons, with custom IDs like `page:go/{list}/{sort}/{page}/{args}`, and the
+/// sort menu, with custom IDs like `page:sort/{list}/{args}`.
+pub async fn turn(ctx: &Context, component: &ComponentInteraction) -> CreateInteractionResponse {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  let state = component.data.custom_id.as_str();
+  let (list, sort, page, args) = if let Some(state) = state.strip_prefix("page:go/") {
+    let mut parts = state.splitn(4, '/');
+    let list = parts.next().unwrap_or_default();
+    let sort = parts.next().unwrap_or_default();
+    let page = parts.next().and_then(|p| p.parse().ok()).unwrap_or(0);
+    (
+      list,
+      sort.to_string(),
+      page,
+      parts.next().unwrap_or_default(),
+    )
+  } else if let Some(state) = state.strip_prefix("page:sort/") {
+    let (list, args) = state.split_once('/').unwrap_or((state, ""));
+    let sort = match &component.data.kind {
+      ComponentInteractionDataKind::StringSelect { values } => {
+        values.first().cloned().unwrap_or_default()
+      }
+      _ => String::new(),
+    };
+    (list, sort, 0, args)
+  } else {
+    return CreateInteractionResponse::Acknowledge;
+  };
+
+  match listing(data, list, args, &sort).await {
+    Ok(listing) => {
+      CreateInteractionResponse::UpdateMessage(page_message(&listing, list, args, &sort, page))
+    }
+    // Leave the list as it was, since the error is likely to pass.
+    Err(message) => CreateInteractionResponse::Message(
+      CreateInteractionResponseMessage::new()
+        .content(message)
+        .flags(InteractionResponseFlags::EPHEMERAL),
+    ),
+  }
+}
+
+fn page_message(
+  listing: &Listing,
+  list: &str,
+  args: &str,
+  sort: &str,
+  page: usize,
+) -> CreateInteractionResponseMessage {
+  // Replacing a page of the list, so anything left from the last one has to go.
+  let message = CreateInteractionResponseMessage::new()
+    .embeds(Vec::new())
+    .components(Vec::new());
+  if listing.items.is_empty() {
+    return message.content(listing.empty.clone());
+  }
+  let sort = if listing.sorts.iter().any(|(key, _)| *key == sort) {
+    sort
+  } else {
+    listing.sorts.first().map_or("", |(key, _)| key)
+  };
+  let pages = pages(&listing.items, listing.separator.len());
+  let page = page.min(pages.len() - 1);
+
+  let mut embed = CreateEmbed::new().title(listing.title.clone()).description(
+    pages[page]
+      .iter()
+      .map(|item| util::truncate(item, MAX_PAGE_CHARS))
+      .collect::<Vec<_>>()
+      .join(listing.separator),
+  );
+  if let Some(footer) = &listing.footer {
+    embed = embed.footer(CreateEmbedFooter::new(footer));
+  }
+  if let Some(color) = listing.color {
+    embed = embed.color(color);
+  }
+
+  let mut rows = Vec::new();
+  if pages.len() > 1 {
+    let go = |to: usize| format!("page:go/{list}/{sort}/{to}/{args}");
+    rows.push(CreateActionRow::Buttons(vec![
+      CreateButton::new(go(page.saturating_sub(1)))
+        .label("Previous")
+        .style(ButtonStyle::Secondary)
+        .disabled(page == 0),
+      CreateButton::new("page:count")
+        .label(format!("Page {} of {}", page + 1, pages.len()))
+        .style(ButtonStyle::Secondary)
+        .disabled(true),
+      CreateButton::new(go((page + 1).min(pages.len() - 1)))
+        .label("Next")
+        .style(ButtonStyle::Secondary)
+        .disabled(page + 1 == pages.len()),
+    ]));
+  }
+  if listing.sorts.len() > 1 {
+    rows.push(CreateActionRow::SelectMenu(
+      CreateSelectMenu::new(
+        format!("page:sort/{list}/{args}"),
+        CreateSelectMenuKind::String {
+          options: listing
+            .sorts
+            .iter()
+            .map(|(key, label)| {
+              CreateSelectMenuOption::new(*label, *key).default_selection(*key == sort)
+            })
+            .collect(),
+        },
+      )
+      .placeholder("Sort by"),
+    ));
+  }
+  message.add_embed(embed).components(rows)
+}
+
+/// Splits items into pages that fit in an embed description, with `separator_len` characters
+/// between items. Every page has at least one item, even one too long to fit, which is cut down
+/// when it's shown.
+fn pages(items: &[String], separator_len: usize) -> Vec<&[String]> {
+  let mut pages = Vec::new();
+  let mut start = 0;
+  let mut chars = 0;
+  for (i, item) in items.iter().enumerate() {
+    let len = item.chars().count() + if i > start { separator_len } else { 0 };
+    if i > start && (chars + len > MAX_PAGE_CHARS || i - start == MAX_PAGE_ITEMS) {
+      pages.push(&items[start..i]);
+      start = i;
+      chars = item.chars().count();
+    } else {
+      chars += len;
+    }
+  }
+  if start < items.len() {
+    pages.push(&items[start..]);
+  }
+  pages
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_pages() {
+    let short: Vec<String> = (0..60).map(|i| i.to_string()).collect();
+    let split = pages(&short, 1);
+    assert_eq!(
+      split.iter().map(|p| p.len()).collect::<Vec<_>>(),
+      vec![25, 25, 10]
+    );
+
+    let long = vec!["a".repeat(3000), "b".repeat(1000), "c".repeat(100)];
+    let split = pages(&long, 2);
+    assert_eq!(split.len(), 2);
+    assert_eq!(split[0].len(), 2);
+    assert_eq!(split[1][0], "c".repeat(100));
+
+    assert_eq!(pages(&["d".repeat(5000)], 1).len(), 1);
+    assert!(pages(&[], 1).is_empty());
+  }
+}
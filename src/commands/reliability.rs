@@ -0,0 +1,140 @@
+use std::fmt::Write;
+
+use crate::arrivaldisplay;
+use crate::cta::analysis::reliability::{line_scores, service_date, service_day_bounds};
+use crate::db::{self, DBLineReliability};
+use crate::CTAShared;
+use serenity::all::{
+  Context, CreateAttachment, CreateCommandOption, CreateEmbed, CreateEmbedFooter,
+  CreateInteractionResponseMessage, InteractionResponseFlags,
+};
+use serenity::builder::CreateCommand;
+use serenity::model::application::{ResolvedOption, ResolvedValue};
+
+const DEFAULT_DAYS: i64 = 7;
+const MAX_DAYS: i64 = 30;
+
+pub async fn run<'a>(
+  ctx: &Context,
+  options: &'a [ResolvedOption<'a>],
+) -> CreateInteractionResponseMessage {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  let days = options
+    .iter()
+    .find_map(|o| match o.value {
+      ResolvedValue::Integer(val) if o.name == "days" => Some(val),
+      _ => None,
+    })
+    .unwrap_or(DEFAULT_DAYS)
+    .clamp(1, MAX_DAYS);
+  let csv = options
+    .iter()
+    .any(|o| o.name == "csv" && matches!(o.value, ResolvedValue::Boolean(true)));
+
+  let today = service_date(chrono::Utc::now().timestamp());
+  let first_day = today
+    .checked_sub_days(chrono::Days::new(days.unsigned_abs()))
+    .unwrap_or(today);
+  let rows = match db::get_line_reliability(&data.db, service_day_bounds(first_day).0).await {
+    Ok(rows) if rows.is_empty() => {
+      return CreateInteractionResponseMessage::new()
+        .content(
+          "There are no reliability figures yet. They're worked out each day from recorded train positions."
+            .to_string(),
+        )
+        .flags(InteractionResponseFlags::EPHEMERAL);
+    }
+    Ok(rows) => rows,
+    Err(e) => {
+      println!("Error reading line reliability: {e}");
+      return CreateInteractionResponseMessage::new()
+        .content("Error reading reliability figures. Please try again later.".to_string())
+        .flags(InteractionResponseFlags::EPHEMERAL);
+    }
+  };
+
+  let title = format!(
+    "Rail reliability, {} to {}",
+    first_day.format("%b %-d"),
+    service_date(rows.iter().map(|r| r.service_day).max().unwrap_or_default()).format("%b %-d")
+  );
+  let mut response = CreateInteractionResponseMessage::new();
+  match arrivaldisplay::render_doc(&arrivaldisplay::reliability_chart(
+    title.clone(),
+    &line_scores(&rows),
+  )) {
+    Ok(png) => {
+      response = response
+        .add_embed(
+          CreateEmbed::new()
+            .title(title)
+            .image("attachment://reliability.png")
+            .footer(CreateEmbedFooter::new(
+              "Scheduled headways assume the scheduled trips were spread evenly over the hours each line ran.",
+            )),
+        )
+        .add_file(CreateAttachment::bytes(png, "reliability.png"));
+    }
+    Err(e) => {
+      println!("Error rendering reliability chart: {e}");
+      response = response.content("Couldn't draw the reliability chart.".to_string());
+    }
+  }
+  if csv {
+    response = response.add_file(CreateAttachment::bytes(
+      to_csv(&rows).into_bytes(),
+      "reliability.csv",
+    ));
+  }
+  response
+}
+
+fn to_csv(rows: &[DBLineReliability]) -> String {
+  let mut csv = String::from(
+    "service_date,line,trips_scheduled,trips_run,avg_headway_min,scheduled_headway_min,delayed_pct\n",
+  );
+  for row in rows {
+    writeln!(
+      csv,
+      "{},{},{},{},{},{},{:.1}",
+      service_date(row.service_day),
+      row.route,
+      row.trips_scheduled,
+      row.trips_run,
+      row
+        .avg_headway
+        .map(|h| format!("{:.1}", h / 60.0))
+        .unwrap_or_default(),
+      row
+        .scheduled_headway
+        .map(|h| format!("{:.1}", h / 60.0))
+        .unwrap_or_default(),
+      row.delayed_share * 100.0
+    )
+    .unwrap();
+  }
+  csv
+}
+
+pub fn register() -> CreateCommand {
+  CreateCommand::new("reliability")
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::Integer,
+        "days",
+        "How many days to cover (default 7)",
+      )
+      .min_int_value(1)
+      .max_int_value(30),
+    )
+    .add_option(CreateCommandOption::new(
+      serenity::all::CommandOptionType::Boolean,
+      "csv",
+      "Also attach the daily figures as a CSV file",
+    ))
+    .add_integration_type(serenity::all::InstallationContext::Guild)
+    .add_integration_type(serenity::all::InstallationContext::User)
+    .description("Shows how reliably each rail line has run compared with its schedule.")
+}
@@ -16,8 +16,11 @@ pub async fn run<'a>(
     ..
   }) = options.first()
   {
-    let route_name = gtfs.get_route_name(id);
-    return CreateInteractionResponseMessage::new().content(route_name.to_string());
+    return CreateInteractionResponseMessage::new().content(
+      gtfs
+        .get_route_name(id)
+        .unwrap_or_else(|| format!("No route has the ID '{id}'.")),
+    );
   }
   CreateInteractionResponseMessage::new().content("Invalid Route ID".to_string())
 }
@@ -0,0 +1,305 @@
+use serenity::all::{
+  ChannelId, ChannelType, CommandInteraction, Context, CreateCommandOption,
+  CreateInteractionResponseMessage, InteractionContext, Permissions, ResolvedValue,
+};
+use serenity::builder::CreateCommand;
+use serenity::model::application::ResolvedOption;
+
+use crate::{db, CTAShared, CTASharedData};
+
+#[allow(clippy::cast_possible_wrap)]
+pub async fn run<'a>(
+  ctx: &Context,
+  options: &'a [ResolvedOption<'a>],
+  command: &CommandInteraction,
+) -> CreateInteractionResponseMessage {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  let Some(guild_id) = command.guild_id else {
+    return CreateInteractionResponseMessage::new()
+      .content("Settings can only be changed in a server.".to_string())
+      .ephemeral(true);
+  };
+  if let Some(
+    option @ ResolvedOption {
+      value: ResolvedValue::SubCommand(opts),
+      ..
+    },
+  ) = options.first()
+  {
+    let enabled = opts.iter().find_map(|o| match o.value {
+      ResolvedValue::Boolean(val) if o.name == "enabled" => Some(val),
+      _ => None,
+    });
+    let channel = opts
+      .iter()
+      .find_map(|o| match &o.value {
+        ResolvedValue::Channel(chan) if o.name == "channel" => Some(chan.id),
+        _ => None,
+      })
+      .unwrap_or(command.channel_id);
+    match option.name {
+      "anomalies" => {
+        let enabled = enabled.unwrap_or(true);
+        return match db::set_guild_anomalies(
+          &data.db,
+          guild_id.get() as i64,
+          enabled,
+          Some(channel.get() as i64),
+        )
+        .await
+        {
+          Ok(()) if enabled => CreateInteractionResponseMessage::new()
+            .content(format!(
+              "Unusual train reports will be posted in <#{channel}>."
+            ))
+            .ephemeral(true),
+          Ok(()) => CreateInteractionResponseMessage::new()
+            .content("Unusual train reports have been turned off.".to_string())
+            .ephemeral(true),
+          Err(why) => {
+            println!("Error saving anomaly settings: {why}");
+            CreateInteractionResponseMessage::new()
+              .content("Error saving settings. Please try again later.".to_string())
+              .ephemeral(true)
+          }
+        };
+      }
+      "gaps" => {
+        let enabled = enabled.unwrap_or(true);
+        return match db::set_guild_gaps(
+          &data.db,
+          guild_id.get() as i64,
+          enabled,
+          Some(channel.get() as i64),
+        )
+        .await
+        {
+          Ok(()) if enabled => CreateInteractionResponseMessage::new()
+            .content(format!(
+              "Possible service disruptions will be posted in <#{channel}>."
+            ))
+            .ephemeral(true),
+          Ok(()) => CreateInteractionResponseMessage::new()
+            .content("Possible service disruption reports have been turned off.".to_string())
+            .ephemeral(true),
+          Err(why) => {
+            println!("Error saving service gap settings: {why}");
+            CreateInteractionResponseMessage::new()
+              .content("Error saving settings. Please try again later.".to_string())
+              .ephemeral(true)
+          }
+        };
+      }
+      "bulletins" => {
+        return bulletins(data, guild_id.get() as i64, enabled, channel, opts).await;
+      }
+      "bunching" => {
+        return bunching(data, guild_id.get() as i64, enabled, channel, opts).await;
+      }
+      _ => {}
+    }
+  }
+  CreateInteractionResponseMessage::new()
+    .content("Internal error with command".to_string())
+    .ephemeral(true)
+}
+
+#[allow(clippy::cast_possible_wrap)]
+async fn bulletins(
+  data: &CTASharedData,
+  guild_id: i64,
+  enabled: Option<bool>,
+  channel: ChannelId,
+  opts: &[ResolvedOption<'_>],
+) -> CreateInteractionResponseMessage {
+  let routes = match route_option(data, opts) {
+    Ok(routes) => routes,
+    Err(message) => {
+      return CreateInteractionResponseMessage::new()
+        .content(message)
+        .ephemeral(true)
+    }
+  };
+  let enabled = enabled.unwrap_or(true);
+  match db::set_guild_bulletins(
+    &data.db,
+    guild_id,
+    enabled,
+    Some(channel.get() as i64),
+    routes.as_deref(),
+  )
+  .await
+  {
+    Ok(guild) if enabled => match guild.bus_routes.filter(|r| !r.is_empty()) {
+      Some(routes) => CreateInteractionResponseMessage::new()
+        .content(format!(
+          "Service bulletins and detours for routes {} will be posted in <#{channel}>.",
+          routes.join(", ")
+        ))
+        .ephemeral(true),
+      None => CreateInteractionResponseMessage::new()
+        .content("Pick which bus routes to follow with the routes option.".to_string())
+        .ephemeral(true),
+    },
+    Ok(_) => CreateInteractionResponseMessage::new()
+      .content("Bus service bulletins and detours have been turned off.".to_string())
+      .ephemeral(true),
+    Err(why) => {
+      println!("Error saving bus bulletin settings: {why}");
+      CreateInteractionResponseMessage::new()
+        .content("Error saving settings. Please try again later.".to_string())
+        .ephemeral(true)
+    }
+  }
+}
+
+#[allow(clippy::cast_possible_wrap)]
+async fn bunching(
+  data: &CTASharedData,
+  guild_id: i64,
+  enabled: Option<bool>,
+  channel: ChannelId,
+  opts: &[ResolvedOption<'_>],
+) -> CreateInteractionResponseMessage {
+  let routes = match route_option(data, opts) {
+    Ok(routes) => routes,
+    Err(message) => {
+      return CreateInteractionResponseMessage::new()
+        .content(message)
+        .ephemeral(true)
+    }
+  };
+  let enabled = enabled.unwrap_or(true);
+  match db::set_guild_bunching(
+    &data.db,
+    guild_id,
+    enabled,
+    Some(channel.get() as i64),
+    routes.as_deref(),
+  )
+  .await
+  {
+    Ok(guild) if enabled => match guild.bunching_routes.filter(|r| !r.is_empty()) {
+      Some(routes) => CreateInteractionResponseMessage::new()
+        .content(format!(
+          "Chronic bus bunching on routes {} will be posted in <#{channel}>.",
+          routes.join(", ")
+        ))
+        .ephemeral(true),
+      None => CreateInteractionResponseMessage::new()
+        .content("Pick which bus routes to follow with the routes option.".to_string())
+        .ephemeral(true),
+    },
+    Ok(_) => CreateInteractionResponseMessage::new()
+      .content("Bus bunching reports have been turned off.".to_string())
+      .ephemeral(true),
+    Err(why) => {
+      println!("Error saving bus bunching settings: {why}");
+      CreateInteractionResponseMessage::new()
+        .content("Error saving settings. Please try again later.".to_string())
+        .ephemeral(true)
+    }
+  }
+}
+
+/// Reads the comma separated `routes` option, making sure every route exists.
+fn route_option(
+  data: &CTASharedData,
+  opts: &[ResolvedOption<'_>],
+) -> Result<Option<Vec<String>>, String> {
+  let routes = opts.iter().find_map(|o| match o.value {
+    ResolvedValue::String(val) if o.name == "routes" => Some(
+      val
+        .split(',')
+        .map(|rt| rt.trim().to_uppercase())
+        .filter(|rt| !rt.is_empty())
+        .collect::<Vec<_>>(),
+    ),
+    _ => None,
+  });
+  if let Some(routes) = &routes {
+    let unknown: Vec<&str> = routes
+      .iter()
+      .filter(|rt| data.gtfs.gtfs_data.get_route(rt).is_err())
+      .map(String::as_str)
+      .collect();
+    if !unknown.is_empty() {
+      return Err(format!("Unknown bus routes: {}", unknown.join(", ")));
+    }
+  }
+  Ok(routes)
+}
+
+fn routes_option() -> CreateCommandOption {
+  CreateCommandOption::new(
+    serenity::all::CommandOptionType::String,
+    "routes",
+    "Bus route numbers, separated by commas. ex: '20,49,X49'",
+  )
+}
+
+fn enabled_option() -> CreateCommandOption {
+  CreateCommandOption::new(
+    serenity::all::CommandOptionType::Boolean,
+    "enabled",
+    "Turn these notifications on or off",
+  )
+  .required(true)
+}
+
+fn channel_option() -> CreateCommandOption {
+  CreateCommandOption::new(
+    serenity::all::CommandOptionType::Channel,
+    "channel",
+    "Channel to post in. Defaults to this channel.",
+  )
+  .channel_types(vec![ChannelType::Text, ChannelType::News])
+}
+
+pub fn register() -> CreateCommand {
+  CreateCommand::new("settings")
+    .description("Configure notifications for this server.")
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::SubCommand,
+        "anomalies",
+        "Post trains with unusual run numbers or routings",
+      )
+      .add_sub_option(enabled_option())
+      .add_sub_option(channel_option()),
+    )
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::SubCommand,
+        "gaps",
+        "Post possible disruptions when trains stop reaching part of a line",
+      )
+      .add_sub_option(enabled_option())
+      .add_sub_option(channel_option()),
+    )
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::SubCommand,
+        "bulletins",
+        "Post new service bulletins and detours for bus routes",
+      )
+      .add_sub_option(enabled_option())
+      .add_sub_option(routes_option())
+      .add_sub_option(channel_option()),
+    )
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::SubCommand,
+        "bunching",
+        "Post bus routes that keep bunching",
+      )
+      .add_sub_option(enabled_option())
+      .add_sub_option(routes_option())
+      .add_sub_option(channel_option()),
+    )
+    .default_member_permissions(Permissions::MANAGE_GUILD)
+    .add_integration_type(serenity::all::InstallationContext::Guild)
+    .contexts(vec![InteractionContext::Guild])
+}
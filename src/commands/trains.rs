@@ -0,0 +1,121 @@
+use crate::commands::pagination::{self, Listing};
+use crate::cta::traintracker::{LRouteCode, LRouteName};
+use crate::{util, CTAShared, CTASharedData};
+use serenity::all::{
+  Context, CreateCommandOption, CreateInteractionResponseMessage, InteractionResponseFlags,
+};
+use serenity::builder::CreateCommand;
+use serenity::model::application::{ResolvedOption, ResolvedValue};
+
+pub async fn run<'a>(
+  ctx: &Context,
+  options: &'a [ResolvedOption<'a>],
+) -> CreateInteractionResponseMessage {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  let Some(val) = options.iter().find_map(|o| match o.value {
+    ResolvedValue::String(route) if o.name == "route" => Some(route),
+    _ => None,
+  }) else {
+    return CreateInteractionResponseMessage::new().content("Options not provided.".to_string());
+  };
+  let Ok(route) = val.parse::<LRouteCode>() else {
+    return CreateInteractionResponseMessage::new()
+      .content(format!("Unknown line: {val}"))
+      .flags(InteractionResponseFlags::EPHEMERAL);
+  };
+  let delayed_only = options
+    .iter()
+    .any(|o| o.name == "delayed_only" && matches!(o.value, ResolvedValue::Boolean(true)));
+
+  let args = format!("{}/{route}", u8::from(delayed_only));
+  pagination::respond(data, "trains", &args).await
+}
+
+/// Trains on a line, in run number order. `args` is like `1/red`, whether only delayed trains are
+/// listed and then the line.
+pub async fn trains_listing(data: &CTASharedData, args: &str) -> Result<Listing, String> {
+  let (delayed_only, route) = args.split_once('/').unwrap_or(("0", args));
+  let delayed_only = delayed_only == "1";
+  let route = route
+    .parse::<LRouteCode>()
+    .map_err(|_| format!("Unknown line: {route}"))?;
+  let line = LRouteName::from(route);
+
+  let fleet = data
+    .traintracker
+    .fleet(&[route])
+    .await
+    .map_err(|err| format!("Error getting train positions: {err}"))?;
+  let mut trains: Vec<_> = fleet
+    .on_route(route)
+    .filter(|train| !delayed_only || train.is_delayed)
+    .collect();
+  trains.sort_by_key(|t| t.run_number);
+  let items = trains
+    .iter()
+    .map(|train| {
+      format!(
+        "**#{:0>3}** to {} | Next: {}{} | Heading {}{}",
+        train.run_number,
+        train.destination_name,
+        train.next_station_name,
+        if train.is_approaching {
+          " (approaching)"
+        } else {
+          ""
+        },
+        util::compass_direction(train.heading),
+        if train.is_delayed {
+          " | :warning: Delayed"
+        } else {
+          ""
+        }
+      )
+    })
+    .collect();
+
+  Ok(Listing {
+    title: format!(
+      "{} {} trains on the {line}",
+      trains.len(),
+      if delayed_only { "delayed" } else { "active" }
+    ),
+    items,
+    separator: "\n",
+    footer: Some(format!(
+      "Positions as of {}",
+      fleet.timestamp.format("%-I:%M:%S %p")
+    )),
+    color: Some(line.color_value()),
+    empty: if delayed_only {
+      format!("No trains are delayed on the {line}.")
+    } else {
+      format!("There are no active trains on the {line}.")
+    },
+    sorts: &[],
+  })
+}
+
+pub fn register() -> CreateCommand {
+  let route_option = LRouteCode::ALL.iter().fold(
+    CreateCommandOption::new(
+      serenity::all::CommandOptionType::String,
+      "route",
+      "Rail line",
+    )
+    .required(true),
+    |option, code| option.add_string_choice(LRouteName::from(*code).to_string(), code.to_string()),
+  );
+  CreateCommand::new("trains")
+    .add_option(route_option)
+    .add_option(CreateCommandOption::new(
+      serenity::all::CommandOptionType::Boolean,
+      "delayed_only",
+      "Only list delayed trains",
+    ))
+    .add_integration_type(serenity::all::InstallationContext::Guild)
+    .add_integration_type(serenity::all::InstallationContext::User)
+    .description("Lists every active train on a line.")
+}
@@ -0,0 +1,305 @@
+use crate::cta::analysis::traveltime::{journeys, ride_time, Leg};
+use crate::cta::traintracker::{ArrivalsParameters, LRouteName, MapOrStopID};
+use crate::db::{self, DBTravelTime};
+use crate::{util, CTAShared, CTASharedData};
+use chrono::NaiveDateTime;
+use serenity::all::{
+  Context, CreateCommandOption, CreateEmbed, CreateEmbedFooter, CreateInteractionResponseMessage,
+  EditInteractionResponse, InteractionResponseFlags,
+};
+use serenity::builder::CreateCommand;
+use serenity::model::application::{ResolvedOption, ResolvedValue};
+
+/// How far back recorded positions are used for typical times.
+const HISTORY_DAYS: i64 = 14;
+/// Trains arriving at the boarding station that are followed to find one heading the right way.
+const FOLLOW_LIMIT: usize = 3;
+
+/// The soonest train riding a leg right now.
+struct CurrentRide {
+  run_number: i32,
+  leaves_at: NaiveDateTime,
+  ride_secs: i64,
+}
+
+/// The stations to ride between and the ways to get there.
+pub struct Trip {
+  from: i32,
+  to: i32,
+  journeys: Vec<Vec<Leg>>,
+}
+
+/// Works out the ways to ride between the stations picked. Errors are a reply for the user, sent
+/// straight away.
+pub async fn plan<'a>(
+  ctx: &Context,
+  options: &'a [ResolvedOption<'a>],
+) -> Result<Trip, CreateInteractionResponseMessage> {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  let option = |name: &str| {
+    options.iter().find_map(|o| match o.value {
+      ResolvedValue::String(val) if o.name == name => Some(val),
+      _ => None,
+    })
+  };
+  let (Some(from), Some(to)) = (option("from"), option("to")) else {
+    return Err(
+      CreateInteractionResponseMessage::new().content("Options not provided.".to_string()),
+    );
+  };
+  let from = find_station(data, from).map_err(error)?;
+  let to = find_station(data, to).map_err(error)?;
+  if from == to {
+    return Err(error("Those are the same station.".to_string()));
+  }
+  let journeys = journeys(&data.stations, from, to);
+  if journeys.is_empty() {
+    return Err(error(format!(
+      "Couldn't find a way to ride from {} to {} with at most one transfer.",
+      station_name(data, from),
+      station_name(data, to)
+    )));
+  }
+  Ok(Trip { from, to, journeys })
+}
+
+/// Typical and current times for each leg of a trip. Following trains takes several requests, so
+/// this is sent with [`respond_later`](super::respond_later).
+pub async fn run(ctx: &Context, trip: Trip) -> EditInteractionResponse {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+  let Trip { from, to, journeys } = trip;
+  let first = &journeys[0];
+
+  let mut embed = CreateEmbed::new()
+    .title(format!(
+      "{} to {}",
+      station_name(data, from),
+      station_name(data, to)
+    ))
+    .description(describe(data, &journeys))
+    .color(LRouteName::from(first[0].route).color_value());
+  let since = chrono::Utc::now().timestamp() - HISTORY_DAYS * 24 * 60 * 60;
+  let mut typical_total = Some(0.0);
+  let mut any_history = false;
+  for leg in journeys.iter().flatten() {
+    let typical = db::get_typical_travel_time(
+      &data.db,
+      &leg.route.to_string(),
+      leg.board,
+      leg.alight,
+      since,
+    )
+    .await
+    .inspect_err(|e| println!("Error reading typical travel time: {e}"))
+    .ok();
+    let median = typical.as_ref().and_then(|t| t.median);
+    any_history |= median.is_some();
+    typical_total = typical_total
+      .zip(median)
+      .map(|(total, median)| total + median);
+    let current = current_ride(data, leg).await;
+    embed = embed.field(
+      format!(
+        "{}: {} to {}",
+        LRouteName::from(leg.route),
+        station_name(data, leg.board),
+        station_name(data, leg.alight)
+      ),
+      leg_summary(typical.as_ref(), current.as_ref()),
+      false,
+    );
+  }
+  if first.len() > 1 {
+    embed = embed.field(
+      "Whole trip",
+      match typical_total {
+        Some(total) => format!(
+          "Typically **{}** on trains, plus waiting for the transfer.",
+          minutes(total)
+        ),
+        None => "No typical time, since not every leg has recorded trips.".to_string(),
+      },
+      false,
+    );
+  }
+  embed = embed.footer(CreateEmbedFooter::new(if any_history {
+    format!(
+      "Times only cover riding the train. Typical times come from the last {HISTORY_DAYS} days of train positions."
+    )
+  } else {
+    format!(
+      "Times only cover riding the train. No train positions have been recorded in the last {HISTORY_DAYS} days, so there are no typical times."
+    )
+  }));
+  EditInteractionResponse::new().add_embed(embed)
+}
+
+/// Says which lines to take, and where to change if there's a transfer.
+fn describe(data: &CTASharedData, options: &[Vec<Leg>]) -> String {
+  if let [first_leg, second_leg] = options[0].as_slice() {
+    let walk = if first_leg.alight == second_leg.board {
+      String::new()
+    } else {
+      format!(
+        " and walk through to {}",
+        station_name(data, second_leg.board)
+      )
+    };
+    format!(
+      "Change from the {} to the {} at {}{walk}.",
+      LRouteName::from(first_leg.route),
+      LRouteName::from(second_leg.route),
+      station_name(data, first_leg.alight)
+    )
+  } else {
+    format!(
+      "Direct on the {}.",
+      options
+        .iter()
+        .map(|legs| LRouteName::from(legs[0].route).to_string())
+        .collect::<Vec<_>>()
+        .join(" or ")
+    )
+  }
+}
+
+/// Resolves a station search to a map ID, or explains why it couldn't.
+fn find_station(data: &CTASharedData, search: &str) -> Result<i32, String> {
+  let stations = data.gtfs.search_stations(search);
+  match stations.as_slice() {
+    [] => Err(format!("No stations found for {search}.")),
+    [station] => station
+      .id
+      .parse()
+      .map_err(|_| format!("{search} doesn't have a usable station ID.")),
+    several => Err(format!(
+      "Several stations match {search}: {}. Please pick one from the suggestions.",
+      several
+        .iter()
+        .take(10)
+        .filter_map(|stop| stop.name.clone())
+        .collect::<Vec<_>>()
+        .join(", ")
+    )),
+  }
+}
+
+fn station_name(data: &CTASharedData, map_id: i32) -> String {
+  data
+    .gtfs
+    .get_stop_name(map_id)
+    .unwrap_or_else(|| format!("Station ID {map_id}"))
+}
+
+/// Follows the next few trains due at the boarding station until one is calling at the
+/// alighting station afterwards.
+async fn current_ride(data: &CTASharedData, leg: &Leg) -> Option<CurrentRide> {
+  let arrivals = data
+    .traintracker
+    .arrivals(ArrivalsParameters {
+      id: MapOrStopID::MapID { mapid: leg.board },
+      max: None,
+      rt: Some(leg.route.to_string()),
+    })
+    .await
+    .inspect_err(|e| println!("Error getting arrivals for travel time: {e}"))
+    .ok()?;
+  for arrival in arrivals
+    .iter()
+    .filter(|a| !a.is_scheduled)
+    .take(FOLLOW_LIMIT)
+  {
+    let etas = match data.traintracker.follow_train(arrival.run_number).await {
+      Ok(etas) => etas,
+      Err(e) => {
+        println!("Error following train for travel time: {e}");
+        continue;
+      }
+    };
+    if let Some(ride_secs) = ride_time(&etas, leg.board, leg.alight) {
+      return Some(CurrentRide {
+        run_number: arrival.run_number,
+        leaves_at: arrival.arrival_time,
+        ride_secs,
+      });
+    }
+  }
+  None
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn leg_summary(typical: Option<&DBTravelTime>, current: Option<&CurrentRide>) -> String {
+  let typical = match typical {
+    Some(DBTravelTime {
+      trips,
+      median: Some(median),
+      slow,
+    }) => format!(
+      "Typically **{}**{} from {trips} trips",
+      minutes(*median),
+      slow
+        .filter(|slow| minutes(*slow) != minutes(*median))
+        .map(|slow| format!(", up to {} on slow trips,", minutes(slow)))
+        .unwrap_or_default()
+    ),
+    Some(_) => format!("No recorded trips in the last {HISTORY_DAYS} days"),
+    None => "Couldn't read recorded trips right now".to_string(),
+  };
+  let current = match current {
+    Some(ride) => format!(
+      "Right now **{}** on #{}, {}",
+      minutes(ride.ride_secs as f64),
+      ride.run_number,
+      match util::countdown(util::minutes_until(
+        chrono::DateTime::from_timestamp(util::chicago_timestamp(ride.leaves_at), 0)
+          .unwrap_or_default()
+          .with_timezone(&chrono_tz::America::Chicago)
+      ))
+      .as_str()
+      {
+        "Due" => "leaving now".to_string(),
+        countdown => format!("leaving in {countdown}"),
+      }
+    ),
+    None => "No tracked trains are heading that way right now".to_string(),
+  };
+  format!("{typical}\n{current}")
+}
+
+fn minutes(secs: f64) -> String {
+  format!("{:.0} min", (secs / 60.0).max(1.0))
+}
+
+fn error(message: String) -> CreateInteractionResponseMessage {
+  CreateInteractionResponseMessage::new()
+    .content(message)
+    .flags(InteractionResponseFlags::EPHEMERAL)
+}
+
+pub fn register() -> CreateCommand {
+  CreateCommand::new("traveltime")
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::String,
+        "from",
+        "Station to start from",
+      )
+      .required(true)
+      .set_autocomplete(true),
+    )
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::String,
+        "to",
+        "Station to ride to",
+      )
+      .required(true)
+      .set_autocomplete(true),
+    )
+    .add_integration_type(serenity::all::InstallationContext::Guild)
+    .add_integration_type(serenity::all::InstallationContext::User)
+    .description("Estimates how long a train ride between two stations takes.")
+}
@@ -0,0 +1,211 @@
+use serenity::all::{
+  CommandInteraction, Context, CreateCommandOption, CreateEmbed, CreateInteractionResponseMessage,
+  ResolvedValue,
+};
+use serenity::builder::CreateCommand;
+use serenity::model::application::ResolvedOption;
+
+use crate::{db, CTAShared};
+
+#[allow(clippy::cast_possible_wrap, clippy::too_many_lines)]
+pub async fn run<'a>(
+  ctx: &Context,
+  options: &'a [ResolvedOption<'a>],
+  command: &CommandInteraction,
+) -> CreateInteractionResponseMessage {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+
+  let Some(
+    option @ ResolvedOption {
+      value: ResolvedValue::SubCommand(opts),
+      ..
+    },
+  ) = options.first()
+  else {
+    return error_response("Internal error with command");
+  };
+  let string_opt = |name: &str| {
+    opts.iter().find_map(|o| match o.value {
+      ResolvedValue::String(val) if o.name == name => Some(val.trim()),
+      _ => None,
+    })
+  };
+  let in_channel = opts
+    .iter()
+    .any(|o| o.name == "channel" && matches!(o.value, ResolvedValue::Boolean(true)));
+
+  // Channel watches post for the whole server, so only server managers can change them.
+  let (target_type, target_id) = if in_channel {
+    let can_manage = command
+      .member
+      .as_ref()
+      .and_then(|m| m.permissions)
+      .is_some_and(serenity::all::Permissions::manage_guild);
+    if command.guild_id.is_none() || !can_manage {
+      return error_response(
+        "You need the Manage Server permission to set up watches for a channel.",
+      );
+    }
+    ("channel", command.channel_id.get() as i64)
+  } else {
+    ("user", command.user.id.get() as i64)
+  };
+
+  match option.name {
+    "add" | "remove" => {
+      let (Some(kind), Some(id)) = (string_opt("type"), string_opt("id")) else {
+        return error_response("Options not provided.");
+      };
+      if kind == "train" && id.parse::<i32>().is_err() {
+        return error_response("Train run numbers must be numbers, like 1225.");
+      }
+      if option.name == "add" {
+        match db::add_watch(
+          &data.db,
+          kind,
+          id,
+          string_opt("label"),
+          target_type,
+          target_id,
+        )
+        .await
+        {
+          Ok(()) => CreateInteractionResponseMessage::new()
+            .content(format!(
+              "You'll be notified {} when {} #{id} enters service.",
+              if in_channel {
+                "in this channel"
+              } else {
+                "by DM"
+              },
+              if kind == "train" { "train" } else { "bus" }
+            ))
+            .ephemeral(true),
+          Err(why) => {
+            println!("Error adding watch: {why}");
+            error_response("Error saving watch. Please try again later.")
+          }
+        }
+      } else {
+        match db::remove_watch(&data.db, kind, id, target_type, target_id).await {
+          Ok(0) => error_response("That isn't on your watchlist."),
+          Ok(_) => CreateInteractionResponseMessage::new()
+            .content(format!("Removed #{id} from the watchlist."))
+            .ephemeral(true),
+          Err(why) => {
+            println!("Error removing watch: {why}");
+            error_response("Error removing watch. Please try again later.")
+          }
+        }
+      }
+    }
+    "list" => match db::get_watches_for_target(&data.db, target_type, target_id).await {
+      Ok(watches) if watches.is_empty() => error_response("Nothing is being watched."),
+      Ok(watches) => {
+        let desc = watches
+          .iter()
+          .map(|w| {
+            format!(
+              "{} #{}{}: {}",
+              if w.kind == "train" { "Train" } else { "Bus" },
+              w.identifier,
+              w.label
+                .as_ref()
+                .map(|l| format!(" ({l})"))
+                .unwrap_or_default(),
+              if w.in_service {
+                "in service"
+              } else {
+                "not in service"
+              }
+            )
+          })
+          .collect::<Vec<_>>()
+          .join("\n");
+        CreateInteractionResponseMessage::new()
+          .add_embed(CreateEmbed::new().title("Watchlist").description(desc))
+          .ephemeral(true)
+      }
+      Err(why) => {
+        println!("Error reading watchlist: {why}");
+        error_response("Error reading watchlist. Please try again later.")
+      }
+    },
+    _ => error_response("Internal error with command"),
+  }
+}
+
+fn error_response(message: &str) -> CreateInteractionResponseMessage {
+  CreateInteractionResponseMessage::new()
+    .content(message.to_string())
+    .ephemeral(true)
+}
+
+fn type_option() -> CreateCommandOption {
+  CreateCommandOption::new(
+    serenity::all::CommandOptionType::String,
+    "type",
+    "What to watch for",
+  )
+  .add_string_choice("Train run number", "train")
+  .add_string_choice("Bus vehicle ID", "bus")
+  .required(true)
+}
+
+fn id_option() -> CreateCommandOption {
+  CreateCommandOption::new(
+    serenity::all::CommandOptionType::String,
+    "id",
+    "Run number or bus vehicle ID",
+  )
+  .required(true)
+}
+
+fn channel_option() -> CreateCommandOption {
+  CreateCommandOption::new(
+    serenity::all::CommandOptionType::Boolean,
+    "channel",
+    "Use this channel's watchlist instead of your own (requires Manage Server)",
+  )
+}
+
+pub fn register() -> CreateCommand {
+  CreateCommand::new("watch")
+    .description("Get notified when a specific train or bus enters service.")
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::SubCommand,
+        "add",
+        "Add a train or bus to the watchlist",
+      )
+      .add_sub_option(type_option())
+      .add_sub_option(id_option())
+      .add_sub_option(CreateCommandOption::new(
+        serenity::all::CommandOptionType::String,
+        "label",
+        "A name for it, like 'Holiday Train'",
+      ))
+      .add_sub_option(channel_option()),
+    )
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::SubCommand,
+        "remove",
+        "Remove a train or bus from the watchlist",
+      )
+      .add_sub_option(type_option())
+      .add_sub_option(id_option())
+      .add_sub_option(channel_option()),
+    )
+    .add_option(
+      CreateCommandOption::new(
+        serenity::all::CommandOptionType::SubCommand,
+        "list",
+        "Show the watchlist",
+      )
+      .add_sub_option(channel_option()),
+    )
+    .add_integration_type(serenity::all::InstallationContext::Guild)
+    .add_integration_type(serenity::all::InstallationContext::User)
+}
@@ -1,7 +1,7 @@
 use std::{ops::Deref, str::FromStr};
 
-use crate::util::bool_from_string;
-use chrono::{NaiveDate, NaiveDateTime};
+use crate::util::{bool_from_string, chicago_timestamp};
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use serde_with::{serde, serde_as, DisplayFromStr};
@@ -83,12 +83,21 @@ pub struct Alert {
   pub impacted_services: ImpactedService,
 }
 
-#[serde(untagged)]
 #[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(untagged)]
 pub enum DateOrDateTime {
   DateTime(NaiveDateTime),
   Date(NaiveDate),
 }
+impl DateOrDateTime {
+  /// Unix timestamp of this date or time in Chicago, using midnight for dates.
+  pub fn timestamp(&self) -> i64 {
+    chicago_timestamp(match self {
+      DateOrDateTime::DateTime(naive_date_time) => *naive_date_time,
+      DateOrDateTime::Date(naive_date) => naive_date.and_time(NaiveTime::MIN),
+    })
+  }
+}
 impl std::fmt::Display for DateOrDateTime {
   fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
     match self {
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use crate::cta::traintracker::{FleetSnapshot, LRouteCode, TTArrival, TTPosition};
+use crate::util::chicago_timestamp;
+
+/// Upper bounds of the lead time buckets accuracy is reported in, in minutes. Anything longer
+/// falls into a final open-ended bucket.
+pub const LEAD_BUCKETS: [i64; 4] = [2, 5, 10, 20];
+
+/// A run reaching a station, as seen in the positions feed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObservedArrival {
+  pub route: LRouteCode,
+  pub run_number: i32,
+  pub station_id: i32,
+  /// Estimated as halfway between the last poll heading to the station and the first poll after.
+  pub arrived_at: i64,
+}
+
+/// One arrival prediction, to be compared with when the run actually shows up.
+#[derive(Debug, Clone)]
+pub struct PredictionSample {
+  pub route: LRouteCode,
+  pub run_number: i32,
+  pub station_id: i32,
+  /// The platform, which says which way the run is going.
+  pub stop_id: i32,
+  pub predicted_at: i64,
+  pub predicted_arrival: i64,
+  pub is_scheduled: bool,
+}
+
+impl From<&TTArrival> for PredictionSample {
+  fn from(arrival: &TTArrival) -> Self {
+    Self {
+      route: arrival.route,
+      run_number: arrival.run_number,
+      station_id: arrival.station_id,
+      stop_id: arrival.stop_id,
+      predicted_at: chicago_timestamp(arrival.prediction_time),
+      predicted_arrival: chicago_timestamp(arrival.arrival_time),
+      is_scheduled: arrival.is_scheduled,
+    }
+  }
+}
+
+impl PredictionSample {
+  /// The positions feed includes each run's predicted arrival at its next station.
+  pub fn from_position(train: &TTPosition) -> Option<Self> {
+    Some(Self {
+      route: train.route?,
+      run_number: train.run_number,
+      station_id: train.next_station_id,
+      stop_id: train.next_stop_id,
+      predicted_at: chicago_timestamp(train.prediction_time),
+      predicted_arrival: chicago_timestamp(train.arrival_time),
+      is_scheduled: false,
+    })
+  }
+}
+
+#[derive(Debug)]
+struct RunState {
+  route: LRouteCode,
+  next_station_id: i32,
+  seen_at: i64,
+}
+
+/// Turns successive position snapshots into arrivals at stations.
+///
+/// `TrainTracker` moves a run's next station along once it reaches the current one, so a change in
+/// next station between polls means the run arrived at the old one in the meantime.
+#[derive(Debug, Default)]
+pub struct ArrivalTracker {
+  runs: HashMap<i32, RunState>,
+}
+
+impl ArrivalTracker {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn observe(&mut self, fleet: &FleetSnapshot, now: i64) -> Vec<ObservedArrival> {
+    let mut arrivals = Vec::new();
+    let mut runs = HashMap::new();
+    for train in &fleet.trains {
+      let Some(route) = train.route else {
+        continue;
+      };
+      if let Some(previous) = self.runs.get(&train.run_number) {
+        if previous.route == route && previous.next_station_id != train.next_station_id {
+          arrivals.push(ObservedArrival {
+            route,
+            run_number: train.run_number,
+            station_id: previous.next_station_id,
+            arrived_at: previous.seen_at + (now - previous.seen_at) / 2,
+          });
+        }
+      }
+      runs.insert(
+        train.run_number,
+        RunState {
+          route,
+          next_station_id: train.next_station_id,
+          seen_at: now,
+        },
+      );
+    }
+    // Runs missing from this snapshot are dropped, so one that comes back much later isn't
+    // credited with arriving somewhere in between.
+    self.runs = runs;
+    arrivals
+  }
+}
+
+/// Describes a lead time bucket by its lower bound, like "5-10 min away".
+pub fn lead_bucket_label(lower_minutes: i64) -> String {
+  match LEAD_BUCKETS.iter().find(|upper| **upper > lower_minutes) {
+    Some(upper) => format!("{lower_minutes}-{upper} min away"),
+    None => format!("{lower_minutes}+ min away"),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cta::analysis::testing;
+
+  fn fleet(trains: &[(i32, i32)]) -> FleetSnapshot {
+    testing::fleet(
+      LRouteCode::Brn,
+      trains
+        .iter()
+        .map(|(run_number, station)| testing::train(*run_number, *station))
+        .collect(),
+    )
+  }
+
+  #[test]
+  fn test_observe_arrivals() {
+    let mut tracker = ArrivalTracker::new();
+    assert!(tracker
+      .observe(&fleet(&[(401, 41320), (402, 40460)]), 0)
+      .is_empty());
+    assert!(tracker
+      .observe(&fleet(&[(401, 41320), (402, 40460)]), 60)
+      .is_empty());
+    let arrivals = tracker.observe(&fleet(&[(401, 41210), (402, 40460)]), 120);
+    assert_eq!(
+      arrivals,
+      vec![ObservedArrival {
+        route: LRouteCode::Brn,
+        run_number: 401,
+        station_id: 41320,
+        arrived_at: 90,
+      }]
+    );
+    // 402 drops out of the feed, then reappears somewhere else.
+    tracker.observe(&fleet(&[(401, 41210)]), 180);
+    assert!(tracker
+      .observe(&fleet(&[(401, 41210), (402, 40800)]), 240)
+      .is_empty());
+  }
+
+  #[test]
+  fn test_lead_bucket_label() {
+    assert_eq!(lead_bucket_label(0), "0-2 min away");
+    assert_eq!(lead_bucket_label(5), "5-10 min away");
+    assert_eq!(lead_bucket_label(20), "20+ min away");
+  }
+}
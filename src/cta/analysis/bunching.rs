@@ -0,0 +1,220 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::cta::bustracker::Vehicle;
+
+/// Buses closer together than this along their pattern, in feet, are running together. About two
+/// blocks.
+pub const BUNCHED_FEET: i32 = 1320;
+/// The space behind a bunch is a long gap once it's this many times the even spacing for the
+/// pattern.
+pub const GAP_FACTOR: f64 = 1.5;
+/// How far back polls of a route are looked at to decide whether its bunching is chronic.
+pub const CHRONIC_WINDOW_SECS: i64 = 45 * 60;
+/// A route is chronically bunched once this share of its recent polls found a bunch...
+pub const CHRONIC_SHARE: f64 = 0.6;
+/// ...out of at least this many polls.
+pub const MIN_SAMPLES: usize = 10;
+/// A route isn't reported again for this long after it was last reported.
+pub const REPORT_COOLDOWN_SECS: i64 = 3 * 60 * 60;
+
+/// Several buses running together with a long gap behind them.
+#[derive(Debug, Clone)]
+pub struct Bunch<'a> {
+  /// Front of the bunch first.
+  pub buses: Vec<&'a Vehicle>,
+  /// Feet from the last bus in the bunch back to the next one, or to the start of the pattern if
+  /// no bus is behind it.
+  pub gap_behind: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct PatternSpacing<'a> {
+  pub pid: i32,
+  /// Buses in running order, front first, with the feet to the bus ahead of each.
+  pub buses: Vec<(&'a Vehicle, Option<i32>)>,
+  /// Feet between buses if they were spread evenly over the pattern.
+  pub even_spacing: f64,
+  pub bunches: Vec<Bunch<'a>>,
+}
+
+impl PatternSpacing<'_> {
+  pub fn is_bunched(&self, vid: &str) -> bool {
+    self
+      .bunches
+      .iter()
+      .any(|bunch| bunch.buses.iter().any(|bus| bus.vid == vid))
+  }
+}
+
+/// Orders the buses on each pattern by how far along it they are, and finds bunches.
+///
+/// `lengths` has the length of each pattern in feet. Buses on patterns without a known length, or
+/// without a distance along their pattern, are left out.
+#[allow(clippy::cast_precision_loss)]
+pub fn spacing<'a>(
+  vehicles: &'a [Vehicle],
+  lengths: &HashMap<i32, f64>,
+) -> Vec<PatternSpacing<'a>> {
+  let mut patterns: HashMap<i32, Vec<(&Vehicle, i32)>> = HashMap::new();
+  for vehicle in vehicles {
+    if let Some(pdist) = vehicle.pdist {
+      patterns
+        .entry(vehicle.pid)
+        .or_default()
+        .push((vehicle, pdist));
+    }
+  }
+  let mut result: Vec<PatternSpacing> = patterns
+    .into_iter()
+    .filter_map(|(pid, mut buses)| {
+      let length = *lengths.get(&pid)?;
+      buses.sort_by_key(|(_, pdist)| std::cmp::Reverse(*pdist));
+      let even_spacing = length / buses.len() as f64;
+      let gaps: Vec<Option<i32>> = (0..buses.len())
+        .map(|i| i.checked_sub(1).map(|ahead| buses[ahead].1 - buses[i].1))
+        .collect();
+      let mut bunches = Vec::new();
+      let mut start = 0;
+      for end in 1..=buses.len() {
+        if gaps
+          .get(end)
+          .copied()
+          .flatten()
+          .is_some_and(|gap| gap <= BUNCHED_FEET)
+        {
+          continue;
+        }
+        let trailing = buses[end - 1].1;
+        let gap_behind = buses
+          .get(end)
+          .map_or(trailing, |behind| trailing - behind.1);
+        if end - start > 1 && f64::from(gap_behind) >= even_spacing * GAP_FACTOR {
+          bunches.push(Bunch {
+            buses: buses[start..end].iter().map(|(bus, _)| *bus).collect(),
+            gap_behind,
+          });
+        }
+        start = end;
+      }
+      Some(PatternSpacing {
+        pid,
+        buses: buses
+          .iter()
+          .zip(gaps)
+          .map(|((bus, _), gap)| (*bus, gap))
+          .collect(),
+        even_spacing,
+        bunches,
+      })
+    })
+    .collect();
+  result.sort_by_key(|p| p.pid);
+  result
+}
+
+/// Remembers which recent polls of each route found bunches, to tell a passing bunch from a route
+/// that keeps bunching.
+#[derive(Debug, Default)]
+pub struct BunchingTracker {
+  polls: HashMap<String, VecDeque<(i64, bool)>>,
+  reported: HashMap<String, i64>,
+}
+
+impl BunchingTracker {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn observe(&mut self, route: &str, bunched: bool, now: i64) {
+    let polls = self.polls.entry(route.to_string()).or_default();
+    polls.push_back((now, bunched));
+    while polls
+      .front()
+      .is_some_and(|(time, _)| *time < now - CHRONIC_WINDOW_SECS)
+    {
+      polls.pop_front();
+    }
+  }
+
+  /// Routes that have been bunched for most of the last [`CHRONIC_WINDOW_SECS`] and haven't been
+  /// reported lately. They count as reported from `now`.
+  #[allow(clippy::cast_precision_loss)]
+  pub fn newly_chronic(&mut self, now: i64) -> Vec<String> {
+    let mut chronic: Vec<String> = self
+      .polls
+      .iter()
+      .filter(|(route, polls)| {
+        let bunched = polls.iter().filter(|(_, bunched)| *bunched).count();
+        polls.len() >= MIN_SAMPLES
+          && bunched as f64 >= polls.len() as f64 * CHRONIC_SHARE
+          && self
+            .reported
+            .get(*route)
+            .is_none_or(|reported| now - reported > REPORT_COOLDOWN_SECS)
+      })
+      .map(|(route, _)| route.clone())
+      .collect();
+    chronic.sort();
+    for route in &chronic {
+      self.reported.insert(route.clone(), now);
+    }
+    chronic
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cta::analysis::testing;
+
+  fn bus(vid: &str, pid: i32, pdist: i32) -> Vehicle {
+    Vehicle {
+      pid,
+      des: "Howard".to_string(),
+      pdist: Some(pdist),
+      ..testing::bus(vid, "22")
+    }
+  }
+
+  #[test]
+  fn test_spacing() {
+    // Three buses together near the end of a 40,000 foot pattern, then one far behind.
+    let vehicles = vec![
+      bus("1", 100, 30_000),
+      bus("2", 100, 29_500),
+      bus("3", 100, 29_000),
+      bus("4", 100, 4_000),
+      bus("5", 200, 1_000),
+    ];
+    let lengths = HashMap::from([(100, 40_000.0)]);
+    let patterns = spacing(&vehicles, &lengths);
+    assert_eq!(patterns.len(), 1);
+    let pattern = &patterns[0];
+    assert!((pattern.even_spacing - 10_000.0).abs() < 1.0);
+    assert_eq!(pattern.buses[0].1, None);
+    assert_eq!(pattern.buses[3].1, Some(25_000));
+    assert_eq!(pattern.bunches.len(), 1);
+    assert_eq!(pattern.bunches[0].buses.len(), 3);
+    assert_eq!(pattern.bunches[0].gap_behind, 25_000);
+    assert!(pattern.is_bunched("2"));
+    assert!(!pattern.is_bunched("4"));
+
+    // Evenly spaced buses aren't bunched.
+    let vehicles = vec![bus("1", 100, 30_000), bus("2", 100, 20_000)];
+    assert!(spacing(&vehicles, &lengths)[0].bunches.is_empty());
+  }
+
+  #[test]
+  fn test_newly_chronic() {
+    let mut tracker = BunchingTracker::new();
+    for i in 0..MIN_SAMPLES {
+      let now = i64::try_from(i).unwrap() * 120;
+      tracker.observe("22", i % 4 != 0, now);
+      tracker.observe("36", i % 2 == 0, now);
+    }
+    let now = i64::try_from(MIN_SAMPLES).unwrap() * 120;
+    assert_eq!(tracker.newly_chronic(now), vec!["22".to_string()]);
+    // Already reported.
+    assert!(tracker.newly_chronic(now + 120).is_empty());
+  }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use crate::cta::traintracker::{FleetSnapshot, LRouteCode};
+
+/// A station is dark once it has gone this many scheduled headways without a train...
+pub const GAP_FACTOR: i64 = 3;
+/// ...and at least this long.
+pub const MIN_GAP_SECS: i64 = 20 * 60;
+/// The scheduled headway is taken from arrivals this long either side of the time asked about.
+const SCHEDULE_WINDOW_SECS: u32 = 30 * 60;
+
+#[derive(Debug, Default)]
+struct StationHistory {
+  /// The platform trains were last seen heading to.
+  stop_id: i32,
+  /// The last time any train was seen heading to this station.
+  last_seen: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DarkStation {
+  pub station_id: i32,
+  pub station_name: String,
+  pub last_seen: i64,
+  /// The scheduled time between trains at this station, in seconds.
+  pub scheduled_headway: i64,
+}
+
+/// Remembers when trains were last seen approaching each station, one direction at a time.
+///
+/// Feed it a [`FleetSnapshot`] on every poll. Stations are reported by
+/// [`GapTracker::dark_stations`] once trains stop showing up for much longer than the schedule
+/// says they should.
+#[derive(Debug, Default)]
+pub struct GapTracker {
+  stations: HashMap<(LRouteCode, i8, i32), StationHistory>,
+  names: HashMap<i32, String>,
+  destinations: HashMap<(LRouteCode, i8), String>,
+}
+
+impl GapTracker {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn observe(&mut self, fleet: &FleetSnapshot, now: i64) {
+    for train in &fleet.trains {
+      let Some(route) = train.route else {
+        continue;
+      };
+      self
+        .names
+        .insert(train.next_station_id, train.next_station_name.clone());
+      self.destinations.insert(
+        (route, train.train_direction),
+        train.destination_name.clone(),
+      );
+      self.stations.insert(
+        (route, train.train_direction, train.next_station_id),
+        StationHistory {
+          stop_id: train.next_stop_id,
+          last_seen: now,
+        },
+      );
+    }
+  }
+
+  /// Stations in one direction of `route` that haven't seen a train for far longer than
+  /// scheduled. `scheduled_headway` gives the headway at a station and platform, or `None` to
+  /// leave the station out, like when no more trains are scheduled there.
+  pub fn dark_stations(
+    &self,
+    route: LRouteCode,
+    direction: i8,
+    now: i64,
+    scheduled_headway: impl Fn(i32, i32) -> Option<i64>,
+  ) -> Vec<DarkStation> {
+    let mut dark: Vec<DarkStation> = self
+      .stations
+      .iter()
+      .filter(|((r, d, _), _)| *r == route && *d == direction)
+      .filter_map(|((_, _, station_id), history)| {
+        let scheduled_headway = scheduled_headway(*station_id, history.stop_id)?;
+        (now - history.last_seen > MIN_GAP_SECS.max(scheduled_headway * GAP_FACTOR)).then(|| {
+          DarkStation {
+            station_id: *station_id,
+            station_name: self.names.get(station_id).cloned().unwrap_or_default(),
+            last_seen: history.last_seen,
+            scheduled_headway,
+          }
+        })
+      })
+      .collect();
+    dark.sort_by_key(|s| (s.last_seen, s.station_id));
+    dark
+  }
+
+  /// Directions of `route` that have been seen, with their most recent destination.
+  pub fn directions(&self, route: LRouteCode) -> Vec<(i8, &str)> {
+    let mut directions: Vec<(i8, &str)> = self
+      .destinations
+      .iter()
+      .filter(|((r, _), _)| *r == route)
+      .map(|((_, d), name)| (*d, name.as_str()))
+      .collect();
+    directions.sort_unstable();
+    directions
+  }
+}
+
+/// The usual time between `arrivals` around `at`, from a stop's schedule in order. `None` when
+/// fewer than two trains are scheduled nearby, or none after `at`, since service is winding down.
+pub fn scheduled_headway(arrivals: &[u32], at: u32) -> Option<i64> {
+  let nearby: Vec<u32> = arrivals
+    .iter()
+    .copied()
+    .filter(|t| t.abs_diff(at) <= SCHEDULE_WINDOW_SECS)
+    .collect();
+  if nearby.len() < 2 || nearby.last().is_none_or(|last| *last <= at) {
+    return None;
+  }
+  let mut gaps: Vec<u32> = nearby.windows(2).map(|w| w[1] - w[0]).collect();
+  gaps.sort_unstable();
+  Some(i64::from(gaps[gaps.len() / 2]))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cta::analysis::testing;
+  use crate::cta::traintracker::TTPosition;
+
+  fn fleet(trains: &[(i32, i32)]) -> FleetSnapshot {
+    testing::fleet(
+      LRouteCode::Blue,
+      trains
+        .iter()
+        .map(|(run_number, station)| TTPosition {
+          next_station_name: format!("Station {station}"),
+          ..testing::train(*run_number, *station)
+        })
+        .collect(),
+    )
+  }
+
+  #[test]
+  fn test_dark_stations() {
+    let mut tracker = GapTracker::new();
+    let every_five_minutes = |_, _| Some(5 * 60);
+    // A train reaches each station every 5 minutes for 40 minutes.
+    for (i, minute) in (0..=40).step_by(5).enumerate() {
+      let run = 100 + i32::try_from(i).unwrap();
+      tracker.observe(&fleet(&[(run, 1), (run + 50, 2)]), minute * 60);
+    }
+    assert_eq!(tracker.directions(LRouteCode::Blue), vec![(1, "O'Hare")]);
+    assert!(tracker
+      .dark_stations(LRouteCode::Blue, 1, 50 * 60, every_five_minutes)
+      .is_empty());
+
+    // Then trains keep reaching station 2 but not station 1.
+    for minute in (45..=70).step_by(5) {
+      tracker.observe(&fleet(&[(200 + minute, 2)]), i64::from(minute) * 60);
+    }
+    let dark = tracker.dark_stations(LRouteCode::Blue, 1, 70 * 60, every_five_minutes);
+    assert_eq!(dark.len(), 1);
+    assert_eq!(dark[0].station_id, 1);
+    assert_eq!(dark[0].last_seen, 40 * 60);
+    assert_eq!(dark[0].scheduled_headway, 5 * 60);
+    // Not when trains are only scheduled every 15 minutes, or not at all.
+    assert!(tracker
+      .dark_stations(LRouteCode::Blue, 1, 70 * 60, |_, _| Some(15 * 60))
+      .is_empty());
+    assert!(tracker
+      .dark_stations(LRouteCode::Blue, 1, 70 * 60, |_, _| None)
+      .is_empty());
+  }
+
+  #[test]
+  fn test_scheduled_headway() {
+    // Every 10 minutes until 6:00, then every 4 minutes until 7:00.
+    let arrivals: Vec<u32> = (0..6)
+      .map(|i| 5 * 3600 + i * 600)
+      .chain((0..=15).map(|i| 6 * 3600 + i * 240))
+      .collect();
+    assert_eq!(scheduled_headway(&arrivals, 5 * 3600 + 900), Some(600));
+    assert_eq!(scheduled_headway(&arrivals, 6 * 3600 + 1800), Some(240));
+    // Nothing left after 7:00, or nothing scheduled yet.
+    assert_eq!(scheduled_headway(&arrivals, 7 * 3600 + 60), None);
+    assert_eq!(scheduled_headway(&arrivals, 3 * 3600), None);
+  }
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use crate::cta::bustracker::{BusTracker, Garage, Vehicle};
+
+/// A bus running on one route while working a block that belongs to another.
+#[derive(Debug, Clone)]
+pub struct Interline<'a> {
+  pub vehicle: &'a Vehicle,
+  /// The route the bus's block is named for.
+  pub block_route: &'a str,
+}
+
+/// How many of `vehicles` come out of each garage, busiest first.
+pub fn garage_counts(vehicles: &[Vehicle]) -> Vec<(Garage, usize)> {
+  let mut counts: HashMap<Garage, usize> = HashMap::new();
+  for vehicle in vehicles {
+    *counts
+      .entry(BusTracker::tablockid_to_garage(&vehicle.tablockid))
+      .or_default() += 1;
+  }
+  let mut counts: Vec<(Garage, usize)> = counts.into_iter().collect();
+  counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+  counts
+}
+
+/// Buses whose block is named for a different route than the one they're on.
+///
+/// Blocks are named for the route they start on, so these buses have come over from another route
+/// or will go on to one. `is_route` tells real routes apart from block names that aren't routes,
+/// like "SS", which are left out.
+pub fn interlined(vehicles: &[Vehicle], is_route: impl Fn(&str) -> bool) -> Vec<Interline<'_>> {
+  vehicles
+    .iter()
+    .filter_map(|vehicle| {
+      let block_route = BusTracker::tablockid_to_route(&vehicle.tablockid);
+      (!block_route.is_empty() && block_route != vehicle.rt && is_route(block_route)).then_some(
+        Interline {
+          vehicle,
+          block_route,
+        },
+      )
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cta::analysis::testing;
+
+  fn bus(vid: &str, rt: &str, tablockid: &str) -> Vehicle {
+    Vehicle {
+      tablockid: tablockid.to_string(),
+      ..testing::bus(vid, rt)
+    }
+  }
+
+  #[test]
+  fn test_garage_counts() {
+    let vehicles = vec![
+      bus("1", "22", "22 -502"),
+      bus("2", "22", "22 -503"),
+      bus("3", "22", "36 -504"),
+      bus("4", "22", "22 -201"),
+    ];
+    assert_eq!(
+      garage_counts(&vehicles),
+      vec![(Garage::NorthPark, 3), (Garage::Kedzie, 1)]
+    );
+  }
+
+  #[test]
+  fn test_interlined() {
+    let vehicles = vec![
+      bus("1", "22", "22 -502"),
+      bus("2", "22", "36 -504"),
+      bus("3", "22", "SS -851"),
+      bus("4", "52A", "52A-754"),
+    ];
+    let interlines = interlined(&vehicles, |route| route != "SS");
+    assert_eq!(interlines.len(), 1);
+    assert_eq!(interlines[0].vehicle.vid, "2");
+    assert_eq!(interlines[0].block_route, "36");
+  }
+}
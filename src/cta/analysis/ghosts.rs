@@ -0,0 +1,338 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::cta::analysis::accuracy::PredictionSample;
+use crate::cta::analysis::running_times::RunningTimes;
+use crate::cta::traintracker::{FleetSnapshot, LRouteCode};
+use crate::db::DBGhostRate;
+
+/// A scheduled trip counts as a ghost if its run still isn't tracked this long after it was due.
+const GRACE_SECS: i64 = 15 * 60;
+/// Predictions for a run going the same way are for the same trip when the trip would have started
+/// within this long of each other. Even the Yellow Line takes longer than this to come back around.
+const SAME_TRIP_SECS: i64 = 10 * 60;
+/// Settled trips are remembered this long after they started, so later predictions for them are
+/// left alone.
+const SETTLED_SECS: i64 = 24 * 60 * 60;
+/// Hours need this many scheduled trips on record before their ghost rate is trusted.
+const MIN_TRIPS: i64 = 5;
+/// Scheduled arrivals are marked as likely ghosts once at least this share of trips in the same
+/// hour didn't run.
+const LIKELY_GHOST_RATE: f64 = 0.5;
+
+/// Parts of the day ghost rates are reported in, by Chicago hour.
+pub const TIME_PERIODS: [(&str, Range<i32>); 5] = [
+  ("Overnight", 0..5),
+  ("Morning rush", 5..9),
+  ("Midday", 9..15),
+  ("Evening rush", 15..19),
+  ("Evening", 19..24),
+];
+
+/// What became of a trip that was only ever shown from the schedule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledTrip {
+  pub route: LRouteCode,
+  pub run_number: i32,
+  /// Earliest scheduled arrival seen for the trip.
+  pub scheduled_arrival: i64,
+  /// Whether the run never showed up in the positions feed.
+  pub ghost: bool,
+}
+
+/// One trip of a run: which way it goes, and when it starts going by the schedule, taken as a
+/// scheduled arrival less the running time to that platform.
+#[derive(Debug, Clone, Copy)]
+struct Trip {
+  direction: Option<u8>,
+  start: i64,
+}
+
+impl Trip {
+  fn same(self, other: Trip) -> bool {
+    self.direction == other.direction && (self.start - other.start).abs() <= SAME_TRIP_SECS
+  }
+}
+
+#[derive(Debug)]
+struct PendingTrip {
+  trip: Trip,
+  /// Platform of the earliest scheduled arrival.
+  stop_id: i32,
+  first_arrival: i64,
+  last_arrival: i64,
+}
+
+/// Follows scheduled-only predictions until their run is either tracked or long overdue.
+#[derive(Debug, Default)]
+pub struct GhostTracker {
+  running_times: HashMap<LRouteCode, RunningTimes>,
+  pending: HashMap<(LRouteCode, i32), Vec<PendingTrip>>,
+  settled: HashMap<(LRouteCode, i32), Vec<Trip>>,
+}
+
+impl GhostTracker {
+  /// `running_times` places trains along each line, to tell which trip a run is on.
+  pub fn new(running_times: HashMap<LRouteCode, RunningTimes>) -> Self {
+    Self {
+      running_times,
+      ..Self::default()
+    }
+  }
+
+  /// Starts following every scheduled prediction in `samples`, unless its trip is already
+  /// settled.
+  pub fn expect(&mut self, samples: &[PredictionSample]) {
+    for sample in samples.iter().filter(|s| s.is_scheduled) {
+      let key = (sample.route, sample.run_number);
+      let trip = self.trip(sample);
+      if self
+        .settled
+        .get(&key)
+        .is_some_and(|trips| trips.iter().any(|settled| settled.same(trip)))
+      {
+        continue;
+      }
+      let pending = self.pending.entry(key).or_default();
+      match pending.iter_mut().find(|pending| pending.trip.same(trip)) {
+        Some(pending) => {
+          if sample.predicted_arrival < pending.first_arrival {
+            pending.first_arrival = sample.predicted_arrival;
+            pending.stop_id = sample.stop_id;
+          }
+          pending.last_arrival = pending.last_arrival.max(sample.predicted_arrival);
+        }
+        None => pending.push(PendingTrip {
+          trip,
+          stop_id: sample.stop_id,
+          first_arrival: sample.predicted_arrival,
+          last_arrival: sample.predicted_arrival,
+        }),
+      }
+    }
+  }
+
+  /// Settles trips once they're due and their run is heading to or past the first platform they
+  /// were scheduled at, or once they're overdue without it.
+  pub fn observe(&mut self, fleet: &FleetSnapshot, now: i64) -> Vec<ScheduledTrip> {
+    let mut settled = Vec::new();
+    for (&(route, run_number), trips) in &mut self.pending {
+      let train = fleet
+        .train(run_number)
+        .filter(|train| train.route == Some(route));
+      let running_times = self.running_times.get(&route);
+      trips.retain(|trip| {
+        if now < trip.first_arrival {
+          return true;
+        }
+        // Runs keep their number from trip to trip, so the run has to be on this trip, not still
+        // finishing its last one. Without the schedule to tell, being tracked has to do.
+        let ran = train.is_some_and(|train| {
+          running_times
+            .and_then(|times| times.reached(train.next_stop_id, trip.stop_id))
+            .unwrap_or(true)
+        });
+        if !ran && now <= trip.last_arrival + GRACE_SECS {
+          return true;
+        }
+        settled.push(ScheduledTrip {
+          route,
+          run_number,
+          scheduled_arrival: trip.first_arrival,
+          ghost: !ran,
+        });
+        self
+          .settled
+          .entry((route, run_number))
+          .or_default()
+          .push(trip.trip);
+        false
+      });
+    }
+    self.pending.retain(|_, trips| !trips.is_empty());
+    self.settled.retain(|_, trips| {
+      trips.retain(|trip| now - trip.start <= SETTLED_SECS);
+      !trips.is_empty()
+    });
+    settled
+  }
+
+  fn trip(&self, sample: &PredictionSample) -> Trip {
+    let position = self
+      .running_times
+      .get(&sample.route)
+      .and_then(|times| times.position(sample.stop_id));
+    Trip {
+      direction: position.map(|p| p.direction),
+      start: sample.predicted_arrival - position.map_or(0, |p| p.secs),
+    }
+  }
+}
+
+/// Ghost rates by line and Chicago hour.
+#[derive(Debug, Default)]
+pub struct GhostRates {
+  by_hour: HashMap<(String, i32), (i64, i64)>,
+}
+
+impl GhostRates {
+  pub fn new(rows: &[DBGhostRate]) -> Self {
+    Self {
+      by_hour: rows
+        .iter()
+        .map(|r| ((r.route.clone(), r.hour), (r.trips, r.ghosts)))
+        .collect(),
+    }
+  }
+
+  /// Scheduled trips and ghosts on `route` within `hours`.
+  pub fn totals(&self, route: LRouteCode, hours: Range<i32>) -> (i64, i64) {
+    let route = route.to_string();
+    hours
+      .filter_map(|hour| self.by_hour.get(&(route.clone(), hour)))
+      .fold((0, 0), |(trips, ghosts), (t, g)| (trips + t, ghosts + g))
+  }
+
+  /// Share of scheduled trips on `route` within `hours` that never ran, if there are enough.
+  #[allow(clippy::cast_precision_loss)]
+  pub fn rate(&self, route: LRouteCode, hours: Range<i32>) -> Option<f64> {
+    let (trips, ghosts) = self.totals(route, hours);
+    (trips >= MIN_TRIPS).then(|| ghosts as f64 / trips as f64)
+  }
+
+  /// Whether a scheduled arrival on `route` in the given hour is unlikely to turn up.
+  pub fn likely_ghost(&self, route: LRouteCode, hour: i32) -> bool {
+    self
+      .rate(route, hour..hour + 1)
+      .is_some_and(|rate| rate >= LIKELY_GHOST_RATE)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cta::analysis::testing;
+  use crate::cta::traintracker::TTPosition;
+
+  /// Pink Line platforms two minutes apart, 30001 to 30003 one way and 30011 to 30013 the other.
+  fn tracker() -> GhostTracker {
+    GhostTracker::new(HashMap::from([(
+      LRouteCode::Pink,
+      RunningTimes::new(vec![
+        (0, vec![(30001, 0), (30002, 120), (30003, 240)]),
+        (1, vec![(30013, 0), (30012, 120), (30011, 240)]),
+      ]),
+    )]))
+  }
+
+  /// Runs and the platforms they're heading to.
+  fn fleet(runs: &[(i32, i32)]) -> FleetSnapshot {
+    testing::fleet(
+      LRouteCode::Pink,
+      runs
+        .iter()
+        .map(|(run_number, next_stop_id)| TTPosition {
+          next_stop_id: *next_stop_id,
+          ..testing::train(*run_number, 40580)
+        })
+        .collect(),
+    )
+  }
+
+  fn scheduled(run_number: i32, stop_id: i32, predicted_arrival: i64) -> PredictionSample {
+    PredictionSample {
+      route: LRouteCode::Pink,
+      run_number,
+      station_id: 40580,
+      stop_id,
+      predicted_at: 0,
+      predicted_arrival,
+      is_scheduled: true,
+    }
+  }
+
+  #[test]
+  fn test_ghost_tracker() {
+    let mut tracker = tracker();
+    tracker.expect(&[
+      scheduled(301, 30003, 600),
+      scheduled(302, 30002, 600),
+      scheduled(302, 30003, 900),
+    ]);
+    assert!(tracker.observe(&fleet(&[]), 600).is_empty());
+    assert_eq!(
+      tracker.observe(&fleet(&[(301, 30003)]), 700),
+      vec![ScheduledTrip {
+        route: LRouteCode::Pink,
+        run_number: 301,
+        scheduled_arrival: 600,
+        ghost: false,
+      }]
+    );
+    // Still within the grace period of 302's later prediction.
+    assert!(tracker.observe(&fleet(&[]), 900 + GRACE_SECS).is_empty());
+    assert_eq!(
+      tracker.observe(&fleet(&[]), 901 + GRACE_SECS),
+      vec![ScheduledTrip {
+        route: LRouteCode::Pink,
+        run_number: 302,
+        scheduled_arrival: 600,
+        ghost: true,
+      }]
+    );
+  }
+
+  #[test]
+  fn test_ghost_tracker_previous_trip() {
+    let mut tracker = tracker();
+    tracker.expect(&[scheduled(301, 30002, 600)]);
+    // 301 is still finishing its last trip, the other way.
+    assert!(tracker.observe(&fleet(&[(301, 30012)]), 300).is_empty());
+    assert!(tracker.observe(&fleet(&[(301, 30011)]), 650).is_empty());
+    assert!(tracker.observe(&fleet(&[(301, 30001)]), 680).is_empty());
+    assert_eq!(
+      tracker.observe(&fleet(&[(301, 30002)]), 700),
+      vec![ScheduledTrip {
+        route: LRouteCode::Pink,
+        run_number: 301,
+        scheduled_arrival: 600,
+        ghost: false,
+      }]
+    );
+    // Later predictions for the same trip aren't counted again, but its next trip is.
+    tracker.expect(&[scheduled(301, 30003, 720), scheduled(301, 30002, 3000)]);
+    assert!(tracker.observe(&fleet(&[(301, 30003)]), 800).is_empty());
+    assert_eq!(
+      tracker.observe(&fleet(&[]), 3001 + GRACE_SECS),
+      vec![ScheduledTrip {
+        route: LRouteCode::Pink,
+        run_number: 301,
+        scheduled_arrival: 3000,
+        ghost: true,
+      }]
+    );
+  }
+
+  #[test]
+  fn test_ghost_rates() {
+    let rates = GhostRates::new(&[
+      DBGhostRate {
+        route: "Pink".to_string(),
+        hour: 6,
+        trips: 10,
+        ghosts: 6,
+      },
+      DBGhostRate {
+        route: "Pink".to_string(),
+        hour: 7,
+        trips: 4,
+        ghosts: 0,
+      },
+    ]);
+    assert!(rates.likely_ghost(LRouteCode::Pink, 6));
+    // Too few trips to judge on their own.
+    assert!(!rates.likely_ghost(LRouteCode::Pink, 7));
+    assert!(!rates.likely_ghost(LRouteCode::Red, 6));
+    assert_eq!(rates.totals(LRouteCode::Pink, 5..9), (14, 6));
+  }
+}
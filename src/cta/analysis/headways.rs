@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use crate::cta::analysis::running_times::RunningTimes;
+use crate::cta::traintracker::{FleetSnapshot, LRouteCode, TTPosition};
+
+/// Trains closer together than this are considered bunched.
+pub const BUNCHED_MINUTES: f32 = 2.0;
+/// Gaps are flagged when they are this many times the typical spacing for the direction...
+pub const GAP_FACTOR: f32 = 2.0;
+/// ...and at least this long.
+pub const MIN_GAP_MINUTES: f32 = 12.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Spacing {
+  Normal,
+  Bunched,
+  Gap,
+}
+
+/// The space between two consecutive trains running in the same direction.
+#[derive(Debug, Clone)]
+pub struct Headway<'a> {
+  pub ahead: &'a TTPosition,
+  pub behind: &'a TTPosition,
+  pub minutes: f32,
+  pub spacing: Spacing,
+}
+
+#[derive(Debug, Clone)]
+pub struct DirectionHeadways<'a> {
+  /// Which way the trains' trips run in the schedule.
+  pub direction: u8,
+  /// The most common destination for trains in this direction.
+  pub toward: String,
+  /// Trains in running order, front of the line first.
+  pub trains: Vec<&'a TTPosition>,
+  pub headways: Vec<Headway<'a>>,
+  pub median_minutes: Option<f32>,
+}
+
+/// Estimates the spacing between trains on a route, in each direction.
+///
+/// Trains are placed along the line by their next platform, and the spacing between neighbours is
+/// the scheduled running time between their next platforms. Trains heading to a platform that
+/// isn't in `running_times` are left out.
+#[allow(clippy::cast_precision_loss)]
+pub fn headways<'a>(
+  fleet: &'a FleetSnapshot,
+  route: LRouteCode,
+  running_times: &RunningTimes,
+) -> Vec<DirectionHeadways<'a>> {
+  let mut directions: HashMap<u8, Vec<(&TTPosition, i64)>> = HashMap::new();
+  for train in fleet.on_route(route) {
+    if let Some(position) = running_times.position(train.next_stop_id) {
+      directions
+        .entry(position.direction)
+        .or_default()
+        .push((train, position.secs));
+    }
+  }
+  let mut result: Vec<DirectionHeadways> = directions
+    .into_iter()
+    .map(|(direction, mut trains)| {
+      trains.sort_by_key(|(train, secs)| (std::cmp::Reverse(*secs), train.run_number));
+      let gaps: Vec<(&TTPosition, &TTPosition, f32)> = trains
+        .windows(2)
+        .map(|pair| {
+          let minutes = (pair[0].1 - pair[1].1) as f32 / 60.0;
+          (pair[0].0, pair[1].0, minutes)
+        })
+        .collect();
+      let median_minutes = median(gaps.iter().map(|g| g.2).collect());
+      let headways = gaps
+        .into_iter()
+        .map(|(ahead, behind, minutes)| Headway {
+          ahead,
+          behind,
+          minutes,
+          spacing: classify(minutes, median_minutes),
+        })
+        .collect();
+      DirectionHeadways {
+        direction,
+        toward: most_common_destination(trains.iter().map(|t| t.0)),
+        trains: trains.into_iter().map(|t| t.0).collect(),
+        headways,
+        median_minutes,
+      }
+    })
+    .collect();
+  result.sort_by_key(|d| d.direction);
+  result
+}
+
+pub fn classify(minutes: f32, median_minutes: Option<f32>) -> Spacing {
+  if minutes < BUNCHED_MINUTES {
+    Spacing::Bunched
+  } else if minutes >= MIN_GAP_MINUTES && median_minutes.is_none_or(|m| minutes > m * GAP_FACTOR) {
+    Spacing::Gap
+  } else {
+    Spacing::Normal
+  }
+}
+
+fn median(mut values: Vec<f32>) -> Option<f32> {
+  if values.is_empty() {
+    return None;
+  }
+  values.sort_by(f32::total_cmp);
+  let mid = values.len() / 2;
+  if values.len().is_multiple_of(2) {
+    Some(f32::midpoint(values[mid - 1], values[mid]))
+  } else {
+    Some(values[mid])
+  }
+}
+
+fn most_common_destination<'a>(trains: impl Iterator<Item = &'a TTPosition>) -> String {
+  let mut counts: HashMap<&str, usize> = HashMap::new();
+  for train in trains {
+    *counts.entry(train.destination_name.as_str()).or_default() += 1;
+  }
+  counts
+    .into_iter()
+    .max_by_key(|(name, count)| (*count, std::cmp::Reverse(*name)))
+    .map(|(name, _)| name.to_string())
+    .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cta::analysis::testing;
+
+  fn train(run_number: i32, next_stop_id: i32) -> TTPosition {
+    TTPosition {
+      destination_name: if next_stop_id < 30100 {
+        "Howard"
+      } else {
+        "95th/Dan Ryan"
+      }
+      .to_string(),
+      next_stop_id,
+      ..testing::train(run_number, 40000)
+    }
+  }
+
+  #[test]
+  fn test_headways() {
+    // Northbound platforms three minutes apart.
+    let running_times = RunningTimes::new(vec![
+      (
+        0,
+        (0..10)
+          .map(|stop| (30000 + stop, u32::try_from(stop).unwrap() * 180))
+          .collect(),
+      ),
+      (1, vec![(30101, 0), (30100, 180)]),
+    ]);
+    let fleet = testing::fleet(
+      LRouteCode::Red,
+      vec![
+        train(805, 30000),
+        train(804, 30005),
+        train(803, 30007),
+        train(802, 30007),
+        train(801, 30009),
+        train(901, 30100),
+        // Not in the schedule.
+        train(902, 30200),
+      ],
+    );
+    let result = headways(&fleet, LRouteCode::Red, &running_times);
+    assert_eq!(result.len(), 2);
+    let north = &result[0];
+    assert_eq!(north.toward, "Howard");
+    assert_eq!(
+      north
+        .trains
+        .iter()
+        .map(|t| t.run_number)
+        .collect::<Vec<_>>(),
+      vec![801, 802, 803, 804, 805]
+    );
+    let spacing: Vec<Spacing> = north.headways.iter().map(|h| h.spacing).collect();
+    assert_eq!(
+      spacing,
+      vec![
+        Spacing::Normal,
+        Spacing::Bunched,
+        Spacing::Normal,
+        Spacing::Gap
+      ]
+    );
+    assert!((north.headways[0].minutes - 6.0).abs() < f32::EPSILON);
+    assert_eq!(result[1].trains.len(), 1);
+    assert!(result[1].headways.is_empty());
+  }
+}
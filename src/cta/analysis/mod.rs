@@ -0,0 +1,116 @@
+use super::stations::CtaStations;
+use super::traintracker;
+use super::traintracker::LRouteCode;
+use super::traintracker::TTRoute;
+
+pub mod accuracy;
+pub mod bunching;
+pub mod gaps;
+pub mod garages;
+pub mod ghosts;
+pub mod headways;
+pub mod reliability;
+pub mod running_times;
+#[cfg(test)]
+mod testing;
+pub mod traveltime;
+
+pub struct AnomalousTrain {
+  pub anomaly_type: AnomalyType,
+  pub route: LRouteCode,
+  pub position: traintracker::TTPosition,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyType {
+  RunNumber,
+  NextStation,
+}
+impl AnomalyType {
+  /// Stable name used to deduplicate reports in the database.
+  pub fn key(self) -> &'static str {
+    match self {
+      AnomalyType::RunNumber => "run_number",
+      AnomalyType::NextStation => "next_station",
+    }
+  }
+}
+pub fn check_route(routes: &[TTRoute], stations: &CtaStations) -> Vec<AnomalousTrain> {
+  routes
+    .iter()
+    .flat_map(|route| {
+      let mut anomalies = check_run_numbers(route);
+      anomalies.extend(check_next_stations(route, stations));
+      anomalies
+    })
+    .collect()
+}
+
+/// Whether `run` falls inside the run number ranges normally assigned to `route`.
+pub fn is_usual_run_number(route: LRouteCode, run: i32) -> bool {
+  match route {
+    LRouteCode::Blue => matches!(run, 100..=299),
+    LRouteCode::Brn => matches!(run, 400..=499),
+    LRouteCode::G => matches!(run, 0..=99 | 600..=699),
+    LRouteCode::Org => matches!(run, 700..=799),
+    LRouteCode::P => matches!(run, 500..=589),
+    LRouteCode::Pink => matches!(run, 300..=399),
+    LRouteCode::Red => matches!(run, 800..=999),
+    LRouteCode::Y => matches!(run, 590..=599),
+  }
+}
+
+/// Human readable version of the ranges in [`is_usual_run_number`].
+pub fn usual_run_numbers(route: LRouteCode) -> &'static str {
+  match route {
+    LRouteCode::Blue => "100-299",
+    LRouteCode::Brn => "400-499",
+    LRouteCode::G => "000-099 and 600-699",
+    LRouteCode::Org => "700-799",
+    LRouteCode::P => "500-589",
+    LRouteCode::Pink => "300-399",
+    LRouteCode::Red => "800-999",
+    LRouteCode::Y => "590-599",
+  }
+}
+
+fn check_run_numbers(route: &TTRoute) -> Vec<AnomalousTrain> {
+  route
+    .trains
+    .iter()
+    .filter(|train| !is_usual_run_number(route.name, train.run_number))
+    .map(|train| AnomalousTrain {
+      anomaly_type: AnomalyType::RunNumber,
+      route: route.name,
+      position: train.clone(),
+    })
+    .collect()
+}
+
+fn check_next_stations(route: &TTRoute, stations: &CtaStations) -> Vec<AnomalousTrain> {
+  route
+    .trains
+    .iter()
+    .filter(|train| stations.serves(train.next_station_id, route.name) == Some(false))
+    .map(|train| AnomalousTrain {
+      anomaly_type: AnomalyType::NextStation,
+      route: route.name,
+      position: train.clone(),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_usual_run_numbers() {
+    assert!(is_usual_run_number(LRouteCode::Red, 812));
+    assert!(!is_usual_run_number(LRouteCode::Red, 412));
+    assert!(is_usual_run_number(LRouteCode::G, 7));
+    assert!(is_usual_run_number(LRouteCode::G, 612));
+    assert!(!is_usual_run_number(LRouteCode::G, 300));
+    assert!(is_usual_run_number(LRouteCode::Y, 591));
+    assert!(!is_usual_run_number(LRouteCode::P, 591));
+  }
+}
@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use chrono::{Days, NaiveDate, NaiveTime};
+
+use crate::db::DBLineReliability;
+use crate::util::chicago_timestamp;
+
+/// Service days start at this hour, Chicago time, so late-night trips count toward the day before.
+pub const SERVICE_DAY_START_HOUR: u32 = 3;
+/// Trains this far apart or more at a station count as the line not running, not as a headway.
+pub const MAX_HEADWAY_SECS: i64 = 2 * 60 * 60;
+
+/// Start and end of the service day that begins on `date`, as Unix timestamps.
+pub fn service_day_bounds(date: NaiveDate) -> (i64, i64) {
+  let start = NaiveTime::from_hms_opt(SERVICE_DAY_START_HOUR, 0, 0).unwrap_or_default();
+  (
+    chicago_timestamp(date.and_time(start)),
+    chicago_timestamp(
+      date
+        .checked_add_days(Days::new(1))
+        .unwrap_or(date)
+        .and_time(start),
+    ),
+  )
+}
+
+/// The date of the service day a timestamp falls in.
+pub fn service_date(timestamp: i64) -> NaiveDate {
+  let local = chrono::DateTime::from_timestamp(timestamp, 0)
+    .unwrap_or_default()
+    .with_timezone(&chrono_tz::America::Chicago)
+    .naive_local();
+  (local - chrono::Duration::hours(i64::from(SERVICE_DAY_START_HOUR))).date()
+}
+
+/// Average scheduled seconds between trains reaching the same platform, given each platform's
+/// scheduled arrivals in order. Worked out like the recorded headways are, so the two compare.
+#[allow(clippy::cast_precision_loss)]
+pub fn average_scheduled_headway(schedule: &HashMap<String, Vec<u32>>) -> Option<f64> {
+  let headways: Vec<u32> = schedule
+    .values()
+    .flat_map(|arrivals| arrivals.windows(2).map(|pair| pair[1] - pair[0]))
+    .filter(|headway| i64::from(*headway) < MAX_HEADWAY_SECS)
+    .collect();
+  (!headways.is_empty())
+    .then(|| headways.iter().map(|h| f64::from(*h)).sum::<f64>() / headways.len() as f64)
+}
+
+/// A line's reliability over several service days.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineScore {
+  pub route: String,
+  pub days: usize,
+  /// Trips run as a share of trips scheduled, if anything was scheduled.
+  pub trips_run_share: Option<f64>,
+  /// Average headway divided by the scheduled headway. Above 1 means trains came less often.
+  pub headway_ratio: Option<f64>,
+  pub delayed_share: f64,
+}
+
+/// Combines daily figures into one score per line, in the order lines first appear.
+#[allow(clippy::cast_precision_loss)]
+pub fn line_scores(days: &[DBLineReliability]) -> Vec<LineScore> {
+  let mut routes: Vec<&str> = Vec::new();
+  for day in days {
+    if !routes.contains(&day.route.as_str()) {
+      routes.push(&day.route);
+    }
+  }
+  routes
+    .into_iter()
+    .map(|route| {
+      let line_days: Vec<&DBLineReliability> = days.iter().filter(|d| d.route == route).collect();
+      let scheduled: i64 = line_days.iter().map(|d| i64::from(d.trips_scheduled)).sum();
+      let run: i64 = line_days.iter().map(|d| i64::from(d.trips_run)).sum();
+      let ratios: Vec<f64> = line_days
+        .iter()
+        .filter_map(|d| match (d.avg_headway, d.scheduled_headway) {
+          (Some(actual), Some(scheduled)) if scheduled > 0.0 => Some(actual / scheduled),
+          _ => None,
+        })
+        .collect();
+      LineScore {
+        route: route.to_string(),
+        days: line_days.len(),
+        trips_run_share: (scheduled > 0).then(|| run as f64 / scheduled as f64),
+        headway_ratio: (!ratios.is_empty())
+          .then(|| ratios.iter().sum::<f64>() / ratios.len() as f64),
+        delayed_share: line_days.iter().map(|d| d.delayed_share).sum::<f64>()
+          / line_days.len() as f64,
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn day(
+    service_day: i64,
+    route: &str,
+    scheduled: i32,
+    run: i32,
+    headway: f64,
+  ) -> DBLineReliability {
+    DBLineReliability {
+      service_day,
+      route: route.to_string(),
+      trips_scheduled: scheduled,
+      trips_run: run,
+      avg_headway: Some(headway),
+      scheduled_headway: Some(600.0),
+      delayed_share: 0.1,
+    }
+  }
+
+  #[test]
+  fn test_line_scores() {
+    let scores = line_scores(&[
+      day(0, "Red", 100, 90, 600.0),
+      day(0, "Blue", 0, 50, 900.0),
+      day(1, "Red", 100, 100, 900.0),
+    ]);
+    assert_eq!(scores.len(), 2);
+    assert_eq!(scores[0].route, "Red");
+    assert_eq!(scores[0].days, 2);
+    assert_eq!(scores[0].trips_run_share, Some(0.95));
+    assert_eq!(scores[0].headway_ratio, Some(1.25));
+    assert_eq!(scores[1].trips_run_share, None);
+  }
+
+  #[test]
+  fn test_average_scheduled_headway() {
+    let schedule = HashMap::from([
+      ("30001".to_string(), vec![18000, 18600, 19200]),
+      // The last train of the night and the first of the morning aren't a headway.
+      ("30002".to_string(), vec![3600, 18000, 18900]),
+      ("30003".to_string(), vec![20000]),
+    ]);
+    assert_eq!(average_scheduled_headway(&schedule), Some(700.0));
+    assert_eq!(average_scheduled_headway(&HashMap::new()), None);
+  }
+
+  #[test]
+  fn test_service_day() {
+    let date = NaiveDate::from_ymd_opt(2025, 3, 8).unwrap();
+    let (start, end) = service_day_bounds(date);
+    // Daylight saving time starts overnight, so this service day is an hour short.
+    assert_eq!(end - start, 23 * 60 * 60);
+    assert_eq!(service_date(start), date);
+    assert_eq!(service_date(end - 1), date);
+    assert_eq!(service_date(end), date.succ_opt().unwrap());
+  }
+}
@@ -0,0 +1,99 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// Where a platform sits along its line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinePosition {
+  /// Which way trips stopping here run in the schedule.
+  pub direction: u8,
+  /// Scheduled running time to the platform, in seconds, counted from the start of the longest
+  /// trip running this way. Branch stops before that start come out negative.
+  pub secs: i64,
+}
+
+/// Scheduled running times along one line, by platform stop ID.
+///
+/// `TrainTracker` gives each train's next platform, so this places trains along the line in the
+/// order they run, and the scheduled time between two platforms is how far apart trains at them
+/// are. Each platform only serves one direction, so positions are only comparable when their
+/// directions match.
+#[derive(Debug, Default)]
+pub struct RunningTimes {
+  stops: HashMap<i32, LinePosition>,
+}
+
+impl RunningTimes {
+  /// Builds the running times from the line's stop patterns, each with the direction it runs and
+  /// its platforms in order with the scheduled time since its first stop, like
+  /// [`crate::cta::gtfs::CtaGTFS::stop_patterns`] returns.
+  ///
+  /// The longest pattern in each direction sets where the times count from. Shorter ones, like
+  /// short turns and branches, are lined up with it at the first platform they share.
+  pub fn new(mut patterns: Vec<(u8, Vec<(i32, u32)>)>) -> Self {
+    patterns.sort_by_key(|(direction, stops)| (*direction, Reverse(stops.len())));
+    let mut stops: HashMap<i32, LinePosition> = HashMap::new();
+    for (direction, pattern) in patterns {
+      let shift = pattern
+        .iter()
+        .find_map(|(stop_id, secs)| {
+          stops
+            .get(stop_id)
+            .filter(|known| known.direction == direction)
+            .map(|known| known.secs - i64::from(*secs))
+        })
+        .unwrap_or(0);
+      for (stop_id, secs) in pattern {
+        stops.entry(stop_id).or_insert(LinePosition {
+          direction,
+          secs: i64::from(secs) + shift,
+        });
+      }
+    }
+    Self { stops }
+  }
+
+  pub fn position(&self, stop_id: i32) -> Option<LinePosition> {
+    self.stops.get(&stop_id).copied()
+  }
+
+  /// Whether a train heading to `next_stop_id` is on its way to `stop_id` or already past it.
+  /// `None` when either platform isn't in the schedule.
+  pub fn reached(&self, next_stop_id: i32, stop_id: i32) -> Option<bool> {
+    let train = self.position(next_stop_id)?;
+    let stop = self.position(stop_id)?;
+    Some(train.direction == stop.direction && train.secs >= stop.secs)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_running_times() {
+    let times = RunningTimes::new(vec![
+      // A short turn, then the full trip, then a branch leaving the trunk at 3.
+      (0, vec![(2, 0), (3, 120)]),
+      (0, vec![(1, 0), (2, 60), (3, 180), (4, 300)]),
+      (0, vec![(3, 0), (5, 90)]),
+      (1, vec![(14, 0), (13, 100), (12, 200), (11, 260)]),
+    ]);
+    assert_eq!(
+      times.position(2),
+      Some(LinePosition {
+        direction: 0,
+        secs: 60
+      })
+    );
+    assert_eq!(times.position(5).map(|p| p.secs), Some(270));
+    assert_eq!(times.position(12).map(|p| p.secs), Some(200));
+    assert_eq!(times.position(6), None);
+
+    assert_eq!(times.reached(3, 3), Some(true));
+    assert_eq!(times.reached(4, 2), Some(true));
+    assert_eq!(times.reached(2, 4), Some(false));
+    // Heading the other way.
+    assert_eq!(times.reached(11, 2), Some(false));
+    assert_eq!(times.reached(6, 2), None);
+  }
+}
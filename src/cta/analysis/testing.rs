@@ -0,0 +1,59 @@
+//! Builders for the positions the analysis tests feed in. Tests change the fields they care about
+//! with struct update syntax, like `TTPosition { lat: 41.9, ..train(801, 40380) }`.
+
+use chrono::NaiveDateTime;
+
+use crate::cta::bustracker::Vehicle;
+use crate::cta::traintracker::{FleetSnapshot, LRouteCode, TTPosition, TTRoute};
+
+/// A train on its way to `next_station_id`.
+pub fn train(run_number: i32, next_station_id: i32) -> TTPosition {
+  let time = NaiveDateTime::default();
+  TTPosition {
+    run_number,
+    route: None,
+    destination_station: 30171,
+    destination_name: "O'Hare".to_string(),
+    train_direction: 1,
+    next_station_id,
+    next_stop_id: 0,
+    next_station_name: String::new(),
+    prediction_time: time,
+    arrival_time: time,
+    is_approaching: false,
+    is_delayed: false,
+    lat: 0.0,
+    lon: 0.0,
+    heading: 0,
+  }
+}
+
+/// One poll of `trains`, all on `route`.
+pub fn fleet(route: LRouteCode, trains: Vec<TTPosition>) -> FleetSnapshot {
+  FleetSnapshot::new(
+    NaiveDateTime::default(),
+    vec![TTRoute {
+      name: route,
+      trains,
+    }],
+  )
+}
+
+/// A bus on `rt` with no trip or block.
+pub fn bus(vid: &str, rt: &str) -> Vehicle {
+  Vehicle {
+    lat: 0.0,
+    lon: 0.0,
+    hdg: 0,
+    tmstmp: String::new(),
+    vid: vid.to_string(),
+    pid: 0,
+    rt: rt.to_string(),
+    des: String::new(),
+    dly: None,
+    zone: String::new(),
+    pdist: None,
+    tatripid: None,
+    tablockid: String::new(),
+  }
+}
@@ -0,0 +1,149 @@
+use crate::cta::stations::CtaStations;
+use crate::cta::traintracker::{LRouteCode, TTFollowEta};
+
+/// Free transfers between neighbouring Loop stations that have separate map IDs, through the
+/// pedestrian tunnels under State and Jackson.
+const WALKING_TRANSFERS: [(i32, i32); 2] = [
+  // Lake (Red) and State/Lake
+  (41660, 40260),
+  // Jackson (Red) and Jackson (Blue)
+  (40560, 40070),
+];
+
+/// One ride on one line, from boarding to getting off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leg {
+  pub route: LRouteCode,
+  pub board: i32,
+  pub alight: i32,
+}
+
+/// Ways to ride between two stations by map ID. Every line serving both gives a one-leg journey.
+/// Otherwise the single transfer with the shortest detour is picked, going by straight-line
+/// distance between stations.
+pub fn journeys(stations: &CtaStations, from: i32, to: i32) -> Vec<Vec<Leg>> {
+  let from_lines = stations.lines_at(from);
+  let to_lines = stations.lines_at(to);
+  let direct: Vec<Vec<Leg>> = from_lines
+    .iter()
+    .filter(|route| to_lines.contains(route))
+    .map(|route| {
+      vec![Leg {
+        route: *route,
+        board: from,
+        alight: to,
+      }]
+    })
+    .collect();
+  if !direct.is_empty() {
+    return direct;
+  }
+
+  let (Some(from_location), Some(to_location)) = (stations.location(from), stations.location(to))
+  else {
+    return Vec::new();
+  };
+  let map_ids = stations.map_ids();
+  let mut best: Option<(f32, Vec<Leg>)> = None;
+  for first in &from_lines {
+    for second in &to_lines {
+      let transfers = map_ids
+        .iter()
+        .map(|id| (*id, *id))
+        .chain(
+          WALKING_TRANSFERS
+            .iter()
+            .flat_map(|(a, b)| [(*a, *b), (*b, *a)]),
+        )
+        .filter(|(off, on)| {
+          stations.serves(*off, *first) == Some(true) && stations.serves(*on, *second) == Some(true)
+        });
+      for (off, on) in transfers {
+        let (Some(off_location), Some(on_location)) =
+          (stations.location(off), stations.location(on))
+        else {
+          continue;
+        };
+        let detour = distance(from_location, off_location) + distance(on_location, to_location);
+        if best.as_ref().is_none_or(|(shortest, _)| detour < *shortest) {
+          best = Some((
+            detour,
+            vec![
+              Leg {
+                route: *first,
+                board: from,
+                alight: off,
+              },
+              Leg {
+                route: *second,
+                board: on,
+                alight: to,
+              },
+            ],
+          ));
+        }
+      }
+    }
+  }
+  best.map(|(_, legs)| vec![legs]).unwrap_or_default()
+}
+
+/// Seconds a followed train is predicted to take from `board` to `alight`, if it's calling at both
+/// in that order.
+pub fn ride_time(etas: &[TTFollowEta], board: i32, alight: i32) -> Option<i64> {
+  let boarding = etas.iter().position(|eta| eta.station_id == board)?;
+  let alighting = etas[boarding..]
+    .iter()
+    .find(|eta| eta.station_id == alight)?;
+  Some(
+    alighting
+      .arrival_time
+      .signed_duration_since(etas[boarding].arrival_time)
+      .num_seconds(),
+  )
+}
+
+/// Rough distance between two latitude and longitude pairs. Only used to compare transfers, so
+/// the curvature of the earth is ignored.
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+  // A degree of longitude is about three quarters of a degree of latitude in Chicago.
+  ((a.0 - b.0).powi(2) + ((a.1 - b.1) * 0.74).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::cta::traintracker::{LRouteName, TrainDirection};
+
+  fn eta(station_id: i32, minute: u32) -> TTFollowEta {
+    let time = chrono::NaiveDate::from_ymd_opt(2025, 11, 3)
+      .unwrap()
+      .and_hms_opt(8, minute, 0)
+      .unwrap();
+    TTFollowEta {
+      station_id,
+      stop_id: 0,
+      station_name: String::new(),
+      run_number: 123,
+      route: LRouteName::Blue,
+      destination_station: 30171,
+      destination_name: "O'Hare".to_string(),
+      train_direction: TrainDirection::Northbound,
+      prediction_time: time,
+      arrival_time: time,
+      is_approaching: false,
+      is_scheduled: false,
+      is_delayed: false,
+      is_faulted: false,
+    }
+  }
+
+  #[test]
+  fn test_ride_time() {
+    let etas = [eta(40070, 2), eta(40370, 4), eta(40380, 5), eta(40490, 9)];
+    assert_eq!(ride_time(&etas, 40370, 40490), Some(5 * 60));
+    // Already past the boarding station.
+    assert_eq!(ride_time(&etas, 40490, 40370), None);
+    assert_eq!(ride_time(&etas, 41020, 40490), None);
+  }
+}
@@ -1,15 +1,38 @@
 use chrono::prelude::*;
 use chrono_tz::{America::Chicago, Tz};
-use reqwest::get;
-use serde::{Deserialize, Serialize};
-use serde_with::{serde, serde_as, DisplayFromStr};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_with::{serde, serde_as, DisplayFromStr, PickFirst};
 use std::result::Result;
+use std::str::FromStr;
 use thiserror::Error;
 
 #[derive(Deserialize, Debug)]
 struct BTResponse<I> {
   #[serde(rename = "bustime-response")]
-  bustime_response: I,
+  bustime_response: BTBody<I>,
+}
+
+/// The contents of the `bustime-response` envelope. Bus Tracker reports problems with a request
+/// as a list of errors next to whatever data it did find.
+#[derive(Deserialize, Debug)]
+struct BTBody<I> {
+  #[serde(default)]
+  error: Vec<BTError>,
+  #[serde(flatten)]
+  data: I,
+}
+
+#[derive(Deserialize, Debug)]
+struct BTError {
+  msg: String,
+}
+
+impl BTError {
+  /// Whether this only says nothing matched, like "No data found for parameter" or "No arrival
+  /// times", which means an empty result rather than a failed request.
+  fn is_no_data(&self) -> bool {
+    self.msg.starts_with("No ")
+  }
 }
 
 #[derive(Deserialize, Debug)]
@@ -19,15 +42,57 @@ struct GetTimeResponse {
 
 #[derive(Deserialize, Debug)]
 struct GetVehiclesResponse {
+  /// Missing when none of the requested vehicles or routes are active.
+  #[serde(default)]
   vehicle: Vec<Vehicle>,
 }
 
 #[derive(Deserialize, Debug)]
 struct GetPredictionsResponse {
+  /// Missing when there are no predictions for the requested stops or vehicles.
+  #[serde(default)]
   prd: Vec<Prediction>,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Deserialize, Debug)]
+struct GetRoutesResponse {
+  #[serde(default)]
+  routes: Vec<Route>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GetDirectionsResponse {
+  #[serde(default)]
+  directions: Vec<RouteDirection>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GetStopsResponse {
+  #[serde(default)]
+  stops: Vec<Stop>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GetPatternsResponse {
+  #[serde(default)]
+  ptr: Vec<Pattern>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GetDetoursResponse {
+  /// Missing when there are no detours on the requested route.
+  #[serde(default)]
+  dtrs: Vec<Detour>,
+}
+
+#[derive(Deserialize, Debug)]
+struct GetServiceBulletinsResponse {
+  /// Missing when nothing is posted for the requested routes or stops.
+  #[serde(default)]
+  sb: Vec<ServiceBulletin>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Garage {
   Garage103rd,
   Kedzie,
@@ -71,6 +136,9 @@ pub struct Vehicle {
   pub dly: Option<bool>,
   pub zone: String,
   pub pdist: Option<i32>,
+  /// The trip the bus is running now. Predictions for a later trip on the same block mean the bus
+  /// still has to finish this one, or lay over, first.
+  pub tatripid: Option<String>,
   /// The TA Block ID field is rather odd.
   /// It's in the form of "Route -GXX" where G corresponds to the bus's garage ID.
   pub tablockid: String,
@@ -102,6 +170,187 @@ pub enum PredictionType {
   A,
   D,
 }
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Route {
+  pub rt: String,
+  pub rtnm: String,
+  /// Hex color used for the route on CTA maps, like "#336633".
+  pub rtclr: String,
+  /// Route designator shown to riders. Only sent by v3.
+  pub rtdd: Option<String>,
+}
+
+/// A direction a route runs in. v2 only sends the direction's name as `dir`, while v3 sends an ID
+/// to query with and a separate display name.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RouteDirection {
+  #[serde(alias = "dir")]
+  pub id: String,
+  pub name: Option<String>,
+}
+
+impl RouteDirection {
+  pub fn name(&self) -> &str {
+    self.name.as_deref().unwrap_or(&self.id)
+  }
+}
+
+#[serde_as]
+#[derive(Deserialize, Debug, Clone)]
+pub struct Stop {
+  #[serde_as(as = "DisplayFromStr")]
+  pub stpid: i32,
+  pub stpnm: String,
+  #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+  pub lat: f64,
+  #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+  pub lon: f64,
+  /// IDs of detours that add this stop. Only sent by v3.
+  #[serde(default)]
+  pub dtradd: Vec<String>,
+  /// IDs of detours that skip this stop. Only sent by v3.
+  #[serde(default)]
+  pub dtrrem: Vec<String>,
+}
+
+#[serde_as]
+#[derive(Deserialize, Debug, Clone)]
+pub struct Pattern {
+  #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+  pub pid: i32,
+  /// Length of the pattern in feet.
+  #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+  pub ln: f64,
+  pub rtdir: String,
+  pub pt: Vec<PatternPoint>,
+  /// The detour this pattern belongs to, if it's a detoured version of a regular pattern. Only
+  /// sent by v3.
+  pub dtrid: Option<String>,
+  /// Points of the detoured path. Only sent by v3.
+  #[serde(default)]
+  pub dtrpt: Vec<PatternPoint>,
+}
+
+impl Pattern {
+  /// Stops a bus `pdist` feet into the pattern calls at until it reaches `stpid`, counting that
+  /// stop. `None` if the stop isn't on this pattern.
+  pub fn stops_until(&self, pdist: f64, stpid: i32) -> Option<usize> {
+    let stop_pdist = self.pt.iter().find(|p| p.stpid == Some(stpid))?.pdist?;
+    Some(
+      self
+        .pt
+        .iter()
+        .filter(|p| p.typ == PatternPointType::S)
+        .filter_map(|p| p.pdist)
+        .filter(|stop| *stop > pdist && *stop <= stop_pdist)
+        .count(),
+    )
+  }
+
+  /// The last stop a bus `pdist` feet into the pattern has reached.
+  pub fn stop_at(&self, pdist: f64) -> Option<&str> {
+    self
+      .pt
+      .iter()
+      .rfind(|p| p.typ == PatternPointType::S && p.pdist.is_some_and(|stop| stop <= pdist))?
+      .stpnm
+      .as_deref()
+  }
+}
+
+#[serde_as]
+#[derive(Deserialize, Debug, Clone)]
+pub struct PatternPoint {
+  pub seq: i32,
+  #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+  pub lat: f64,
+  #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+  pub lon: f64,
+  pub typ: PatternPointType,
+  /// Only set on stops.
+  #[serde_as(as = "Option<DisplayFromStr>")]
+  #[serde(default)]
+  pub stpid: Option<i32>,
+  /// Only set on stops.
+  pub stpnm: Option<String>,
+  /// Distance along the pattern in feet. Only set on stops.
+  pub pdist: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatternPointType {
+  /// A bus stop.
+  S,
+  /// A waypoint only there to draw the path.
+  W,
+}
+
+#[serde_as]
+#[derive(Deserialize, Debug, Clone)]
+pub struct Detour {
+  pub id: String,
+  /// Goes up each time the CTA edits the detour.
+  #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+  pub ver: i32,
+  /// 1 while the detour is in effect and 0 once it's been cancelled.
+  #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+  pub st: i32,
+  pub desc: String,
+  #[serde(default)]
+  pub rtdirs: Vec<DetourRouteDirection>,
+  pub startdt: String,
+  pub enddt: String,
+}
+
+impl Detour {
+  pub fn is_active(&self) -> bool {
+    self.st == 1
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DetourRouteDirection {
+  pub rt: String,
+  pub dir: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ServiceBulletin {
+  pub nm: String,
+  pub sbj: String,
+  /// Full text of the bulletin, which may contain HTML.
+  pub dtl: String,
+  /// Short version of `dtl`.
+  #[serde(default)]
+  pub brf: String,
+  /// Cause of the disruption. Only sent by v3.
+  pub cse: Option<String>,
+  /// Effect on service. Only sent by v3.
+  pub efct: Option<String>,
+  /// "High", "Medium" or "Low".
+  pub prty: String,
+  #[serde(default)]
+  pub srvc: Vec<BulletinService>,
+  /// Last modified time. Only sent by v3.
+  #[serde(rename = "mod")]
+  pub modified: Option<String>,
+  /// Link to more details. Only sent by v3.
+  pub url: Option<String>,
+}
+
+/// A route, direction or stop a bulletin applies to. Any of these can be blank.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct BulletinService {
+  #[serde(default)]
+  pub rt: String,
+  #[serde(default)]
+  pub rtdir: String,
+  #[serde(default)]
+  pub stpid: String,
+  #[serde(default)]
+  pub stpnm: String,
+}
 #[derive(Error, Debug)]
 pub enum BusTrackerError {
   #[error("Failed to fetch data from BusTracker API")]
@@ -114,6 +363,37 @@ pub enum BusTrackerError {
   TimeOutOfRange,
   #[error("Integer Parsing Error")]
   ParseIntError(#[from] std::num::ParseIntError),
+  #[error("BusTracker API returned an error: {0}")]
+  Api(String),
+}
+
+/// Which version of the Bus Tracker API to call. v3 adds detours and a few extra fields, and is
+/// otherwise a drop-in replacement for v2.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ApiVersion {
+  #[default]
+  V2,
+  V3,
+}
+
+impl ApiVersion {
+  fn base_url(self) -> &'static str {
+    match self {
+      ApiVersion::V2 => "http://www.ctabustracker.com/bustime/api/v2/",
+      ApiVersion::V3 => "https://www.ctabustracker.com/bustime/api/v3/",
+    }
+  }
+}
+
+impl FromStr for ApiVersion {
+  type Err = String;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      "v2" | "2" => Ok(ApiVersion::V2),
+      "v3" | "3" => Ok(ApiVersion::V3),
+      other => Err(format!("Unknown Bus Tracker API version: {other}")),
+    }
+  }
 }
 #[derive(Serialize, Debug)]
 pub struct VehiclesParameters {
@@ -150,30 +430,114 @@ pub enum StpidOrVid {
   },
 }
 
+#[derive(Serialize, Debug)]
+pub struct StopsParameters {
+  pub rt: String,
+  /// A direction ID from `get_directions`.
+  pub dir: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct PatternsParameters {
+  #[serde(flatten)]
+  pub search: PidOrRt,
+}
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum PidOrRt {
+  Pid { pid: Vec<i32> },
+  Rt { rt: String },
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct DetoursParameters {
+  pub rt: Option<String>,
+  /// Only used along with `rt`.
+  pub rtdir: Option<String>,
+}
+
+/// At least one of `rt` or `stpid` has to be set.
+#[derive(Serialize, Debug, Default)]
+pub struct ServiceBulletinsParameters {
+  pub rt: Vec<String>,
+  /// Only used along with `rt`.
+  pub rtdir: Option<String>,
+  pub stpid: Vec<String>,
+}
+
 #[derive(Clone)]
 pub struct BusTracker {
   token: String,
+  version: ApiVersion,
+  client: reqwest::Client,
 }
 impl BusTracker {
-  const BASE_URL: &str = "http://www.ctabustracker.com/bustime/api/v2/";
   pub fn new(token: &str) -> Self {
     Self {
       token: token.to_string(),
+      version: ApiVersion::default(),
+      client: reqwest::Client::new(),
+    }
+  }
+
+  pub fn with_version(mut self, version: ApiVersion) -> Self {
+    self.version = version;
+    self
+  }
+
+  /// Calls `endpoint` and unwraps the `bustime-response` envelope.
+  async fn request<I: DeserializeOwned>(
+    &self,
+    version: ApiVersion,
+    endpoint: &str,
+    params: &[(&str, String)],
+  ) -> Result<I, BusTrackerError> {
+    let resp_text = self
+      .request_builder(version, endpoint, params)
+      .send()
+      .await?
+      .text()
+      .await?;
+    Self::parse_response(&resp_text)
+  }
+
+  fn request_builder(
+    &self,
+    version: ApiVersion,
+    endpoint: &str,
+    params: &[(&str, String)],
+  ) -> reqwest::RequestBuilder {
+    self
+      .client
+      .get(format!("{}{endpoint}", version.base_url()))
+      .query(&[("key", self.token.as_str()), ("format", "json")])
+      .query(params)
+  }
+
+  /// Unwraps the `bustime-response` envelope, failing if Bus Tracker reported an error other than
+  /// finding nothing.
+  fn parse_response<I: DeserializeOwned>(text: &str) -> Result<I, BusTrackerError> {
+    let body = serde_json::from_str::<BTResponse<I>>(text)
+      .inspect_err(|e| println!("{text}\nError: {e}"))?
+      .bustime_response;
+    let errors: Vec<&str> = body
+      .error
+      .iter()
+      .filter(|e| !e.is_no_data())
+      .map(|e| e.msg.as_str())
+      .collect();
+    if errors.is_empty() {
+      Ok(body.data)
+    } else {
+      Err(BusTrackerError::Api(errors.join("; ")))
     }
   }
-  pub async fn get_time(&self) -> Result<DateTime<Tz>, BusTrackerError> {
-    let resp_text = get(format!(
-      "{}gettime?key={}&format=json",
-      Self::BASE_URL,
-      self.token
-    ))
-    .await?
-    .text()
-    .await?;
 
+  pub async fn get_time(&self) -> Result<DateTime<Tz>, BusTrackerError> {
     Self::parse_bustime_secs(
-      &serde_json::from_str::<BTResponse<GetTimeResponse>>(resp_text.as_str())?
-        .bustime_response
+      &self
+        .request::<GetTimeResponse>(self.version, "gettime", &[])
+        .await?
         .tm,
     )
   }
@@ -182,23 +546,18 @@ impl BusTracker {
     &self,
     options: VehiclesParameters,
   ) -> Result<Vec<Vehicle>, BusTrackerError> {
-    let params: String = match options.search {
-      VidOrRt::Vid { vehicle_ids: vid } => format!("vid={}", vid.join(",")),
-      VidOrRt::Rt { route_codes: rt } => format!("rt={}", rt.join(",")),
+    let search = match options.search {
+      VidOrRt::Vid { vehicle_ids: vid } => ("vid", vid.join(",")),
+      VidOrRt::Rt { route_codes: rt } => ("rt", rt.join(",")),
     };
-    let resp_text = get(format!(
-      "{}getvehicles?key={}&format=json&tmres=s&{}",
-      Self::BASE_URL,
-      self.token,
-      params
-    ))
-    .await?
-    .text()
-    .await?;
     Ok(
-      serde_json::from_str::<BTResponse<GetVehiclesResponse>>(resp_text.as_str())
-        .inspect_err(|e| println!("{}\nError: {e}", resp_text.as_str()))?
-        .bustime_response
+      self
+        .request::<GetVehiclesResponse>(
+          self.version,
+          "getvehicles",
+          &[("tmres", "s".to_string()), search],
+        )
+        .await?
         .vehicle,
     )
   }
@@ -207,30 +566,128 @@ impl BusTracker {
     &self,
     options: PredictionsParameters,
   ) -> Result<Vec<Prediction>, BusTrackerError> {
-    let params: String = match options.search {
+    let params = match options.search {
       StpidOrVid::StpId { stpid, rt } => match rt {
-        Some(rts) => format!("stpid={}&rt={}", stpid.join(","), rts.join(",")),
-        None => format!("stpid={}", stpid.join(",")),
+        Some(rts) => vec![("stpid", stpid.join(",")), ("rt", rts.join(","))],
+        None => vec![("stpid", stpid.join(","))],
       },
-      StpidOrVid::Vid { vid } => format!("vid={}", vid.join(",")),
+      StpidOrVid::Vid { vid } => vec![("vid", vid.join(","))],
     };
-    let resp_text = get(format!(
-      "{}getpredictions?key={}&format=json&{}",
-      Self::BASE_URL,
-      self.token,
-      params
-    ))
-    .await?
-    .text()
-    .await?;
     Ok(
-      serde_json::from_str::<BTResponse<GetPredictionsResponse>>(resp_text.as_str())
-        .inspect_err(|e| println!("{}\nError: {e}", resp_text.as_str()))?
-        .bustime_response
+      self
+        .request::<GetPredictionsResponse>(self.version, "getpredictions", &params)
+        .await?
         .prd,
     )
   }
 
+  pub async fn get_routes(&self) -> Result<Vec<Route>, BusTrackerError> {
+    Ok(
+      self
+        .request::<GetRoutesResponse>(self.version, "getroutes", &[])
+        .await?
+        .routes,
+    )
+  }
+
+  pub async fn get_directions(&self, rt: &str) -> Result<Vec<RouteDirection>, BusTrackerError> {
+    Ok(
+      self
+        .request::<GetDirectionsResponse>(self.version, "getdirections", &[("rt", rt.to_string())])
+        .await?
+        .directions,
+    )
+  }
+
+  pub async fn get_stops(&self, options: StopsParameters) -> Result<Vec<Stop>, BusTrackerError> {
+    Ok(
+      self
+        .request::<GetStopsResponse>(
+          self.version,
+          "getstops",
+          &[("rt", options.rt), ("dir", options.dir)],
+        )
+        .await?
+        .stops,
+    )
+  }
+
+  pub async fn get_patterns(
+    &self,
+    options: PatternsParameters,
+  ) -> Result<Vec<Pattern>, BusTrackerError> {
+    let params = match options.search {
+      PidOrRt::Pid { pid } => (
+        "pid",
+        pid
+          .iter()
+          .map(ToString::to_string)
+          .collect::<Vec<_>>()
+          .join(","),
+      ),
+      PidOrRt::Rt { rt } => ("rt", rt),
+    };
+    Ok(
+      self
+        .request::<GetPatternsResponse>(self.version, "getpatterns", &[params])
+        .await?
+        .ptr,
+    )
+  }
+
+  /// Patterns for a route from v3, which also lists the detoured versions of each pattern with
+  /// `dtrid` set.
+  pub async fn get_detour_patterns(&self, rt: &str) -> Result<Vec<Pattern>, BusTrackerError> {
+    Ok(
+      self
+        .request::<GetPatternsResponse>(ApiVersion::V3, "getpatterns", &[("rt", rt.to_string())])
+        .await?
+        .ptr,
+    )
+  }
+
+  /// Detours were added in v3, so this always calls v3 whatever version is configured.
+  pub async fn get_detours(
+    &self,
+    options: DetoursParameters,
+  ) -> Result<Vec<Detour>, BusTrackerError> {
+    let mut params = Vec::new();
+    if let Some(rt) = options.rt {
+      params.push(("rt", rt));
+      if let Some(rtdir) = options.rtdir {
+        params.push(("rtdir", rtdir));
+      }
+    }
+    Ok(
+      self
+        .request::<GetDetoursResponse>(ApiVersion::V3, "getdetours", &params)
+        .await?
+        .dtrs,
+    )
+  }
+
+  pub async fn get_service_bulletins(
+    &self,
+    options: ServiceBulletinsParameters,
+  ) -> Result<Vec<ServiceBulletin>, BusTrackerError> {
+    let mut params = Vec::new();
+    if !options.rt.is_empty() {
+      params.push(("rt", options.rt.join(",")));
+      if let Some(rtdir) = options.rtdir {
+        params.push(("rtdir", rtdir));
+      }
+    }
+    if !options.stpid.is_empty() {
+      params.push(("stpid", options.stpid.join(",")));
+    }
+    Ok(
+      self
+        .request::<GetServiceBulletinsResponse>(self.version, "getservicebulletins", &params)
+        .await?
+        .sb,
+    )
+  }
+
   pub fn parse_bustime_secs(timestamp: &str) -> Result<DateTime<chrono_tz::Tz>, BusTrackerError> {
     let tz = Chicago;
     NaiveDateTime::parse_from_str(timestamp, "%Y%m%d %H:%M:%S")?
@@ -271,6 +728,12 @@ impl BusTracker {
       None => Garage::Unknown,
     }
   }
+
+  /// The route a TA Block ID belongs to, the part before the dash. A bus on a block from another
+  /// route is interlined. Some blocks, like "SS -851", aren't named for a route at all.
+  pub fn tablockid_to_route(tablockid: &str) -> &str {
+    tablockid.split('-').next().unwrap_or_default().trim()
+  }
   // pub async fn get_predictions(&self, options: PredictionParameters) -> Result
 }
 #[test]
@@ -302,3 +765,147 @@ fn test_tablockid_to_garage() {
     Garage::Garage77th
   );
 }
+#[test]
+fn test_tablockid_to_route() {
+  assert_eq!(BusTracker::tablockid_to_route("95 -109"), "95");
+  assert_eq!(BusTracker::tablockid_to_route("52A-754"), "52A");
+  assert_eq!(BusTracker::tablockid_to_route("SS -851"), "SS");
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse<I: DeserializeOwned>(json: &str) -> I {
+    BusTracker::parse_response(json).unwrap()
+  }
+
+  #[test]
+  fn test_parse_routes() {
+    for json in [
+      include_str!("fixtures/bustracker/v2_getroutes.json"),
+      include_str!("fixtures/bustracker/v3_getroutes.json"),
+    ] {
+      let routes = parse::<GetRoutesResponse>(json).routes;
+      let clark = routes.iter().find(|r| r.rt == "22").unwrap();
+      assert_eq!(clark.rtnm, "Clark");
+      assert_eq!(clark.rtclr, "#ff0000");
+    }
+  }
+
+  #[test]
+  fn test_parse_directions() {
+    for json in [
+      include_str!("fixtures/bustracker/v2_getdirections.json"),
+      include_str!("fixtures/bustracker/v3_getdirections.json"),
+    ] {
+      let directions = parse::<GetDirectionsResponse>(json).directions;
+      assert_eq!(directions.len(), 2);
+      assert_eq!(directions[0].id, "Northbound");
+      assert_eq!(directions[0].name(), "Northbound");
+    }
+  }
+
+  #[test]
+  fn test_parse_stops() {
+    let stops =
+      parse::<GetStopsResponse>(include_str!("fixtures/bustracker/v2_getstops.json")).stops;
+    assert_eq!(stops[0].stpid, 1836);
+    assert!(stops[0].dtrrem.is_empty());
+    let stops =
+      parse::<GetStopsResponse>(include_str!("fixtures/bustracker/v3_getstops.json")).stops;
+    assert_eq!(stops[0].dtrrem, vec!["4102".to_string()]);
+    assert_eq!(stops[1].dtradd, vec!["4102".to_string()]);
+  }
+
+  #[test]
+  fn test_parse_patterns() {
+    let patterns =
+      parse::<GetPatternsResponse>(include_str!("fixtures/bustracker/v2_getpatterns.json")).ptr;
+    assert_eq!(patterns[0].pid, 3932);
+    assert_eq!(patterns[0].pt[0].stpid, Some(14487));
+    assert_eq!(patterns[0].pt[1].typ, PatternPointType::W);
+    assert_eq!(patterns[0].pt[1].stpid, None);
+    assert_eq!(patterns[0].stops_until(100.0, 1917), Some(1));
+    assert_eq!(patterns[0].stops_until(0.0, 14487), Some(0));
+    assert_eq!(patterns[0].stops_until(0.0, 1), None);
+    assert_eq!(patterns[0].stop_at(700.0), Some("Clark & Van Buren"));
+    assert_eq!(patterns[0].stop_at(600.0), Some("Harrison & Clark"));
+    let patterns =
+      parse::<GetPatternsResponse>(include_str!("fixtures/bustracker/v3_getpatterns.json")).ptr;
+    assert_eq!(patterns[0].dtrid, None);
+    assert_eq!(patterns[1].dtrid.as_deref(), Some("4102"));
+    assert_eq!(
+      patterns[1].dtrpt[1].stpnm.as_deref(),
+      Some("Halsted & Belmont")
+    );
+  }
+
+  #[test]
+  fn test_parse_detours() {
+    let detours =
+      parse::<GetDetoursResponse>(include_str!("fixtures/bustracker/v3_getdetours.json")).dtrs;
+    assert!(detours[0].is_active());
+    assert!(!detours[1].is_active());
+    assert_eq!(detours[0].rtdirs.len(), 2);
+    assert!(BusTracker::parse_bustime(&detours[0].startdt).is_ok());
+  }
+
+  #[test]
+  fn test_parse_service_bulletins() {
+    let bulletins = parse::<GetServiceBulletinsResponse>(include_str!(
+      "fixtures/bustracker/v2_getservicebulletins.json"
+    ))
+    .sb;
+    assert_eq!(bulletins.len(), 2);
+    assert_eq!(bulletins[0].srvc[0].rt, "22");
+    assert_eq!(bulletins[1].srvc[0].rt, "");
+    assert_eq!(bulletins[1].cse, None);
+    let bulletins = parse::<GetServiceBulletinsResponse>(include_str!(
+      "fixtures/bustracker/v3_getservicebulletins.json"
+    ))
+    .sb;
+    assert_eq!(bulletins[0].efct.as_deref(), Some("Detour"));
+    assert_eq!(bulletins[0].modified.as_deref(), Some("20251101 14:32"));
+  }
+
+  #[test]
+  fn test_parse_error_response() {
+    // Bus Tracker reports errors inside an otherwise successful response.
+    let json = include_str!("fixtures/bustracker/v2_error.json");
+    assert!(matches!(
+      BusTracker::parse_response::<GetRoutesResponse>(json),
+      Err(BusTrackerError::Api(msg)) if msg == "Invalid API access key supplied"
+    ));
+    // Finding nothing is an empty result, not an error.
+    let json = include_str!("fixtures/bustracker/v2_no_data.json");
+    assert!(parse::<GetDetoursResponse>(json).dtrs.is_empty());
+    assert!(parse::<GetVehiclesResponse>(json).vehicle.is_empty());
+  }
+
+  #[test]
+  fn test_request_query() {
+    let request = BusTracker::new("key")
+      .request_builder(
+        ApiVersion::V3,
+        "getdetours",
+        &[
+          ("rt", "22".to_string()),
+          ("rtdir", "North & South".to_string()),
+        ],
+      )
+      .build()
+      .unwrap();
+    assert_eq!(
+      request.url().as_str(),
+      "https://www.ctabustracker.com/bustime/api/v3/getdetours?key=key&format=json&rt=22&rtdir=North+%26+South"
+    );
+  }
+
+  #[test]
+  fn test_api_version() {
+    assert_eq!("v3".parse::<ApiVersion>(), Ok(ApiVersion::V3));
+    assert_eq!("2".parse::<ApiVersion>(), Ok(ApiVersion::V2));
+    assert!("v4".parse::<ApiVersion>().is_err());
+  }
+}
@@ -1,5 +1,10 @@
-use gtfs_structures::Gtfs;
-use std::{env, fs, path::Path};
+use chrono::NaiveDate;
+use gtfs_structures::{DirectionType, Gtfs, RawGtfs, Route, RouteType, Stop};
+use std::{
+  collections::{HashMap, HashSet},
+  env, fs,
+  path::Path,
+};
 
 static GTFS_URL: &str = "https://www.transitchicago.com/downloads/sch_data/google_transit.zip";
 static DOCKER_GTFS_PATH: &str = "/data/google_transit.zip";
@@ -80,18 +85,20 @@ impl CtaGTFS {
 
   async fn load_from_web() -> Gtfs {
     let reader = gtfs_structures::GtfsReader::default()
-      .read_stop_times(false)
+      .read_stop_times(true)
       .read_shapes(false)
       .unkown_enum_as_default(true)
       .trim_fields(true);
     reader
+      .raw()
       .read_from_url_async(GTFS_URL)
       .await
+      .and_then(Self::with_rail_stop_times)
       .expect("Error downloading GTFS data. ")
   }
   async fn cache_from_web(cache_directory: &str) -> Gtfs {
     let reader = gtfs_structures::GtfsReader::default()
-      .read_stop_times(false)
+      .read_stop_times(true)
       .read_shapes(false)
       .unkown_enum_as_default(true)
       .trim_fields(true);
@@ -104,17 +111,23 @@ impl CtaGTFS {
     // std::io::copy(&mut body.as_bytes(), &mut out).expect("Failed to copy GTFS content to cache");
     fs::write(path.clone(), body).expect("Error overwriting GTFS data.");
     reader
+      .raw()
       .read_from_path(path)
+      .and_then(Self::with_rail_stop_times)
       .expect("Could not load GTFS data.")
   }
   async fn load_from_cache(cache_directory: &str) -> Gtfs {
     let reader = gtfs_structures::GtfsReader::default()
-      .read_stop_times(false)
+      .read_stop_times(true)
       .read_shapes(false)
       .unkown_enum_as_default(true)
       .trim_fields(true);
     let cached_data_loc = format!("{}/google_transit.zip", cache_directory);
-    match reader.read_from_path(cached_data_loc) {
+    match reader
+      .raw()
+      .read_from_path(cached_data_loc)
+      .and_then(Self::with_rail_stop_times)
+    {
       Ok(data) => data,
       Err(e) => {
         println!("Invalid GTFS data encountered. Refreshing the cache.");
@@ -123,6 +136,27 @@ impl CtaGTFS {
     }
   }
 
+  /// Keeps only the stop times of train trips. Buses make up nearly all of the CTA's stop times,
+  /// and only train schedules are read from them.
+  fn with_rail_stop_times(mut raw: RawGtfs) -> Result<Gtfs, gtfs_structures::Error> {
+    if let (Ok(routes), Ok(trips), Ok(stop_times)) = (&raw.routes, &raw.trips, &mut raw.stop_times)
+    {
+      let rail_routes: HashSet<&str> = routes
+        .iter()
+        .filter(|route| route.route_type != RouteType::Bus)
+        .map(|route| route.id.as_str())
+        .collect();
+      let rail_trips: HashSet<&str> = trips
+        .iter()
+        .filter(|trip| rail_routes.contains(trip.route_id.as_str()))
+        .map(|trip| trip.id.as_str())
+        .collect();
+      stop_times.retain(|stop_time| rail_trips.contains(stop_time.trip_id.as_str()));
+      stop_times.shrink_to_fit();
+    }
+    Gtfs::try_from(raw)
+  }
+
   pub async fn reload_gtfs(&self) {
     match self.cache_dir.clone() {
       Some(dir) => {
@@ -143,16 +177,21 @@ impl CtaGTFS {
       .collect()
   }
 
-  pub fn get_route_name(&self, id: &str) -> std::string::String {
-    // load_gtfs().await;
-    self
+  /// Long name of a route, like "Clark" for "22". `None` for routes GTFS doesn't know.
+  pub fn get_route_name(&self, id: &str) -> Option<String> {
+    self.gtfs_data.get_route(id).ok()?.long_name.clone()
+  }
+
+  /// Every bus route, in route number order.
+  pub fn bus_routes(&self) -> Vec<&Route> {
+    let mut routes: Vec<&Route> = self
       .gtfs_data
-      .get_route(id)
-      .expect("Invalid route ID.")
-      .long_name
-      .as_ref()
-      .expect("Route has no long name")
-      .to_string()
+      .routes
+      .values()
+      .filter(|r| r.route_type == RouteType::Bus)
+      .collect();
+    routes.sort_by(|a, b| route_order(&a.id).cmp(&route_order(&b.id)));
+    routes
   }
 
   pub fn search_stops(&self, search: &str) -> Option<Vec<String>> {
@@ -165,8 +204,236 @@ impl CtaGTFS {
     Some(found_stops).or(None)
   }
 
+  /// Train stations whose name contains `search`. A station named exactly `search` is returned on
+  /// its own, so a name picked from autocomplete never matches several stations.
+  pub fn search_stations(&self, search: &str) -> Vec<&Stop> {
+    let stations: Vec<&Stop> = self
+      .search_stops(search)
+      .unwrap_or_default()
+      .iter()
+      .filter(|id| matches!(id.parse().unwrap_or(0), 40000..=49999))
+      .filter_map(|id| self.gtfs_data.get_stop(id).ok())
+      .collect();
+    match stations
+      .iter()
+      .find(|stop| stop.name.as_deref() == Some(search))
+    {
+      Some(exact) => vec![*exact],
+      None => stations,
+    }
+  }
+
+  /// Bus stops whose name contains `search`. Each side of the street is its own stop, so when
+  /// some are named exactly `search`, all of those are returned and nothing else.
+  pub fn search_bus_stops(&self, search: &str) -> Vec<&Stop> {
+    let stops: Vec<&Stop> = self
+      .search_stops(search)
+      .unwrap_or_default()
+      .iter()
+      .filter(|id| id.parse().is_ok_and(|id: i32| id < 30000))
+      .filter_map(|id| self.gtfs_data.get_stop(id).ok())
+      .collect();
+    let exact: Vec<&Stop> = stops
+      .iter()
+      .filter(|stop| stop.name.as_deref() == Some(search))
+      .copied()
+      .collect();
+    if exact.is_empty() {
+      stops
+    } else {
+      exact
+    }
+  }
+
+  /// Looks up bus stops by stop ID, skipping any that aren't in the schedule.
+  pub fn get_bus_stops(&self, ids: &[String]) -> Vec<&Stop> {
+    ids
+      .iter()
+      .filter_map(|id| self.gtfs_data.get_stop(id).ok())
+      .collect()
+  }
+
   pub fn get_stop_name(&self, id: i32) -> Option<String> {
     // load_gtfs().await;
     self.gtfs_data.get_stop(&id.to_string()).ok()?.clone().name
   }
+
+  /// Number of trips scheduled on a route for the service day starting on `date`.
+  pub fn scheduled_trips(&self, route_id: &str, date: NaiveDate) -> usize {
+    let mut running: HashMap<&str, bool> = HashMap::new();
+    self
+      .gtfs_data
+      .trips
+      .values()
+      .filter(|trip| trip.route_id == route_id)
+      .filter(|trip| {
+        *running.entry(trip.service_id.as_str()).or_insert_with(|| {
+          self
+            .gtfs_data
+            .trip_days(&trip.service_id, date)
+            .contains(&0)
+        })
+      })
+      .count()
+  }
+
+  /// Scheduled arrivals at each stop on a train route for the service day starting on `date`, by
+  /// stop ID and in order. Only train stop times are loaded, so bus routes have none. Times are seconds after midnight on `date`, so trips running past midnight
+  /// go past a day, like in the schedule itself.
+  pub fn stop_schedule(&self, route_id: &str, date: NaiveDate) -> HashMap<String, Vec<u32>> {
+    let mut running: HashMap<&str, bool> = HashMap::new();
+    let mut schedule: HashMap<String, Vec<u32>> = HashMap::new();
+    for trip in self.gtfs_data.trips.values() {
+      if trip.route_id != route_id
+        || !*running.entry(trip.service_id.as_str()).or_insert_with(|| {
+          self
+            .gtfs_data
+            .trip_days(&trip.service_id, date)
+            .contains(&0)
+        })
+      {
+        continue;
+      }
+      for stop_time in &trip.stop_times {
+        if let Some(arrival) = stop_time.arrival_time {
+          schedule
+            .entry(stop_time.stop.id.clone())
+            .or_default()
+            .push(arrival);
+        }
+      }
+    }
+    for arrivals in schedule.values_mut() {
+      arrivals.sort_unstable();
+    }
+    schedule
+  }
+
+  /// The distinct stop patterns of a train route's trips. Each has the direction its trips run,
+  /// `1` for inbound, and their platforms in order with the scheduled seconds since the first stop.
+  pub fn stop_patterns(&self, route_id: &str) -> Vec<(u8, Vec<(i32, u32)>)> {
+    let mut seen: HashSet<(u8, Vec<i32>)> = HashSet::new();
+    let mut patterns = Vec::new();
+    for trip in self
+      .gtfs_data
+      .trips
+      .values()
+      .filter(|trip| trip.route_id == route_id)
+    {
+      let stops: Vec<(i32, u32)> = trip
+        .stop_times
+        .iter()
+        .filter_map(|stop_time| Some((stop_time.stop.id.parse().ok()?, stop_time.arrival_time?)))
+        .collect();
+      let Some(&(_, start)) = stops.first() else {
+        continue;
+      };
+      let direction = u8::from(trip.direction_id == Some(DirectionType::Inbound));
+      if seen.insert((direction, stops.iter().map(|(id, _)| *id).collect())) {
+        patterns.push((
+          direction,
+          stops
+            .into_iter()
+            .map(|(id, arrival)| (id, arrival.saturating_sub(start)))
+            .collect(),
+        ));
+      }
+    }
+    patterns
+  }
+}
+
+/// The way buses are heading at a stop, like "Southbound". The CTA describes bus stops as
+/// "Clark & Belmont, Southbound, Southwest Corner".
+pub fn bus_stop_direction(stop: &Stop) -> Option<&str> {
+  stop
+    .description
+    .as_deref()?
+    .split(',')
+    .map(str::trim)
+    .find(|part| part.ends_with("bound"))
+}
+
+/// Sorts routes by their number, then by their letters, so "X9" comes after "9" and before "10".
+fn route_order(id: &str) -> (u32, &str) {
+  let number: String = id.chars().filter(char::is_ascii_digit).collect();
+  (number.parse().unwrap_or(u32::MAX), id)
+}
+
+/// A colored square emoji close to a route's color, for places that can only show text.
+pub fn route_color_emoji(route: &Route) -> &'static str {
+  let (r, g, b) = (
+    f32::from(route.color.r),
+    f32::from(route.color.g),
+    f32::from(route.color.b),
+  );
+  let max = r.max(g).max(b);
+  let min = r.min(g).min(b);
+  // Grays, like the CTA's usual bus route color.
+  if max - min < 40.0 {
+    return if max < 128.0 { "⬛" } else { "⬜" };
+  }
+  let hue = if (max - r).abs() < f32::EPSILON {
+    60.0 * ((g - b) / (max - min)).rem_euclid(6.0)
+  } else if (max - g).abs() < f32::EPSILON {
+    60.0 * ((b - r) / (max - min) + 2.0)
+  } else {
+    60.0 * ((r - g) / (max - min) + 4.0)
+  };
+  match hue {
+    h if h < 20.0 => "🟥",
+    h if h < 45.0 => "🟧",
+    h if h < 70.0 => "🟨",
+    h if h < 170.0 => "🟩",
+    h if h < 260.0 => "🟦",
+    h if h < 330.0 => "🟪",
+    _ => "🟥",
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_bus_stop_direction() {
+    let stop = |description: &str| Stop {
+      description: Some(description.to_string()),
+      ..Default::default()
+    };
+    assert_eq!(
+      bus_stop_direction(&stop("Clark & Belmont, Southbound, Southwest Corner")),
+      Some("Southbound")
+    );
+    assert_eq!(
+      bus_stop_direction(&stop(
+        "Jackson & Austin Terminal, Northeastbound, Bus Terminal"
+      )),
+      Some("Northeastbound")
+    );
+    assert_eq!(bus_stop_direction(&stop("Clark & Belmont")), None);
+    assert_eq!(bus_stop_direction(&Stop::default()), None);
+  }
+
+  #[test]
+  fn test_route_order() {
+    let mut routes = vec!["X9", "10", "9", "J14", "N9", "1"];
+    routes.sort_by_key(|id| route_order(id));
+    assert_eq!(routes, vec!["1", "9", "N9", "X9", "10", "J14"]);
+  }
+
+  #[test]
+  fn test_route_color_emoji() {
+    let route = |hex: &str| {
+      let mut route = Route::default();
+      route.color.r = u8::from_str_radix(&hex[0..2], 16).unwrap();
+      route.color.g = u8::from_str_radix(&hex[2..4], 16).unwrap();
+      route.color.b = u8::from_str_radix(&hex[4..6], 16).unwrap();
+      route
+    };
+    assert_eq!(route_color_emoji(&route("565a5c")), "⬛");
+    assert_eq!(route_color_emoji(&route("c60c30")), "🟥");
+    assert_eq!(route_color_emoji(&route("00a1de")), "🟦");
+    assert_eq!(route_color_emoji(&route("009b3a")), "🟩");
+  }
 }
@@ -7,6 +7,7 @@ pub mod alerts;
 pub mod analysis;
 pub mod bustracker;
 pub mod gtfs;
+pub mod roster;
 pub mod stations;
 pub mod traintracker;
 
@@ -0,0 +1,127 @@
+use serde::Deserialize;
+use std::fmt::Display;
+
+/// What powers a bus.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Propulsion {
+  Diesel,
+  Hybrid,
+  Electric,
+}
+
+impl Propulsion {
+  pub const ALL: [Propulsion; 3] = [Propulsion::Diesel, Propulsion::Hybrid, Propulsion::Electric];
+}
+
+impl Display for Propulsion {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.write_str(match self {
+      Propulsion::Diesel => "Diesel",
+      Propulsion::Hybrid => "Hybrid",
+      Propulsion::Electric => "Electric",
+    })
+  }
+}
+
+/// A run of bus numbers delivered together, all the same model.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct BusSeries {
+  pub first: u32,
+  pub last: u32,
+  pub make: String,
+  pub model: String,
+  pub year: i32,
+  pub propulsion: Propulsion,
+}
+
+impl BusSeries {
+  /// Year, make and model, like "2014 Nova Bus LFS".
+  pub fn description(&self) -> String {
+    format!("{} {} {}", self.year, self.make, self.model)
+  }
+}
+
+/// Known bus series, read from `fleet.json` in the assets directory so it can be kept up to date
+/// as buses are delivered and retired without a rebuild.
+#[derive(Debug, Default)]
+pub struct BusRoster {
+  series: Vec<BusSeries>,
+}
+
+impl BusRoster {
+  /// Loads the roster, falling back to an empty one if the file is missing or malformed.
+  pub fn load() -> Self {
+    let assets = std::env::var("ASSETS_DIR").unwrap_or_else(|_| "./assets/".to_string());
+    let roster = std::fs::read_to_string(format!("{assets}/fleet.json"))
+      .map_err(|e| e.to_string())
+      .and_then(|json| Self::from_json(&json).map_err(|e| e.to_string()));
+    match roster {
+      Ok(roster) => roster,
+      Err(e) => {
+        println!("Could not load bus fleet roster: {e}");
+        Self::default()
+      }
+    }
+  }
+
+  pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+    Ok(Self {
+      series: serde_json::from_str(json)?,
+    })
+  }
+
+  /// The series a Bus Tracker vehicle ID belongs to.
+  pub fn lookup(&self, vid: &str) -> Option<&BusSeries> {
+    let number: u32 = vid.trim().parse().ok()?;
+    self
+      .series
+      .iter()
+      .find(|series| (series.first..=series.last).contains(&number))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_lookup() {
+    let roster = BusRoster::from_json(
+      r#"[
+        {"first": 700, "last": 701, "make": "New Flyer", "model": "XE40", "year": 2014, "propulsion": "electric"},
+        {"first": 7900, "last": 8449, "make": "Nova Bus", "model": "LFS", "year": 2014, "propulsion": "diesel"}
+      ]"#,
+    )
+    .unwrap();
+    assert_eq!(
+      roster.lookup("8312").map(BusSeries::description),
+      Some("2014 Nova Bus LFS".to_string())
+    );
+    assert_eq!(
+      roster.lookup("701").map(|s| s.propulsion),
+      Some(Propulsion::Electric)
+    );
+    assert_eq!(roster.lookup("702"), None);
+    assert_eq!(roster.lookup("not a bus"), None);
+  }
+
+  #[test]
+  fn test_bundled_roster() {
+    let json =
+      std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/fleet.json")).unwrap();
+    let mut series = BusRoster::from_json(&json).unwrap().series;
+    series.sort_by_key(|s| s.first);
+    for s in &series {
+      assert!(s.first <= s.last, "{} runs backwards", s.description());
+    }
+    for pair in series.windows(2) {
+      assert!(
+        pair[0].last < pair[1].first,
+        "{} and {} overlap",
+        pair[0].description(),
+        pair[1].description()
+      );
+    }
+  }
+}
@@ -3,6 +3,8 @@ use serde_with::*;
 
 use reqwest::*;
 use std::sync::OnceLock;
+
+use super::traintracker::LRouteCode;
 static STATIONS_URL: &str = "https://data.cityofchicago.org/resource/8pix-ypme.json";
 
 #[serde_as]
@@ -71,4 +73,54 @@ impl CtaStations {
       .find(|p| p.map_id == id || p.stop_id == id)
       .map(|s| s.station_descriptive_name.clone())
   }
+
+  /// Map IDs of every station, each listed once.
+  pub fn map_ids(&self) -> Vec<i32> {
+    let mut ids: Vec<i32> = self.stops.iter().map(|s| s.map_id).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+  }
+
+  /// Latitude and longitude of a station or platform, by map ID or stop ID.
+  pub fn location(&self, id: i32) -> Option<(f32, f32)> {
+    self
+      .stops
+      .iter()
+      .find(|p| p.map_id == id || p.stop_id == id)
+      .map(|s| (s.location.latitude, s.location.longitude))
+  }
+
+  /// Whether the station with this map ID is served by `route`, or `None` if the station is unknown.
+  pub fn serves(&self, map_id: i32, route: LRouteCode) -> Option<bool> {
+    self.served_by(map_id, route, true)
+  }
+
+  /// Like [`CtaStations::serves`], but leaves out stations only served by the Purple Line Express.
+  pub fn serves_all_day(&self, map_id: i32, route: LRouteCode) -> Option<bool> {
+    self.served_by(map_id, route, false)
+  }
+
+  /// Every line stopping at the station with this map ID, including the Purple Line Express.
+  pub fn lines_at(&self, map_id: i32) -> Vec<LRouteCode> {
+    LRouteCode::ALL
+      .into_iter()
+      .filter(|route| self.serves(map_id, *route) == Some(true))
+      .collect()
+  }
+
+  fn served_by(&self, map_id: i32, route: LRouteCode, express: bool) -> Option<bool> {
+    let mut stops = self.stops.iter().filter(|s| s.map_id == map_id).peekable();
+    stops.peek()?;
+    Some(stops.any(|s| match route {
+      LRouteCode::Red => s.red,
+      LRouteCode::Blue => s.blue,
+      LRouteCode::G => s.g,
+      LRouteCode::Brn => s.brn,
+      LRouteCode::P => s.p || (express && s.pexp),
+      LRouteCode::Y => s.y,
+      LRouteCode::Pink => s.pnk,
+      LRouteCode::Org => s.o,
+    }))
+  }
 }
@@ -2,8 +2,8 @@ use crate::util::bool_from_string;
 use chrono::NaiveDateTime;
 use reqwest::get;
 use serde::{Deserialize, Serialize};
-use serde_with::{serde, serde_as, DisplayFromStr};
-use std::{result::Result, str::FromStr};
+use serde_with::{serde, serde_as, DisplayFromStr, OneOrMany};
+use std::{collections::HashMap, fmt, result::Result, str::FromStr};
 use thiserror::Error;
 
 #[derive(Deserialize, Debug)]
@@ -30,7 +30,9 @@ struct PositionTT {
 pub struct TTRoute {
   #[serde(rename = "@name")]
   pub name: LRouteCode,
-  #[serde(rename = "train")]
+  /// The API omits this key for routes with no trains and sends a bare object when there is only one.
+  #[serde_as(as = "OneOrMany<_>")]
+  #[serde(rename = "train", default)]
   pub trains: Vec<TTPosition>,
 }
 
@@ -97,6 +99,16 @@ struct ArrivalsTT {
 #[serde_as]
 #[derive(Deserialize, Debug)]
 pub struct TTArrival {
+  #[serde_as(as = "DisplayFromStr")]
+  #[serde(rename = "staId")]
+  pub station_id: i32,
+  #[serde_as(as = "DisplayFromStr")]
+  #[serde(rename = "stpId")]
+  pub stop_id: i32,
+  #[serde(rename = "staNm")]
+  pub station_name: String,
+  #[serde(rename = "stpDe")]
+  pub stop_description: String,
   #[serde_as(as = "DisplayFromStr")]
   #[serde(rename = "rn")]
   pub run_number: i32,
@@ -135,15 +147,24 @@ pub struct TTArrival {
   pub heading: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+/// Route codes as used by the arrivals API. The positions API sends them in lowercase.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum LRouteCode {
+  #[serde(alias = "red")]
   Red,
+  #[serde(alias = "p")]
   P,
+  #[serde(alias = "y")]
   Y,
+  #[serde(alias = "blue")]
   Blue,
+  #[serde(alias = "pink")]
   Pink,
+  #[serde(alias = "g")]
   G,
+  #[serde(alias = "org")]
   Org,
+  #[serde(alias = "brn")]
   Brn,
 }
 
@@ -167,6 +188,80 @@ pub enum LRouteName {
   Brn,
 }
 
+impl LRouteCode {
+  pub const ALL: [LRouteCode; 8] = [
+    LRouteCode::Red,
+    LRouteCode::Blue,
+    LRouteCode::Brn,
+    LRouteCode::G,
+    LRouteCode::Org,
+    LRouteCode::P,
+    LRouteCode::Pink,
+    LRouteCode::Y,
+  ];
+}
+/// Formats the route the way the `TrainTracker` API expects it in `rt=` parameters.
+impl fmt::Display for LRouteCode {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let code = match self {
+      LRouteCode::Red => "Red",
+      LRouteCode::P => "P",
+      LRouteCode::Y => "Y",
+      LRouteCode::Blue => "Blue",
+      LRouteCode::Pink => "Pink",
+      LRouteCode::G => "G",
+      LRouteCode::Org => "Org",
+      LRouteCode::Brn => "Brn",
+    };
+    write!(f, "{code}")
+  }
+}
+impl FromStr for LRouteCode {
+  type Err = TrainTrackerError;
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.to_lowercase().as_str() {
+      "red" => Ok(LRouteCode::Red),
+      "p" | "purple" => Ok(LRouteCode::P),
+      "y" | "yellow" => Ok(LRouteCode::Y),
+      "blue" => Ok(LRouteCode::Blue),
+      "pink" => Ok(LRouteCode::Pink),
+      "g" | "green" => Ok(LRouteCode::G),
+      "org" | "orange" => Ok(LRouteCode::Org),
+      "brn" | "brown" => Ok(LRouteCode::Brn),
+      _ => Err(TrainTrackerError::DataError),
+    }
+  }
+}
+
+impl LRouteName {
+  /// The official CTA line color as a hex string.
+  pub fn color(&self) -> &'static str {
+    match self {
+      LRouteName::Red => "#c60c30",
+      LRouteName::P => "#522398",
+      LRouteName::Y => "#f9e300",
+      LRouteName::Blue => "#00a1de",
+      LRouteName::Pink => "#e27ea6",
+      LRouteName::G => "#009b3a",
+      LRouteName::Org => "#f9461c",
+      LRouteName::Brn => "#62361b",
+    }
+  }
+  /// [`LRouteName::color`] as a number, for Discord embeds.
+  pub fn color_value(&self) -> u32 {
+    u32::from_str_radix(&self.color()[1..], 16).unwrap_or_default()
+  }
+}
+impl fmt::Display for LRouteName {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{}",
+      serde_variant::to_variant_name(self).unwrap_or_default()
+    )
+  }
+}
+
 impl From<LRouteCode> for LRouteName {
   fn from(value: LRouteCode) -> Self {
     match value {
@@ -207,7 +302,9 @@ struct FollowTrainTT {
   error_code: i32,
   #[serde(rename = "errNm")]
   error_name: Option<String>,
-  position: Position,
+  position: Option<Position>,
+  #[serde_as(as = "OneOrMany<_>")]
+  #[serde(default)]
   eta: Vec<TTFollowEta>,
 }
 
@@ -368,26 +465,93 @@ impl TrainTracker {
     )
   }
 
-  pub async fn positions(&self, rt: Vec<LRouteCode>) -> Result<Vec<TTRoute>, TrainTrackerError> {
-    let routes: String = rt
+  /// Positions request for several routes at once. The API wants them as one comma separated
+  /// `rt` parameter.
+  fn positions_url(&self, rt: &[LRouteCode]) -> String {
+    let routes = rt
       .iter()
-      .filter_map(|r| serde_json::ser::to_string(r).ok())
-      .fold(String::new(), |prev, r| format!("{prev}{r},"));
-    let resp_text = get(format!(
+      .map(ToString::to_string)
+      .collect::<Vec<_>>()
+      .join(",");
+    format!(
       "{}ttpositions.aspx?rt={routes}&key={}&outputType=JSON",
       Self::BASE_URL,
       self.token
-    ))
-    .await?
-    .text()
-    .await?;
-    // let topLevel: TopLevelResponse<FollowTrainTT> = ;
-    Ok(
-      serde_json::from_str::<TopLevelResponse<PositionTT>>(&resp_text)?
-        .ctatt
-        .route,
     )
   }
+
+  async fn fetch_positions(&self, rt: &[LRouteCode]) -> Result<PositionTT, TrainTrackerError> {
+    let resp_text = get(self.positions_url(rt)).await?.text().await?;
+    Ok(serde_json::from_str::<TopLevelResponse<PositionTT>>(&resp_text)?.ctatt)
+  }
+
+  pub async fn positions(&self, rt: &[LRouteCode]) -> Result<Vec<TTRoute>, TrainTrackerError> {
+    Ok(self.fetch_positions(rt).await?.route)
+  }
+
+  /// Gets the positions of every train on the given routes as an indexed [`FleetSnapshot`].
+  pub async fn fleet(&self, rt: &[LRouteCode]) -> Result<FleetSnapshot, TrainTrackerError> {
+    let positions = self.fetch_positions(rt).await?;
+    Ok(FleetSnapshot::new(positions.timestamp, positions.route))
+  }
+}
+
+/// Every train reported by one positions request, indexed by run number and by next station.
+#[derive(Debug, Clone)]
+pub struct FleetSnapshot {
+  pub timestamp: NaiveDateTime,
+  /// All trains in the snapshot. `route` is always set, even though the API only sends it per route.
+  pub trains: Vec<TTPosition>,
+  by_run: HashMap<i32, usize>,
+  by_next_station: HashMap<i32, Vec<usize>>,
+}
+impl FleetSnapshot {
+  pub fn new(timestamp: NaiveDateTime, routes: Vec<TTRoute>) -> Self {
+    let trains: Vec<TTPosition> = routes
+      .into_iter()
+      .flat_map(|route| {
+        route.trains.into_iter().map(move |mut train| {
+          train.route = Some(route.name);
+          train
+        })
+      })
+      .collect();
+    let mut by_run = HashMap::new();
+    let mut by_next_station: HashMap<i32, Vec<usize>> = HashMap::new();
+    for (i, train) in trains.iter().enumerate() {
+      by_run.insert(train.run_number, i);
+      by_next_station
+        .entry(train.next_station_id)
+        .or_default()
+        .push(i);
+    }
+    Self {
+      timestamp,
+      trains,
+      by_run,
+      by_next_station,
+    }
+  }
+  pub fn len(&self) -> usize {
+    self.trains.len()
+  }
+  pub fn is_empty(&self) -> bool {
+    self.trains.is_empty()
+  }
+  pub fn train(&self, run_number: i32) -> Option<&TTPosition> {
+    self.by_run.get(&run_number).map(|i| &self.trains[*i])
+  }
+  /// Trains whose next stop is the given station (by map ID).
+  pub fn next_at(&self, station_id: i32) -> Vec<&TTPosition> {
+    self
+      .by_next_station
+      .get(&station_id)
+      .map(|idx| idx.iter().map(|i| &self.trains[*i]).collect())
+      .unwrap_or_default()
+  }
+  pub fn on_route(&self, route: LRouteCode) -> impl Iterator<Item = &TTPosition> {
+    self.trains.iter().filter(move |t| t.route == Some(route))
+  }
 }
 
 #[cfg(test)]
@@ -397,9 +561,8 @@ mod tests {
 
   #[test]
   fn test_parse_arrival() {
-    assert_eq!(
-      serde_json::from_str::<TopLevelResponse<ArrivalsTT>>(
-        &"{ 
+    let arrivals = serde_json::from_str::<TopLevelResponse<ArrivalsTT>>(
+      "{ 
     \"ctatt\":{ 
         \"tmst\":\"2015-04-30T20:23:53\",
         \"errCd\":\"0\",
@@ -428,18 +591,58 @@ mod tests {
            }
         ]
     }
-}"
-      )?
-      .ctatt
-      .arrivals,
-      vec![Arrival {}]
-    );
+}",
+    )
+    .unwrap()
+    .ctatt
+    .arrivals;
+    assert_eq!(arrivals.len(), 1);
+    assert_eq!(arrivals[0].run_number, 726);
+    assert_eq!(arrivals[0].station_id, 40960);
+    assert_eq!(arrivals[0].route, LRouteCode::Org);
+    assert_eq!(arrivals[0].destination_name, "Loop");
+  }
+
+  #[test]
+  fn test_parse_positions() {
+    let positions = serde_json::from_str::<TopLevelResponse<PositionTT>>(
+      r#"{"ctatt":{"tmst":"2015-04-30T20:23:53","errCd":"0","errNm":null,"route":[
+        {"@name":"red","train":[
+          {"rn":"804","destSt":"30173","destNm":"Howard","trDr":"1","nextStaId":"40900","nextStpId":"30173","nextStaNm":"Howard","prdt":"2015-04-30T20:23:32","arrT":"2015-04-30T20:24:32","isApp":"1","isDly":"0","flags":null,"lat":"42.01063","lon":"-87.66324","heading":"358"},
+          {"rn":"812","destSt":"30089","destNm":"95th/Dan Ryan","trDr":"5","nextStaId":"41450","nextStpId":"30282","nextStaNm":"Chicago","prdt":"2015-04-30T20:23:40","arrT":"2015-04-30T20:25:40","isApp":"0","isDly":"1","flags":null,"lat":"41.90254","lon":"-87.62758","heading":"178"}
+        ]},
+        {"@name":"y","train":
+          {"rn":"590","destSt":"30176","destNm":"Skokie","trDr":"1","nextStaId":"40140","nextStpId":"30297","nextStaNm":"Dempster-Skokie","prdt":"2015-04-30T20:23:12","arrT":"2015-04-30T20:27:12","isApp":"0","isDly":"0","flags":null,"lat":"42.02624","lon":"-87.74701","heading":"270"}
+        },
+        {"@name":"pink"}
+      ]}}"#,
+    )
+    .unwrap()
+    .ctatt;
+    let fleet = FleetSnapshot::new(positions.timestamp, positions.route);
+    assert_eq!(fleet.len(), 3);
+    assert_eq!(fleet.on_route(LRouteCode::Red).count(), 2);
+    assert_eq!(fleet.on_route(LRouteCode::Pink).count(), 0);
+    let run = fleet.train(812).expect("run 812 should be indexed");
+    assert_eq!(run.route, Some(LRouteCode::Red));
+    assert!(run.is_delayed);
+    assert_eq!(fleet.next_at(40140)[0].run_number, 590);
+    assert!(fleet.train(100).is_none());
   }
 
   #[test]
-  fn test_bad_add() {
-    // This assert would fire and test will fail.
-    // Please note, that private functions can be tested too!
-    assert_eq!(bad_add(1, 2), 3);
+  fn test_route_code_param() {
+    let url =
+      TrainTracker::new("key").positions_url(&[LRouteCode::Red, LRouteCode::Brn, LRouteCode::G]);
+    assert_eq!(
+      url,
+      format!(
+        "{}ttpositions.aspx?rt=Red,Brn,G&key=key&outputType=JSON",
+        TrainTracker::BASE_URL
+      )
+    );
+    for code in LRouteCode::ALL {
+      assert_eq!(code.to_string().parse::<LRouteCode>().unwrap(), code);
+    }
   }
 }
@@ -1,9 +1,14 @@
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::PgRow;
 use sqlx::types::Json;
-use sqlx::{Executor, Postgres};
+use sqlx::{Executor, Pool, Postgres};
 
 use crate::cta::alerts::{Alert, Service};
+use crate::cta::analysis::accuracy::{ObservedArrival, PredictionSample};
+use crate::cta::analysis::ghosts::ScheduledTrip;
+use crate::cta::bustracker::{BusTracker, Vehicle};
+use crate::cta::traintracker::TTPosition;
+use crate::util::chicago_timestamp;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct DBAlert {
@@ -31,6 +36,16 @@ pub struct DBGuild {
   pub planned_alerts: Option<bool>,
   pub route_ids: Option<Vec<String>>,
   pub ephemeral_arrivals: Option<bool>,
+  pub anomaly_alerts: Option<bool>,
+  pub anomaly_channel: Option<i64>,
+  pub gap_alerts: Option<bool>,
+  pub gap_channel: Option<i64>,
+  pub bulletin_alerts: Option<bool>,
+  pub bulletin_channel: Option<i64>,
+  pub bus_routes: Option<Vec<String>>,
+  pub bunching_alerts: Option<bool>,
+  pub bunching_channel: Option<i64>,
+  pub bunching_routes: Option<Vec<String>>,
 }
 #[derive(sqlx::FromRow, Debug)]
 pub struct DBKeyValue {
@@ -54,6 +69,149 @@ pub async fn get_subscribed_guilds(
     .await
 }
 
+pub async fn get_anomaly_guilds(
+  db: impl Executor<'_, Database = Postgres>,
+) -> Result<Vec<DBGuild>, sqlx::Error> {
+  sqlx::query_as!(
+    DBGuild,
+    "SELECT * FROM guilds WHERE anomaly_alerts = true AND anomaly_channel IS NOT NULL;"
+  )
+  .fetch_all(db)
+  .await
+}
+
+pub async fn set_guild_anomalies(
+  db: impl Executor<'_, Database = Postgres>,
+  guild_id: i64,
+  enabled: bool,
+  channel: Option<i64>,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "INSERT INTO guilds(guild_id, anomaly_alerts, anomaly_channel)
+      VALUES ($1, $2, $3)
+      ON CONFLICT (guild_id) DO UPDATE SET
+        anomaly_alerts = EXCLUDED.anomaly_alerts,
+        anomaly_channel = COALESCE(EXCLUDED.anomaly_channel, guilds.anomaly_channel);",
+    guild_id,
+    enabled,
+    channel
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+pub async fn get_gap_guilds(
+  db: impl Executor<'_, Database = Postgres>,
+) -> Result<Vec<DBGuild>, sqlx::Error> {
+  sqlx::query_as!(
+    DBGuild,
+    "SELECT * FROM guilds WHERE gap_alerts = true AND gap_channel IS NOT NULL;"
+  )
+  .fetch_all(db)
+  .await
+}
+
+pub async fn set_guild_gaps(
+  db: impl Executor<'_, Database = Postgres>,
+  guild_id: i64,
+  enabled: bool,
+  channel: Option<i64>,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "INSERT INTO guilds(guild_id, gap_alerts, gap_channel)
+      VALUES ($1, $2, $3)
+      ON CONFLICT (guild_id) DO UPDATE SET
+        gap_alerts = EXCLUDED.gap_alerts,
+        gap_channel = COALESCE(EXCLUDED.gap_channel, guilds.gap_channel);",
+    guild_id,
+    enabled,
+    channel
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+pub async fn get_bulletin_guilds(
+  db: impl Executor<'_, Database = Postgres>,
+) -> Result<Vec<DBGuild>, sqlx::Error> {
+  sqlx::query_as!(
+    DBGuild,
+    "SELECT * FROM guilds
+      WHERE bulletin_alerts = true AND bulletin_channel IS NOT NULL AND bus_routes IS NOT NULL;"
+  )
+  .fetch_all(db)
+  .await
+}
+
+/// Saves a guild's bus bulletin settings. Leaving `channel` or `routes` out keeps what was set
+/// before.
+pub async fn set_guild_bulletins(
+  db: impl Executor<'_, Database = Postgres>,
+  guild_id: i64,
+  enabled: bool,
+  channel: Option<i64>,
+  routes: Option<&[String]>,
+) -> Result<DBGuild, sqlx::Error> {
+  sqlx::query_as!(
+    DBGuild,
+    "INSERT INTO guilds(guild_id, bulletin_alerts, bulletin_channel, bus_routes)
+      VALUES ($1, $2, $3, $4)
+      ON CONFLICT (guild_id) DO UPDATE SET
+        bulletin_alerts = EXCLUDED.bulletin_alerts,
+        bulletin_channel = COALESCE(EXCLUDED.bulletin_channel, guilds.bulletin_channel),
+        bus_routes = COALESCE(EXCLUDED.bus_routes, guilds.bus_routes)
+      RETURNING *;",
+    guild_id,
+    enabled,
+    channel,
+    routes
+  )
+  .fetch_one(db)
+  .await
+}
+
+pub async fn get_bunching_guilds(
+  db: impl Executor<'_, Database = Postgres>,
+) -> Result<Vec<DBGuild>, sqlx::Error> {
+  sqlx::query_as!(
+    DBGuild,
+    "SELECT * FROM guilds
+      WHERE bunching_alerts = true AND bunching_channel IS NOT NULL
+        AND bunching_routes IS NOT NULL;"
+  )
+  .fetch_all(db)
+  .await
+}
+
+/// Saves a guild's bus bunching settings. Leaving `channel` or `routes` out keeps what was set
+/// before.
+pub async fn set_guild_bunching(
+  db: impl Executor<'_, Database = Postgres>,
+  guild_id: i64,
+  enabled: bool,
+  channel: Option<i64>,
+  routes: Option<&[String]>,
+) -> Result<DBGuild, sqlx::Error> {
+  sqlx::query_as!(
+    DBGuild,
+    "INSERT INTO guilds(guild_id, bunching_alerts, bunching_channel, bunching_routes)
+      VALUES ($1, $2, $3, $4)
+      ON CONFLICT (guild_id) DO UPDATE SET
+        bunching_alerts = EXCLUDED.bunching_alerts,
+        bunching_channel = COALESCE(EXCLUDED.bunching_channel, guilds.bunching_channel),
+        bunching_routes = COALESCE(EXCLUDED.bunching_routes, guilds.bunching_routes)
+      RETURNING *;",
+    guild_id,
+    enabled,
+    channel,
+    routes
+  )
+  .fetch_one(db)
+  .await
+}
+
 pub async fn get_alerts_with_ids(
   db: impl Executor<'_, Database = Postgres>,
   ids: &[i32],
@@ -99,6 +257,155 @@ pub async fn add_alert(
   Ok(())
 }
 
+#[derive(sqlx::FromRow, Debug)]
+pub struct DBBusBulletin {
+  pub name: String,
+  pub subject: String,
+  pub routes: Vec<String>,
+  pub published_to: i32,
+  pub first_seen: i64,
+  pub last_seen: i64,
+}
+
+pub async fn get_bus_bulletins_with_names(
+  db: impl Executor<'_, Database = Postgres>,
+  names: &[String],
+) -> Result<Vec<DBBusBulletin>, sqlx::Error> {
+  sqlx::query_as!(
+    DBBusBulletin,
+    "SELECT * FROM bus_bulletins WHERE name = ANY($1);",
+    names
+  )
+  .fetch_all(db)
+  .await
+}
+
+pub async fn add_bus_bulletin(
+  db: impl Executor<'_, Database = Postgres>,
+  bulletin: &DBBusBulletin,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "INSERT INTO bus_bulletins(name, subject, routes, published_to, first_seen, last_seen)
+      VALUES ($1, $2, $3, $4, $5, $6)
+      ON CONFLICT (name) DO NOTHING;",
+    bulletin.name,
+    bulletin.subject,
+    &bulletin.routes,
+    bulletin.published_to,
+    bulletin.first_seen,
+    bulletin.last_seen
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+/// Marks bulletins as still posted by Bus Tracker.
+pub async fn touch_bus_bulletins(
+  db: impl Executor<'_, Database = Postgres>,
+  names: &[String],
+  now: i64,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "UPDATE bus_bulletins SET last_seen = $2 WHERE name = ANY($1);",
+    names,
+    now
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+/// Forgets bulletins Bus Tracker stopped listing before `before`.
+pub async fn prune_bus_bulletins(
+  db: impl Executor<'_, Database = Postgres>,
+  before: i64,
+) -> Result<u64, sqlx::Error> {
+  Ok(
+    sqlx::query!("DELETE FROM bus_bulletins WHERE last_seen < $1;", before)
+      .execute(db)
+      .await?
+      .rows_affected(),
+  )
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct DBBusDetour {
+  pub detour_id: String,
+  pub version: i32,
+  pub routes: Vec<String>,
+  pub published_to: i32,
+  pub first_seen: i64,
+  pub last_seen: i64,
+}
+
+pub async fn get_bus_detours_with_ids(
+  db: impl Executor<'_, Database = Postgres>,
+  ids: &[String],
+) -> Result<Vec<DBBusDetour>, sqlx::Error> {
+  sqlx::query_as!(
+    DBBusDetour,
+    "SELECT * FROM bus_detours WHERE detour_id = ANY($1);",
+    ids
+  )
+  .fetch_all(db)
+  .await
+}
+
+/// Saves a posted detour, replacing the record of any earlier version.
+pub async fn save_bus_detour(
+  db: impl Executor<'_, Database = Postgres>,
+  detour: &DBBusDetour,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "INSERT INTO bus_detours(detour_id, version, routes, published_to, first_seen, last_seen)
+      VALUES ($1, $2, $3, $4, $5, $6)
+      ON CONFLICT (detour_id) DO UPDATE SET
+        version = EXCLUDED.version,
+        routes = EXCLUDED.routes,
+        published_to = EXCLUDED.published_to,
+        last_seen = EXCLUDED.last_seen;",
+    detour.detour_id,
+    detour.version,
+    &detour.routes,
+    detour.published_to,
+    detour.first_seen,
+    detour.last_seen
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+/// Marks detours as still in effect.
+pub async fn touch_bus_detours(
+  db: impl Executor<'_, Database = Postgres>,
+  ids: &[String],
+  now: i64,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "UPDATE bus_detours SET last_seen = $2 WHERE detour_id = ANY($1);",
+    ids,
+    now
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+/// Forgets detours that stopped being in effect before `before`.
+pub async fn prune_bus_detours(
+  db: impl Executor<'_, Database = Postgres>,
+  before: i64,
+) -> Result<u64, sqlx::Error> {
+  Ok(
+    sqlx::query!("DELETE FROM bus_detours WHERE last_seen < $1;", before)
+      .execute(db)
+      .await?
+      .rows_affected(),
+  )
+}
+
 pub async fn get_value(db: impl Executor<'_, Database = Postgres>, key: &str) {
   let res = sqlx::query_as!(DBKeyValue, "SELECT * FROM kv_store WHERE key = $1;", key)
     .fetch_one(db)
@@ -106,3 +413,1101 @@ pub async fn get_value(db: impl Executor<'_, Database = Postgres>, key: &str) {
 
   dbg!(res.unwrap());
 }
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct DBFollowedTrain {
+  pub message_id: i64,
+  pub channel_id: i64,
+  pub run_number: i32,
+  /// Station names in the order the train was due to reach them when the follow started.
+  pub stations: Vec<String>,
+  pub started_at: i64,
+  pub last_seen_index: i32,
+  pub missed_polls: i32,
+}
+
+pub async fn get_followed_trains(
+  db: impl Executor<'_, Database = Postgres>,
+) -> Result<Vec<DBFollowedTrain>, sqlx::Error> {
+  sqlx::query_as!(DBFollowedTrain, "SELECT * FROM followed_trains;")
+    .fetch_all(db)
+    .await
+}
+
+pub async fn add_followed_train(
+  db: impl Executor<'_, Database = Postgres>,
+  follow: &DBFollowedTrain,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "INSERT INTO
+      followed_trains(message_id, channel_id, run_number, stations, started_at, last_seen_index, missed_polls)
+      VALUES ($1, $2, $3, $4, $5, $6, $7)
+      ON CONFLICT (message_id) DO UPDATE SET
+        run_number = EXCLUDED.run_number,
+        stations = EXCLUDED.stations,
+        started_at = EXCLUDED.started_at,
+        last_seen_index = EXCLUDED.last_seen_index,
+        missed_polls = EXCLUDED.missed_polls;",
+    follow.message_id,
+    follow.channel_id,
+    follow.run_number,
+    &follow.stations,
+    follow.started_at,
+    follow.last_seen_index,
+    follow.missed_polls
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+pub async fn update_followed_train(
+  db: impl Executor<'_, Database = Postgres>,
+  message_id: i64,
+  last_seen_index: i32,
+  missed_polls: i32,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "UPDATE followed_trains SET last_seen_index = $2, missed_polls = $3 WHERE message_id = $1;",
+    message_id,
+    last_seen_index,
+    missed_polls
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+pub async fn remove_followed_train(
+  db: impl Executor<'_, Database = Postgres>,
+  message_id: i64,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "DELETE FROM followed_trains WHERE message_id = $1;",
+    message_id
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct DBFollowedBus {
+  pub message_id: i64,
+  pub channel_id: i64,
+  pub vid: String,
+  pub started_at: i64,
+  pub missed_polls: i32,
+}
+
+pub async fn get_followed_buses(
+  db: impl Executor<'_, Database = Postgres>,
+) -> Result<Vec<DBFollowedBus>, sqlx::Error> {
+  sqlx::query_as!(DBFollowedBus, "SELECT * FROM followed_buses;")
+    .fetch_all(db)
+    .await
+}
+
+pub async fn add_followed_bus(
+  db: impl Executor<'_, Database = Postgres>,
+  follow: &DBFollowedBus,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "INSERT INTO followed_buses(message_id, channel_id, vid, started_at, missed_polls)
+      VALUES ($1, $2, $3, $4, $5)
+      ON CONFLICT (message_id) DO UPDATE SET
+        vid = EXCLUDED.vid,
+        started_at = EXCLUDED.started_at,
+        missed_polls = EXCLUDED.missed_polls;",
+    follow.message_id,
+    follow.channel_id,
+    follow.vid,
+    follow.started_at,
+    follow.missed_polls
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+pub async fn update_followed_bus(
+  db: impl Executor<'_, Database = Postgres>,
+  message_id: i64,
+  missed_polls: i32,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "UPDATE followed_buses SET missed_polls = $2 WHERE message_id = $1;",
+    message_id,
+    missed_polls
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+pub async fn remove_followed_bus(
+  db: impl Executor<'_, Database = Postgres>,
+  message_id: i64,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "DELETE FROM followed_buses WHERE message_id = $1;",
+    message_id
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+/// Records that an anomaly was seen, returning `true` if it had not already been reported.
+pub async fn record_anomaly(
+  db: impl Executor<'_, Database = Postgres>,
+  route: &str,
+  run_number: i32,
+  anomaly_type: &str,
+  seen_at: i64,
+) -> Result<bool, sqlx::Error> {
+  let row = sqlx::query!(
+    "INSERT INTO reported_anomalies(route, run_number, anomaly_type, first_seen, last_seen)
+      VALUES ($1, $2, $3, $4, $4)
+      ON CONFLICT (route, run_number, anomaly_type) DO UPDATE SET last_seen = EXCLUDED.last_seen
+      RETURNING (xmax = 0) AS \"inserted!\";",
+    route,
+    run_number,
+    anomaly_type,
+    seen_at
+  )
+  .fetch_one(db)
+  .await?;
+  Ok(row.inserted)
+}
+
+pub async fn set_anomaly_published(
+  db: impl Executor<'_, Database = Postgres>,
+  route: &str,
+  run_number: i32,
+  anomaly_type: &str,
+  published_to: i32,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "UPDATE reported_anomalies SET published_to = $4
+      WHERE route = $1 AND run_number = $2 AND anomaly_type = $3;",
+    route,
+    run_number,
+    anomaly_type,
+    published_to
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+/// Forgets anomalies that have not been seen since `before`, so they are reported again if they return.
+pub async fn prune_anomalies(
+  db: impl Executor<'_, Database = Postgres>,
+  before: i64,
+) -> Result<u64, sqlx::Error> {
+  Ok(
+    sqlx::query!(
+      "DELETE FROM reported_anomalies WHERE last_seen < $1;",
+      before
+    )
+    .execute(db)
+    .await?
+    .rows_affected(),
+  )
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DBWatch {
+  pub watch_id: i32,
+  /// Either `train` (run number) or `bus` (vehicle ID).
+  pub kind: String,
+  pub identifier: String,
+  pub label: Option<String>,
+  /// Either `channel` or `user`, saying what `target_id` refers to.
+  pub target_type: String,
+  pub target_id: i64,
+  pub in_service: bool,
+  pub last_seen: Option<i64>,
+}
+
+pub async fn get_watches(
+  db: impl Executor<'_, Database = Postgres>,
+) -> Result<Vec<DBWatch>, sqlx::Error> {
+  sqlx::query_as!(DBWatch, "SELECT * FROM watchlist;")
+    .fetch_all(db)
+    .await
+}
+
+pub async fn get_watches_for_target(
+  db: impl Executor<'_, Database = Postgres>,
+  target_type: &str,
+  target_id: i64,
+) -> Result<Vec<DBWatch>, sqlx::Error> {
+  sqlx::query_as!(
+    DBWatch,
+    "SELECT * FROM watchlist WHERE target_type = $1 AND target_id = $2 ORDER BY kind, identifier;",
+    target_type,
+    target_id
+  )
+  .fetch_all(db)
+  .await
+}
+
+pub async fn add_watch(
+  db: impl Executor<'_, Database = Postgres>,
+  kind: &str,
+  identifier: &str,
+  label: Option<&str>,
+  target_type: &str,
+  target_id: i64,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "INSERT INTO watchlist(kind, identifier, label, target_type, target_id)
+      VALUES ($1, $2, $3, $4, $5)
+      ON CONFLICT (kind, identifier, target_type, target_id) DO UPDATE SET label = EXCLUDED.label;",
+    kind,
+    identifier,
+    label,
+    target_type,
+    target_id
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+pub async fn remove_watch(
+  db: impl Executor<'_, Database = Postgres>,
+  kind: &str,
+  identifier: &str,
+  target_type: &str,
+  target_id: i64,
+) -> Result<u64, sqlx::Error> {
+  Ok(
+    sqlx::query!(
+      "DELETE FROM watchlist
+        WHERE kind = $1 AND identifier = $2 AND target_type = $3 AND target_id = $4;",
+      kind,
+      identifier,
+      target_type,
+      target_id
+    )
+    .execute(db)
+    .await?
+    .rows_affected(),
+  )
+}
+
+pub async fn set_watch_status(
+  db: impl Executor<'_, Database = Postgres>,
+  watch_id: i32,
+  in_service: bool,
+  last_seen: Option<i64>,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "UPDATE watchlist SET in_service = $2, last_seen = $3 WHERE watch_id = $1;",
+    watch_id,
+    in_service,
+    last_seen
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DBServiceGap {
+  pub gap_id: i32,
+  pub route: String,
+  pub direction: i16,
+  pub toward: String,
+  pub station_names: Vec<String>,
+  /// When a train was last seen at any of the stations.
+  pub last_seen: i64,
+  /// The usual time between trains at those stations, in seconds.
+  pub usual_headway: i64,
+  pub detected_at: i64,
+  pub resolved_at: Option<i64>,
+  pub alert_id: Option<i32>,
+  pub alert_headline: Option<String>,
+  pub alert_url: Option<String>,
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DBServiceGapMessage {
+  pub channel_id: i64,
+  pub message_id: i64,
+}
+
+pub async fn get_open_service_gaps(
+  db: impl Executor<'_, Database = Postgres>,
+) -> Result<Vec<DBServiceGap>, sqlx::Error> {
+  sqlx::query_as!(
+    DBServiceGap,
+    "SELECT * FROM service_gaps WHERE resolved_at IS NULL;"
+  )
+  .fetch_all(db)
+  .await
+}
+
+/// Gaps detected since `since` that haven't been matched to an official alert yet.
+pub async fn get_unlinked_service_gaps(
+  db: impl Executor<'_, Database = Postgres>,
+  since: i64,
+) -> Result<Vec<DBServiceGap>, sqlx::Error> {
+  sqlx::query_as!(
+    DBServiceGap,
+    "SELECT * FROM service_gaps WHERE alert_id IS NULL AND detected_at >= $1;",
+    since
+  )
+  .fetch_all(db)
+  .await
+}
+
+/// Saves a newly detected gap, returning `None` if that direction of the line already has an open gap.
+#[allow(clippy::too_many_arguments)]
+pub async fn add_service_gap(
+  db: impl Executor<'_, Database = Postgres>,
+  route: &str,
+  direction: i16,
+  toward: &str,
+  station_names: &[String],
+  last_seen: i64,
+  usual_headway: i64,
+  detected_at: i64,
+) -> Result<Option<DBServiceGap>, sqlx::Error> {
+  sqlx::query_as!(
+    DBServiceGap,
+    "INSERT INTO service_gaps(route, direction, toward, station_names, last_seen, usual_headway, detected_at)
+      VALUES ($1, $2, $3, $4, $5, $6, $7)
+      ON CONFLICT (route, direction) WHERE resolved_at IS NULL DO NOTHING
+      RETURNING *;",
+    route,
+    direction,
+    toward,
+    station_names,
+    last_seen,
+    usual_headway,
+    detected_at
+  )
+  .fetch_optional(db)
+  .await
+}
+
+pub async fn resolve_service_gap(
+  db: impl Executor<'_, Database = Postgres>,
+  gap_id: i32,
+  resolved_at: i64,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "UPDATE service_gaps SET resolved_at = $2 WHERE gap_id = $1;",
+    gap_id,
+    resolved_at
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+pub async fn link_service_gap(
+  db: impl Executor<'_, Database = Postgres>,
+  gap_id: i32,
+  alert_id: i32,
+  alert_headline: &str,
+  alert_url: &str,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "UPDATE service_gaps SET alert_id = $2, alert_headline = $3, alert_url = $4 WHERE gap_id = $1;",
+    gap_id,
+    alert_id,
+    alert_headline,
+    alert_url
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+/// Deletes gaps resolved before `before`, along with their messages.
+pub async fn prune_service_gaps(
+  db: impl Executor<'_, Database = Postgres>,
+  before: i64,
+) -> Result<u64, sqlx::Error> {
+  Ok(
+    sqlx::query!("DELETE FROM service_gaps WHERE resolved_at < $1;", before)
+      .execute(db)
+      .await?
+      .rows_affected(),
+  )
+}
+
+pub async fn get_service_gap_messages(
+  db: impl Executor<'_, Database = Postgres>,
+  gap_id: i32,
+) -> Result<Vec<DBServiceGapMessage>, sqlx::Error> {
+  sqlx::query_as!(
+    DBServiceGapMessage,
+    "SELECT channel_id, message_id FROM service_gap_messages WHERE gap_id = $1;",
+    gap_id
+  )
+  .fetch_all(db)
+  .await
+}
+
+pub async fn add_service_gap_message(
+  db: impl Executor<'_, Database = Postgres>,
+  gap_id: i32,
+  channel_id: i64,
+  message_id: i64,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "INSERT INTO service_gap_messages(gap_id, channel_id, message_id) VALUES ($1, $2, $3)
+      ON CONFLICT (gap_id, channel_id) DO UPDATE SET message_id = EXCLUDED.message_id;",
+    gap_id,
+    channel_id,
+    message_id
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
+
+/// Partitioned tables written by the history recorder.
+pub const HISTORY_TABLES: [&str; 2] = ["train_position_history", "bus_vehicle_history"];
+const SECS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Creates the daily history partitions covering `from` through `until`, if they don't exist yet.
+pub async fn create_history_partitions(
+  db: &Pool<Postgres>,
+  from: i64,
+  until: i64,
+) -> Result<(), sqlx::Error> {
+  let mut day = from.div_euclid(SECS_PER_DAY);
+  while day <= until.div_euclid(SECS_PER_DAY) {
+    let suffix = chrono::DateTime::from_timestamp(day * SECS_PER_DAY, 0)
+      .unwrap_or_default()
+      .format("%Y%m%d");
+    for table in HISTORY_TABLES {
+      sqlx::query(&format!(
+        "CREATE TABLE IF NOT EXISTS {table}_p{suffix} PARTITION OF {table}
+          FOR VALUES FROM ({}) TO ({});",
+        day * SECS_PER_DAY,
+        (day + 1) * SECS_PER_DAY
+      ))
+      .execute(db)
+      .await?;
+    }
+    day += 1;
+  }
+  Ok(())
+}
+
+/// Drops history partitions that only hold samples from before `before`, returning their names.
+pub async fn drop_history_partitions(
+  db: &Pool<Postgres>,
+  before: i64,
+) -> Result<Vec<String>, sqlx::Error> {
+  let cutoff = chrono::DateTime::from_timestamp(before.div_euclid(SECS_PER_DAY) * SECS_PER_DAY, 0)
+    .unwrap_or_default()
+    .format("%Y%m%d")
+    .to_string();
+  let mut dropped = Vec::new();
+  for table in HISTORY_TABLES {
+    let partitions = sqlx::query!(
+      "SELECT child.relname::text AS \"name!\" FROM pg_inherits
+        JOIN pg_class parent ON pg_inherits.inhparent = parent.oid
+        JOIN pg_class child ON pg_inherits.inhrelid = child.oid
+        WHERE parent.relname = $1;",
+      table
+    )
+    .fetch_all(db)
+    .await?;
+    for partition in partitions {
+      // Partition names end in their UTC date, so they sort by age.
+      let Some(date) = partition.name.strip_prefix(&format!("{table}_p")) else {
+        continue;
+      };
+      if date < cutoff.as_str() {
+        sqlx::query(&format!("DROP TABLE IF EXISTS {};", partition.name))
+          .execute(db)
+          .await?;
+        dropped.push(partition.name);
+      }
+    }
+  }
+  Ok(dropped)
+}
+
+pub async fn record_train_positions(
+  db: impl Executor<'_, Database = Postgres>,
+  recorded_at: i64,
+  trains: &[TTPosition],
+) -> Result<u64, sqlx::Error> {
+  Ok(
+    sqlx::query!(
+      "INSERT INTO train_position_history(recorded_at, route, run_number, destination_station,
+        destination_name, direction, next_station_id, next_stop_id, next_station_name,
+        prediction_time, arrival_time, is_approaching, is_delayed, lat, lon, heading)
+        SELECT $1, * FROM UNNEST($2::text[], $3::int[], $4::int[], $5::text[], $6::smallint[],
+          $7::int[], $8::int[], $9::text[], $10::bigint[], $11::bigint[], $12::bool[], $13::bool[],
+          $14::real[], $15::real[], $16::int[]);",
+      recorded_at,
+      &trains
+        .iter()
+        .map(|t| t.route.map(|r| r.to_string()).unwrap_or_default())
+        .collect::<Vec<_>>(),
+      &trains.iter().map(|t| t.run_number).collect::<Vec<_>>(),
+      &trains
+        .iter()
+        .map(|t| t.destination_station)
+        .collect::<Vec<_>>(),
+      &trains
+        .iter()
+        .map(|t| t.destination_name.clone())
+        .collect::<Vec<_>>(),
+      &trains
+        .iter()
+        .map(|t| i16::from(t.train_direction))
+        .collect::<Vec<_>>(),
+      &trains.iter().map(|t| t.next_station_id).collect::<Vec<_>>(),
+      &trains.iter().map(|t| t.next_stop_id).collect::<Vec<_>>(),
+      &trains
+        .iter()
+        .map(|t| t.next_station_name.clone())
+        .collect::<Vec<_>>(),
+      &trains
+        .iter()
+        .map(|t| chicago_timestamp(t.prediction_time))
+        .collect::<Vec<_>>(),
+      &trains
+        .iter()
+        .map(|t| chicago_timestamp(t.arrival_time))
+        .collect::<Vec<_>>(),
+      &trains.iter().map(|t| t.is_approaching).collect::<Vec<_>>(),
+      &trains.iter().map(|t| t.is_delayed).collect::<Vec<_>>(),
+      &trains.iter().map(|t| t.lat).collect::<Vec<_>>(),
+      &trains.iter().map(|t| t.lon).collect::<Vec<_>>(),
+      &trains.iter().map(|t| t.heading).collect::<Vec<_>>(),
+    )
+    .execute(db)
+    .await?
+    .rows_affected(),
+  )
+}
+
+pub async fn record_bus_vehicles(
+  db: impl Executor<'_, Database = Postgres>,
+  recorded_at: i64,
+  vehicles: &[Vehicle],
+) -> Result<u64, sqlx::Error> {
+  Ok(
+    sqlx::query!(
+      "INSERT INTO bus_vehicle_history(recorded_at, vid, route, pattern_id, destination, timestamp,
+        lat, lon, heading, pattern_distance, is_delayed, tablockid)
+        SELECT $1, * FROM UNNEST($2::text[], $3::text[], $4::int[], $5::text[], $6::bigint[],
+          $7::real[], $8::real[], $9::int[], $10::int[], $11::bool[], $12::text[]);",
+      recorded_at,
+      &vehicles.iter().map(|v| v.vid.clone()).collect::<Vec<_>>(),
+      &vehicles.iter().map(|v| v.rt.clone()).collect::<Vec<_>>(),
+      &vehicles.iter().map(|v| v.pid).collect::<Vec<_>>(),
+      &vehicles.iter().map(|v| v.des.clone()).collect::<Vec<_>>(),
+      &vehicles
+        .iter()
+        .map(|v| {
+          BusTracker::parse_bustime_secs(&v.tmstmp).map_or(recorded_at, |t| t.timestamp())
+        })
+        .collect::<Vec<_>>(),
+      &vehicles.iter().map(|v| v.lat).collect::<Vec<_>>(),
+      &vehicles.iter().map(|v| v.lon).collect::<Vec<_>>(),
+      &vehicles.iter().map(|v| v.hdg).collect::<Vec<_>>(),
+      &vehicles.iter().map(|v| v.pdist).collect::<Vec<_>>() as &[Option<i32>],
+      &vehicles.iter().map(|v| v.dly).collect::<Vec<_>>() as &[Option<bool>],
+      &vehicles
+        .iter()
+        .map(|v| v.tablockid.clone())
+        .collect::<Vec<_>>(),
+    )
+    .execute(db)
+    .await?
+    .rows_affected(),
+  )
+}
+
+pub async fn record_predictions(
+  db: impl Executor<'_, Database = Postgres>,
+  samples: &[PredictionSample],
+) -> Result<u64, sqlx::Error> {
+  Ok(
+    sqlx::query!(
+      "INSERT INTO prediction_samples(route, run_number, station_id, predicted_at,
+        predicted_arrival, is_scheduled)
+        SELECT * FROM UNNEST($1::text[], $2::int[], $3::int[], $4::bigint[], $5::bigint[],
+          $6::bool[]);",
+      &samples
+        .iter()
+        .map(|s| s.route.to_string())
+        .collect::<Vec<_>>(),
+      &samples.iter().map(|s| s.run_number).collect::<Vec<_>>(),
+      &samples.iter().map(|s| s.station_id).collect::<Vec<_>>(),
+      &samples.iter().map(|s| s.predicted_at).collect::<Vec<_>>(),
+      &samples
+        .iter()
+        .map(|s| s.predicted_arrival)
+        .collect::<Vec<_>>(),
+      &samples.iter().map(|s| s.is_scheduled).collect::<Vec<_>>(),
+    )
+    .execute(db)
+    .await?
+    .rows_affected(),
+  )
+}
+
+pub async fn record_observed_arrivals(
+  db: impl Executor<'_, Database = Postgres>,
+  arrivals: &[ObservedArrival],
+) -> Result<u64, sqlx::Error> {
+  Ok(
+    sqlx::query!(
+      "INSERT INTO observed_arrivals(route, run_number, station_id, arrived_at)
+        SELECT * FROM UNNEST($1::text[], $2::int[], $3::int[], $4::bigint[])
+        ON CONFLICT DO NOTHING;",
+      &arrivals
+        .iter()
+        .map(|a| a.route.to_string())
+        .collect::<Vec<_>>(),
+      &arrivals.iter().map(|a| a.run_number).collect::<Vec<_>>(),
+      &arrivals.iter().map(|a| a.station_id).collect::<Vec<_>>(),
+      &arrivals.iter().map(|a| a.arrived_at).collect::<Vec<_>>(),
+    )
+    .execute(db)
+    .await?
+    .rows_affected(),
+  )
+}
+
+/// Fills in the actual arrival time of predictions made since `since`, using the first observed
+/// arrival of the same run at the same station after the prediction was made.
+pub async fn match_predictions(
+  db: impl Executor<'_, Database = Postgres>,
+  since: i64,
+  max_lead: i64,
+) -> Result<u64, sqlx::Error> {
+  Ok(
+    sqlx::query!(
+      "UPDATE prediction_samples SET actual_arrival = matched.arrived_at
+        FROM (
+          SELECT p.sample_id, MIN(o.arrived_at) AS arrived_at
+          FROM prediction_samples p
+          JOIN observed_arrivals o ON o.route = p.route
+            AND o.run_number = p.run_number
+            AND o.station_id = p.station_id
+            AND o.arrived_at BETWEEN p.predicted_at AND p.predicted_at + $2
+          WHERE p.actual_arrival IS NULL AND p.predicted_at >= $1
+          GROUP BY p.sample_id
+        ) matched
+        WHERE prediction_samples.sample_id = matched.sample_id;",
+      since,
+      max_lead
+    )
+    .execute(db)
+    .await?
+    .rows_affected(),
+  )
+}
+
+pub async fn prune_prediction_samples(
+  db: &Pool<Postgres>,
+  before: i64,
+) -> Result<u64, sqlx::Error> {
+  let samples = sqlx::query!(
+    "DELETE FROM prediction_samples WHERE predicted_at < $1;",
+    before
+  )
+  .execute(db)
+  .await?
+  .rows_affected();
+  let arrivals = sqlx::query!(
+    "DELETE FROM observed_arrivals WHERE arrived_at < $1;",
+    before
+  )
+  .execute(db)
+  .await?
+  .rows_affected();
+  Ok(samples + arrivals)
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct DBLeadAccuracy {
+  /// Lower bound of the lead time bucket, in minutes.
+  pub lead_minutes: i64,
+  pub samples: i64,
+  /// Average of actual minus predicted arrival, in seconds. Positive means trains came late.
+  pub mean_error: f64,
+  pub median_abs_error: f64,
+  /// Share of predictions that were within a minute, from 0 to 1.
+  pub within_minute: f64,
+}
+
+/// Accuracy of live predictions on a route since `since`, bucketed by lead time. `lead_buckets`
+/// are the upper bounds of the buckets in minutes, in order.
+pub async fn get_lead_accuracy(
+  db: impl Executor<'_, Database = Postgres>,
+  route: &str,
+  since: i64,
+  lead_buckets: &[i64],
+) -> Result<Vec<DBLeadAccuracy>, sqlx::Error> {
+  sqlx::query_as!(
+    DBLeadAccuracy,
+    "SELECT
+        COALESCE((SELECT MAX(bound) FROM UNNEST($3::bigint[]) bound WHERE bound * 60 <= lead), 0)
+          AS \"lead_minutes!\",
+        COUNT(*) AS \"samples!\",
+        AVG(error)::float8 AS \"mean_error!\",
+        (PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY ABS(error)))::float8 AS \"median_abs_error!\",
+        AVG((ABS(error) <= 60)::int)::float8 AS \"within_minute!\"
+      FROM (
+        SELECT predicted_arrival - predicted_at AS lead, actual_arrival - predicted_arrival AS error
+        FROM prediction_samples
+        WHERE route = $1 AND predicted_at >= $2 AND actual_arrival IS NOT NULL AND NOT is_scheduled
+      ) matched
+      GROUP BY 1
+      ORDER BY 1;",
+    route,
+    since,
+    lead_buckets
+  )
+  .fetch_all(db)
+  .await
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct DBStationAccuracy {
+  pub station_id: i32,
+  pub samples: i64,
+  pub median_abs_error: f64,
+}
+
+/// Stations on a route whose predictions were furthest off, for stations with at least
+/// `min_samples` matched predictions.
+pub async fn get_least_accurate_stations(
+  db: impl Executor<'_, Database = Postgres>,
+  route: &str,
+  since: i64,
+  min_samples: i64,
+  limit: i64,
+) -> Result<Vec<DBStationAccuracy>, sqlx::Error> {
+  sqlx::query_as!(
+    DBStationAccuracy,
+    "SELECT station_id,
+        COUNT(*) AS \"samples!\",
+        (PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY ABS(actual_arrival - predicted_arrival)))::float8
+          AS \"median_abs_error!\"
+      FROM prediction_samples
+      WHERE route = $1 AND predicted_at >= $2 AND actual_arrival IS NOT NULL AND NOT is_scheduled
+      GROUP BY station_id
+      HAVING COUNT(*) >= $3
+      ORDER BY 3 DESC
+      LIMIT $4;",
+    route,
+    since,
+    min_samples,
+    limit
+  )
+  .fetch_all(db)
+  .await
+}
+
+pub async fn record_scheduled_trips(
+  db: impl Executor<'_, Database = Postgres>,
+  trips: &[ScheduledTrip],
+) -> Result<u64, sqlx::Error> {
+  Ok(
+    sqlx::query!(
+      "INSERT INTO scheduled_trips(route, run_number, scheduled_arrival, ghost)
+        SELECT * FROM UNNEST($1::text[], $2::int[], $3::bigint[], $4::bool[])
+        ON CONFLICT DO NOTHING;",
+      &trips
+        .iter()
+        .map(|t| t.route.to_string())
+        .collect::<Vec<_>>(),
+      &trips.iter().map(|t| t.run_number).collect::<Vec<_>>(),
+      &trips
+        .iter()
+        .map(|t| t.scheduled_arrival)
+        .collect::<Vec<_>>(),
+      &trips.iter().map(|t| t.ghost).collect::<Vec<_>>(),
+    )
+    .execute(db)
+    .await?
+    .rows_affected(),
+  )
+}
+
+pub async fn prune_scheduled_trips(
+  db: impl Executor<'_, Database = Postgres>,
+  before: i64,
+) -> Result<u64, sqlx::Error> {
+  Ok(
+    sqlx::query!(
+      "DELETE FROM scheduled_trips WHERE scheduled_arrival < $1;",
+      before
+    )
+    .execute(db)
+    .await?
+    .rows_affected(),
+  )
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct DBGhostRate {
+  pub route: String,
+  /// Hour of the scheduled arrival, Chicago time.
+  pub hour: i32,
+  pub trips: i64,
+  pub ghosts: i64,
+}
+
+/// Scheduled-only trips since `since` and how many of them never ran, by route and hour.
+pub async fn get_ghost_rates(
+  db: impl Executor<'_, Database = Postgres>,
+  since: i64,
+) -> Result<Vec<DBGhostRate>, sqlx::Error> {
+  sqlx::query_as!(
+    DBGhostRate,
+    "SELECT route,
+        EXTRACT(HOUR FROM to_timestamp(scheduled_arrival) AT TIME ZONE 'America/Chicago')::int
+          AS \"hour!\",
+        COUNT(*) AS \"trips!\",
+        COUNT(*) FILTER (WHERE ghost) AS \"ghosts!\"
+      FROM scheduled_trips
+      WHERE scheduled_arrival >= $1
+      GROUP BY 1, 2;",
+    since
+  )
+  .fetch_all(db)
+  .await
+}
+
+/// What the recorded positions say about one line over one service day.
+#[derive(sqlx::FromRow, Debug)]
+pub struct DBObservedService {
+  pub samples: i64,
+  /// A trip is a run heading one way. Runs that vanish for half an hour start a new trip.
+  pub trips_run: i64,
+  pub delayed_share: f64,
+  /// Average seconds between consecutive runs reaching the same station in the same direction.
+  /// Gaps of `max_headway` or more are left out, as the line not running rather than a headway.
+  pub avg_headway: Option<f64>,
+}
+
+pub async fn get_observed_service(
+  db: impl Executor<'_, Database = Postgres>,
+  route: &str,
+  start: i64,
+  end: i64,
+  max_headway: i64,
+) -> Result<DBObservedService, sqlx::Error> {
+  sqlx::query_as!(
+    DBObservedService,
+    "WITH samples AS (
+        SELECT recorded_at, run_number, direction, next_station_id, is_delayed,
+          LAG(direction) OVER run_order AS prev_direction,
+          LAG(next_station_id) OVER run_order AS prev_station,
+          recorded_at - LAG(recorded_at) OVER run_order AS since_prev
+        FROM train_position_history
+        WHERE route = $1 AND recorded_at >= $2 AND recorded_at < $3
+        WINDOW run_order AS (PARTITION BY run_number ORDER BY recorded_at)
+      ),
+      visits AS (
+        SELECT recorded_at - LAG(recorded_at)
+          OVER (PARTITION BY direction, next_station_id ORDER BY recorded_at) AS headway
+        FROM samples
+        WHERE prev_station IS DISTINCT FROM next_station_id
+      )
+      SELECT
+        COUNT(*) AS \"samples!\",
+        COUNT(*) FILTER (WHERE prev_direction IS NULL OR prev_direction <> direction
+          OR since_prev > 1800) AS \"trips_run!\",
+        COALESCE(AVG(is_delayed::int), 0)::float8 AS \"delayed_share!\",
+        (SELECT AVG(headway) FROM visits WHERE headway < $4)::float8 AS avg_headway
+      FROM samples;",
+    route,
+    start,
+    end,
+    max_headway
+  )
+  .fetch_one(db)
+  .await
+}
+
+/// Longest stretch between `start` and `end` with no train positions recorded, in seconds,
+/// counting from `start` to the first poll and from the last poll to `end`.
+pub async fn get_longest_recording_gap(
+  db: impl Executor<'_, Database = Postgres>,
+  start: i64,
+  end: i64,
+) -> Result<i64, sqlx::Error> {
+  Ok(
+    sqlx::query!(
+      "WITH polls AS (
+          SELECT DISTINCT recorded_at FROM train_position_history
+          WHERE recorded_at >= $1 AND recorded_at < $2
+        )
+        SELECT COALESCE(GREATEST(
+          (SELECT MAX(recorded_at - prev) FROM (
+            SELECT recorded_at, LAG(recorded_at, 1, $1) OVER (ORDER BY recorded_at) AS prev
+            FROM polls
+          ) gaps),
+          $2 - (SELECT MAX(recorded_at) FROM polls)
+        ), $2 - $1) AS \"gap!\";",
+      start,
+      end
+    )
+    .fetch_one(db)
+    .await?
+    .gap,
+  )
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct DBTravelTime {
+  pub trips: i64,
+  /// Median seconds between reaching `from` and reaching `to`.
+  pub median: Option<f64>,
+  /// Seconds the slowest tenth of trips took at least.
+  pub slow: Option<f64>,
+}
+
+/// Typical riding time between two stations on a route, from recorded positions since `since`.
+/// A run reaches a station at about the last sample with that station as its next stop.
+pub async fn get_typical_travel_time(
+  db: impl Executor<'_, Database = Postgres>,
+  route: &str,
+  from: i32,
+  to: i32,
+  since: i64,
+) -> Result<DBTravelTime, sqlx::Error> {
+  sqlx::query_as!(
+    DBTravelTime,
+    "WITH samples AS (
+        SELECT run_number, direction, next_station_id, recorded_at,
+          LEAD(next_station_id) OVER run_order AS following_station,
+          LEAD(recorded_at) OVER run_order - recorded_at AS until_following
+        FROM train_position_history
+        WHERE route = $1 AND recorded_at >= $4 AND next_station_id IN ($2, $3)
+        WINDOW run_order AS (PARTITION BY run_number ORDER BY recorded_at)
+      ),
+      arrivals AS (
+        SELECT run_number, direction, next_station_id, recorded_at
+        FROM samples
+        WHERE following_station IS DISTINCT FROM next_station_id OR until_following > 600
+      ),
+      trips AS (
+        SELECT MIN(b.recorded_at - a.recorded_at) AS travel_time
+        FROM arrivals a
+        JOIN arrivals b ON b.run_number = a.run_number
+          AND b.direction = a.direction
+          AND b.next_station_id = $3
+          AND b.recorded_at > a.recorded_at
+          AND b.recorded_at < a.recorded_at + 7200
+        WHERE a.next_station_id = $2
+        GROUP BY a.run_number, a.recorded_at
+      )
+      SELECT COUNT(*) AS \"trips!\",
+        (PERCENTILE_CONT(0.5) WITHIN GROUP (ORDER BY travel_time))::float8 AS median,
+        (PERCENTILE_CONT(0.9) WITHIN GROUP (ORDER BY travel_time))::float8 AS slow
+      FROM trips;",
+    route,
+    from,
+    to,
+    since
+  )
+  .fetch_one(db)
+  .await
+}
+
+#[derive(sqlx::FromRow, Debug)]
+pub struct DBBusRouteHistory {
+  pub vid: String,
+  /// Every route the bus was recorded on, in the order it first ran them.
+  pub routes: Vec<String>,
+}
+
+/// Buses out of `vids` that recorded history has on more than one route since `since`. Only
+/// routes the history recorder polls are covered.
+pub async fn get_bus_route_history(
+  db: impl Executor<'_, Database = Postgres>,
+  vids: &[String],
+  since: i64,
+) -> Result<Vec<DBBusRouteHistory>, sqlx::Error> {
+  sqlx::query_as!(
+    DBBusRouteHistory,
+    "WITH first_seen AS (
+        SELECT vid, route, MIN(recorded_at) AS recorded_at
+        FROM bus_vehicle_history
+        WHERE vid = ANY($1) AND recorded_at >= $2
+        GROUP BY vid, route
+      )
+      SELECT vid, ARRAY_AGG(route ORDER BY recorded_at) AS \"routes!\"
+      FROM first_seen
+      GROUP BY vid
+      HAVING COUNT(*) > 1
+      ORDER BY vid;",
+    vids,
+    since
+  )
+  .fetch_all(db)
+  .await
+}
+
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct DBLineReliability {
+  pub service_day: i64,
+  pub route: String,
+  pub trips_scheduled: i32,
+  pub trips_run: i32,
+  pub avg_headway: Option<f64>,
+  pub scheduled_headway: Option<f64>,
+  pub delayed_share: f64,
+}
+
+pub async fn get_line_reliability(
+  db: impl Executor<'_, Database = Postgres>,
+  since: i64,
+) -> Result<Vec<DBLineReliability>, sqlx::Error> {
+  sqlx::query_as!(
+    DBLineReliability,
+    "SELECT * FROM line_reliability WHERE service_day >= $1 ORDER BY service_day, route;",
+    since
+  )
+  .fetch_all(db)
+  .await
+}
+
+pub async fn add_line_reliability(
+  db: impl Executor<'_, Database = Postgres>,
+  day: &DBLineReliability,
+) -> Result<(), sqlx::Error> {
+  sqlx::query!(
+    "INSERT INTO line_reliability(service_day, route, trips_scheduled, trips_run, avg_headway,
+        scheduled_headway, delayed_share)
+      VALUES ($1, $2, $3, $4, $5, $6, $7)
+      ON CONFLICT (service_day, route) DO UPDATE SET
+        trips_scheduled = EXCLUDED.trips_scheduled,
+        trips_run = EXCLUDED.trips_run,
+        avg_headway = EXCLUDED.avg_headway,
+        scheduled_headway = EXCLUDED.scheduled_headway,
+        delayed_share = EXCLUDED.delayed_share;",
+    day.service_day,
+    day.route,
+    day.trips_scheduled,
+    day.trips_run,
+    day.avg_headway,
+    day.scheduled_headway,
+    day.delayed_share
+  )
+  .execute(db)
+  .await?;
+  Ok(())
+}
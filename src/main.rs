@@ -25,6 +25,7 @@ pub struct CTASharedData {
   pub bustracker: cta::bustracker::BusTracker,
   pub stations: cta::stations::CtaStations,
   pub gtfs: cta::gtfs::CtaGTFS,
+  pub roster: cta::roster::BusRoster,
   pub db: Pool<Postgres>,
   // info: Info,
 }
@@ -49,6 +50,15 @@ impl EventHandler for Handler {
     commands::initialize(ctx.clone()).await;
 
     tokio::spawn(watcher::watch(ctx.clone()));
+    tokio::spawn(watcher::follow::watch(ctx.clone()));
+    tokio::spawn(watcher::anomalies::watch(ctx.clone()));
+    tokio::spawn(watcher::watchlist::watch(ctx.clone()));
+    tokio::spawn(watcher::gaps::watch(ctx.clone()));
+    tokio::spawn(watcher::history::watch(ctx.clone()));
+    tokio::spawn(watcher::accuracy::watch(ctx.clone()));
+    tokio::spawn(watcher::bulletins::watch(ctx.clone()));
+    tokio::spawn(watcher::bunching::watch(ctx.clone()));
+    tokio::spawn(watcher::detours::watch(ctx.clone()));
   }
   // async fn message(&self, ctx: Context, msg: Message) {
   //   if msg.content == "!ping" {
@@ -72,6 +82,21 @@ impl EventHandler for Handler {
           Some(commands::broadcast::run(&ctx, &command.data.options(), &interaction).await)
         }
         "alerts" => Some(commands::alerts::run(&ctx, &command.data.options()).await),
+        "trains" => Some(commands::trains::run(&ctx, &command.data.options()).await),
+        "headways" => Some(commands::headways::run(&ctx, &command.data.options()).await),
+        "accuracy" => Some(commands::accuracy::run(&ctx, &command.data.options()).await),
+        "reliability" => Some(commands::reliability::run(&ctx, &command.data.options()).await),
+        "ghosts" => Some(commands::ghosts::run(&ctx, &command.data.options()).await),
+        "traveltime" => match commands::traveltime::plan(&ctx, &command.data.options()).await {
+          Ok(trip) => {
+            commands::respond_later(&ctx, &command, commands::traveltime::run(&ctx, trip)).await;
+            None
+          }
+          Err(message) => Some(message),
+        },
+        "fleet" => Some(commands::fleet::run(&ctx, &command.data.options()).await),
+        "settings" => Some(commands::settings::run(&ctx, &command.data.options(), &command).await),
+        "watch" => Some(commands::watch::run(&ctx, &command.data.options(), &command).await),
         _ => {
           Some(CreateInteractionResponseMessage::new().content("not implemented yet.".to_string()))
         }
@@ -87,34 +112,25 @@ impl EventHandler for Handler {
     }
     // Check if it's one of the special cases where
     if let Interaction::Component(component) = interaction.clone() {
-      let content = if component
-        .data
-        .custom_id
-        .as_str()
-        .starts_with("bus_arrivals:select")
-      {
+      let custom_id = component.data.custom_id.as_str();
+      let content = if custom_id.starts_with("bus_arrivals:select") {
         Some(commands::bus::arrivals_select(&ctx, &component).await)
-      } else if component
-        .data
-        .custom_id
-        .as_str()
-        .starts_with("bus_arrivals:refresh")
-      {
+      } else if custom_id.starts_with("bus_arrivals:refresh") {
         Some(commands::bus::arrivals_refresh(&ctx, &component).await)
-      } else if component
-        .data
-        .custom_id
-        .as_str()
-        .starts_with("arrivals:select")
-      {
+      } else if custom_id.starts_with("arrivals:select") {
         Some(commands::arrivals::select(&ctx, &component).await)
-      } else if component
-        .data
-        .custom_id
-        .as_str()
-        .starts_with("arrivals:refresh")
-      {
+      } else if custom_id.starts_with("arrivals:refresh") {
         Some(commands::arrivals::refresh(&ctx, &component).await)
+      } else if custom_id.starts_with("get_train:follow") {
+        Some(commands::get_train::follow(&ctx, &component).await)
+      } else if custom_id.starts_with("get_train:unfollow") {
+        Some(commands::get_train::unfollow(&ctx, &component).await)
+      } else if custom_id.starts_with("bus:follow") {
+        Some(commands::bus::follow(&ctx, &component).await)
+      } else if custom_id.starts_with("bus:unfollow") {
+        Some(commands::bus::unfollow(&ctx, &component).await)
+      } else if custom_id.starts_with("page:") {
+        Some(commands::pagination::turn(&ctx, &component).await)
       } else {
         Some(CreateInteractionResponse::Message(
           CreateInteractionResponseMessage::new().content("not implemented yet."),
@@ -178,7 +194,14 @@ async fn init_shared(ctx: &Context) {
       env::var("CTA_BUS_API_KEY")
         .expect("CTA_BUS_API_KEY not found!")
         .as_str(),
-    ),
+    )
+    .with_version(match env::var("CTA_BUS_API_VERSION") {
+      Ok(val) => val.parse().unwrap_or_else(|e| {
+        println!("{e}. Falling back to v2.");
+        cta::bustracker::ApiVersion::V2
+      }),
+      Err(_) => cta::bustracker::ApiVersion::V2,
+    }),
     gtfs: cta::gtfs::CtaGTFS::new().await,
     traintracker: cta::traintracker::TrainTracker::new(
       env::var("CTA_RAIL_API_KEY")
@@ -186,6 +209,7 @@ async fn init_shared(ctx: &Context) {
         .as_str(),
     ),
     stations: cta::stations::CtaStations::new().await,
+    roster: cta::roster::BusRoster::load(),
     db, // db_connection: PgConnection::connect(
         //   ).await
         //   .expect("Couldn't connect to database.")
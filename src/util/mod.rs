@@ -23,6 +23,70 @@ pub fn countdown(mins_until: i32) -> String {
   }
 }
 
+/// Converts a heading in degrees into an eight-point compass direction.
+pub fn compass_direction(heading: i32) -> &'static str {
+  const POINTS: [&str; 8] = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+  let index = ((heading.rem_euclid(360) * 2 + 45) / 90) % 8;
+  POINTS[usize::try_from(index).unwrap_or_default()]
+}
+
+/// Unix timestamp of a date and time given in Chicago local time, like those from the CTA APIs.
+pub fn chicago_timestamp(naive: chrono::NaiveDateTime) -> i64 {
+  naive
+    .and_local_timezone(chrono_tz::America::Chicago)
+    .earliest()
+    .map_or_else(|| naive.and_utc().timestamp(), |t| t.timestamp())
+}
+
+/// Turns HTML from the CTA feeds into plain text, keeping paragraphs and line breaks.
+pub fn strip_html(html: &str) -> String {
+  let mut text = String::new();
+  let mut tag: Option<String> = None;
+  for c in html.chars() {
+    match (&mut tag, c) {
+      (None, '<') => tag = Some(String::new()),
+      (None, c) => text.push(c),
+      (Some(name), '>') => {
+        let name = name
+          .trim_start_matches('/')
+          .trim_end_matches('/')
+          .split_whitespace()
+          .next()
+          .unwrap_or_default()
+          .to_lowercase();
+        if matches!(name.as_str(), "br" | "p" | "div" | "li") {
+          text.push('\n');
+        }
+        tag = None;
+      }
+      (Some(name), c) => name.push(c),
+    }
+  }
+  let text = text
+    .replace("&nbsp;", " ")
+    .replace("&quot;", "\"")
+    .replace("&#39;", "'")
+    .replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&amp;", "&");
+  let mut lines: Vec<&str> = Vec::new();
+  for line in text.lines().map(str::trim) {
+    if !line.is_empty() || lines.last().is_some_and(|last| !last.is_empty()) {
+      lines.push(line);
+    }
+  }
+  lines.join("\n").trim().to_string()
+}
+
+/// Cuts `text` down to at most `max` characters, marking where it was cut.
+pub fn truncate(text: &str, max: usize) -> String {
+  if text.chars().count() <= max {
+    text.to_string()
+  } else {
+    format!("{}…", text.chars().take(max - 1).collect::<String>())
+  }
+}
+
 /// Deserialize bool from String with custom value mapping
 pub fn bool_from_string<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
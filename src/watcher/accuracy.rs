@@ -0,0 +1,128 @@
+use std::env;
+use std::time::Duration;
+
+use serenity::all::Context;
+
+use crate::{
+  cta::{
+    analysis::{
+      accuracy::{ArrivalTracker, PredictionSample},
+      ghosts::GhostTracker,
+      running_times::RunningTimes,
+    },
+    traintracker::{ArrivalsParameters, LRouteCode, MapOrStopID},
+  },
+  db, CTAShared, CTASharedData,
+};
+
+/// Stations whose arrival boards are sampled on each poll. The watcher works through every
+/// station in turn, which keeps well within the `TrainTracker` request limit.
+const STATIONS_PER_POLL: usize = 4;
+/// Predictions are only matched with arrivals up to this long after they were made.
+const MAX_LEAD_SECS: i64 = 2 * 60 * 60;
+const RETENTION_SECS: i64 = 14 * 24 * 60 * 60;
+const PRUNE_INTERVAL_SECS: i64 = 60 * 60;
+
+/// Samples predictions and arrivals when `RECORD_ACCURACY` is set to `1` or `true`. It writes a
+/// row for every prediction it sees, so it's off unless asked for.
+pub async fn watch(ctx: Context) {
+  static INTERVAL_SECS: u64 = 60;
+  if !matches!(env::var("RECORD_ACCURACY").as_deref(), Ok("1" | "true")) {
+    println!("RECORD_ACCURACY not set. Not recording prediction accuracy.");
+    return;
+  }
+  println!("Prediction accuracy task spawned. Polling every {INTERVAL_SECS} seconds.");
+  let mut tracker = ArrivalTracker::new();
+  let mut ghosts = {
+    let data = ctx.data.read().await;
+    let data = data.get::<CTAShared>().expect("no shared data");
+    GhostTracker::new(
+      LRouteCode::ALL
+        .into_iter()
+        .map(|route| {
+          let patterns = data.gtfs.stop_patterns(&route.to_string());
+          (route, RunningTimes::new(patterns))
+        })
+        .collect(),
+    )
+  };
+  let mut next_station = 0;
+  let mut last_pruned = 0;
+  loop {
+    let now = chrono::Utc::now().timestamp();
+    {
+      let data = ctx.data.read().await;
+      let data = data.get::<CTAShared>().expect("no shared data");
+      check(data, &mut tracker, &mut ghosts, &mut next_station, now).await;
+      if now - last_pruned > PRUNE_INTERVAL_SECS {
+        match db::prune_prediction_samples(&data.db, now - RETENTION_SECS).await {
+          Ok(_) => last_pruned = now,
+          Err(e) => println!("Error pruning old predictions: {e}"),
+        }
+        if let Err(e) = db::prune_scheduled_trips(&data.db, now - RETENTION_SECS).await {
+          println!("Error pruning old scheduled trips: {e}");
+        }
+      }
+    }
+    tokio::time::sleep(Duration::from_secs(INTERVAL_SECS)).await;
+  }
+}
+
+async fn check(
+  data: &CTASharedData,
+  tracker: &mut ArrivalTracker,
+  ghosts: &mut GhostTracker,
+  next_station: &mut usize,
+  now: i64,
+) {
+  let mut samples: Vec<PredictionSample> = Vec::new();
+  match data.traintracker.fleet(&LRouteCode::ALL).await {
+    Ok(fleet) => {
+      let arrivals = tracker.observe(&fleet, now);
+      if let Err(e) = db::record_observed_arrivals(&data.db, &arrivals).await {
+        println!("Error recording observed arrivals: {e}");
+      }
+      let trips = ghosts.observe(&fleet, now);
+      if let Err(e) = db::record_scheduled_trips(&data.db, &trips).await {
+        println!("Error recording scheduled trips: {e}");
+      }
+      samples.extend(
+        fleet
+          .trains
+          .iter()
+          .filter_map(PredictionSample::from_position),
+      );
+    }
+    Err(e) => println!("Error getting train positions for prediction accuracy: {e}"),
+  }
+
+  let stations = data.stations.map_ids();
+  for _ in 0..STATIONS_PER_POLL.min(stations.len()) {
+    let mapid = stations[*next_station % stations.len()];
+    *next_station = (*next_station + 1) % stations.len();
+    match data
+      .traintracker
+      .arrivals(ArrivalsParameters {
+        id: MapOrStopID::MapID { mapid },
+        max: None,
+        rt: None,
+      })
+      .await
+    {
+      Ok(arrivals) => {
+        let station_samples: Vec<PredictionSample> =
+          arrivals.iter().map(PredictionSample::from).collect();
+        ghosts.expect(&station_samples);
+        samples.extend(station_samples);
+      }
+      Err(e) => println!("Error getting arrivals at {mapid} for prediction accuracy: {e}"),
+    }
+  }
+
+  if let Err(e) = db::record_predictions(&data.db, &samples).await {
+    println!("Error recording predictions: {e}");
+  }
+  if let Err(e) = db::match_predictions(&data.db, now - MAX_LEAD_SECS, MAX_LEAD_SECS).await {
+    println!("Error matching predictions with arrivals: {e}");
+  }
+}
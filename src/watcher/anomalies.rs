@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use serenity::all::{ChannelId, Context, CreateEmbed, CreateEmbedFooter, CreateMessage};
+
+use crate::{
+  cta::{
+    analysis::{self, AnomalousTrain, AnomalyType},
+    traintracker::{LRouteCode, LRouteName},
+  },
+  db, CTAShared, CTASharedData,
+};
+
+/// An anomaly that hasn't been seen for this long is forgotten and will be posted again if it returns.
+const FORGET_AFTER_SECS: i64 = 6 * 60 * 60;
+
+pub async fn watch(ctx: Context) {
+  static INTERVAL_SECS: u64 = 60;
+  println!("Anomaly watcher task spawned. Polling every {INTERVAL_SECS} seconds.");
+  loop {
+    check(&ctx).await;
+    tokio::time::sleep(Duration::from_secs(INTERVAL_SECS)).await;
+  }
+}
+
+async fn check(ctx: &Context) {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+  let now = chrono::Utc::now().timestamp();
+
+  let routes = match data.traintracker.positions(&LRouteCode::ALL).await {
+    Ok(routes) => routes,
+    Err(e) => {
+      println!("Error getting train positions for anomaly check: {e}");
+      return;
+    }
+  };
+  for anomaly in analysis::check_route(&routes, &data.stations) {
+    let route = anomaly.route.to_string();
+    match db::record_anomaly(
+      &data.db,
+      &route,
+      anomaly.position.run_number,
+      anomaly.anomaly_type.key(),
+      now,
+    )
+    .await
+    {
+      Ok(true) => {
+        let published = publish(ctx, data, &anomaly).await;
+        let _ = db::set_anomaly_published(
+          &data.db,
+          &route,
+          anomaly.position.run_number,
+          anomaly.anomaly_type.key(),
+          published,
+        )
+        .await;
+      }
+      Ok(false) => {}
+      Err(e) => {
+        println!("Error recording anomaly: {e}");
+      }
+    }
+  }
+  if let Err(e) = db::prune_anomalies(&data.db, now - FORGET_AFTER_SECS).await {
+    println!("Error pruning old anomalies: {e}");
+  }
+}
+
+#[allow(clippy::cast_sign_loss)]
+async fn publish(ctx: &Context, data: &CTASharedData, anomaly: &AnomalousTrain) -> i32 {
+  let guilds = match db::get_anomaly_guilds(&data.db).await {
+    Ok(guilds) => guilds,
+    Err(e) => {
+      println!("Error reading anomaly guilds from database: {e}");
+      return 0;
+    }
+  };
+  let embed = anomaly_embed(anomaly);
+  let mut publish_count = 0;
+  for guild in guilds {
+    if let Some(chan_id) = guild.anomaly_channel {
+      if ChannelId::from(chan_id as u64)
+        .send_message(&ctx.http, CreateMessage::new().add_embed(embed.clone()))
+        .await
+        .is_ok()
+      {
+        publish_count += 1;
+      }
+    }
+  }
+  publish_count
+}
+
+fn anomaly_embed(anomaly: &AnomalousTrain) -> CreateEmbed {
+  let line = LRouteName::from(anomaly.route);
+  let train = &anomaly.position;
+  let description = match anomaly.anomaly_type {
+    AnomalyType::RunNumber => format!(
+      "Run #{:0>3} is running on the {line} to {}, outside the usual {} range.",
+      train.run_number,
+      train.destination_name,
+      analysis::usual_run_numbers(anomaly.route)
+    ),
+    AnomalyType::NextStation => format!(
+      "Run #{:0>3} on the {line} is headed to {}, which is not a {line} station.",
+      train.run_number, train.next_station_name
+    ),
+  };
+  CreateEmbed::new()
+    .title(format!("Unusual train on the {line}"))
+    .color(line.color_value())
+    .description(description)
+    .field("Next Station", &train.next_station_name, true)
+    .field("Destination", &train.destination_name, true)
+    .footer(CreateEmbedFooter::new(format!(
+      "Reported at {}",
+      train.prediction_time.format("%-I:%M %p")
+    )))
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serenity::all::{ChannelId, Context, CreateMessage};
+
+use crate::{
+  commands::bus,
+  cta::bustracker::{ServiceBulletin, ServiceBulletinsParameters},
+  db::{self, DBBusBulletin},
+  CTAShared,
+};
+
+/// Bulletins Bus Tracker hasn't listed for this long are forgotten, so they'd be posted again if
+/// they came back.
+const FORGET_AFTER_SECS: i64 = 7 * 24 * 60 * 60;
+
+pub async fn watch(ctx: Context) {
+  static INTERVAL_SECS: u64 = 300;
+  println!("Bus bulletin watcher task spawned. Polling every {INTERVAL_SECS} seconds.");
+  loop {
+    check(&ctx).await;
+    tokio::time::sleep(Duration::from_secs(INTERVAL_SECS)).await;
+  }
+}
+
+#[allow(clippy::cast_sign_loss)]
+async fn check(ctx: &Context) {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+  let now = chrono::Utc::now().timestamp();
+
+  let guilds = match db::get_bulletin_guilds(&data.db).await {
+    Ok(guilds) => guilds,
+    Err(e) => {
+      println!("Error getting bulletin guilds from database: {e}");
+      return;
+    }
+  };
+  let mut routes: Vec<&String> = guilds
+    .iter()
+    .flat_map(|g| g.bus_routes.iter().flatten())
+    .collect();
+  routes.sort();
+  routes.dedup();
+
+  // Routes are asked for one at a time, since bulletins about a stop don't always say which
+  // route they're for.
+  let mut bulletins: HashMap<String, (ServiceBulletin, Vec<String>)> = HashMap::new();
+  for route in routes {
+    match data
+      .bustracker
+      .get_service_bulletins(ServiceBulletinsParameters {
+        rt: vec![route.clone()],
+        ..Default::default()
+      })
+      .await
+    {
+      Ok(list) => {
+        for bulletin in list {
+          bulletins
+            .entry(bulletin.nm.clone())
+            .or_insert_with(|| (bulletin, Vec::new()))
+            .1
+            .push(route.clone());
+        }
+      }
+      Err(e) => println!("Error getting service bulletins for route {route}: {e}"),
+    }
+  }
+
+  let names: Vec<String> = bulletins.keys().cloned().collect();
+  let known = match db::get_bus_bulletins_with_names(&data.db, &names).await {
+    Ok(known) => known,
+    Err(e) => {
+      println!("Error getting bus bulletins from database: {e}");
+      return;
+    }
+  };
+  for (name, (bulletin, routes)) in &bulletins {
+    if known.iter().any(|k| &k.name == name) {
+      continue;
+    }
+    let mut published_to = 0;
+    for guild in &guilds {
+      let (Some(channel), Some(guild_routes)) = (guild.bulletin_channel, &guild.bus_routes) else {
+        continue;
+      };
+      if !guild_routes.iter().any(|rt| routes.contains(rt)) {
+        continue;
+      }
+      match ChannelId::new(channel as u64)
+        .send_message(
+          &ctx.http,
+          CreateMessage::new().embed(bus::bulletin_embed(bulletin)),
+        )
+        .await
+      {
+        Ok(_) => published_to += 1,
+        Err(e) => println!("Could not post bus bulletin to channel {channel}: {e}"),
+      }
+    }
+    let record = DBBusBulletin {
+      name: name.clone(),
+      subject: bulletin.sbj.clone(),
+      routes: routes.clone(),
+      published_to,
+      first_seen: now,
+      last_seen: now,
+    };
+    if let Err(e) = db::add_bus_bulletin(&data.db, &record).await {
+      println!("Error saving bus bulletin: {e}");
+    }
+  }
+
+  if let Err(e) = db::touch_bus_bulletins(&data.db, &names, now).await {
+    println!("Error updating bus bulletins: {e}");
+  }
+  if let Err(e) = db::prune_bus_bulletins(&data.db, now - FORGET_AFTER_SECS).await {
+    println!("Error pruning bus bulletins: {e}");
+  }
+}
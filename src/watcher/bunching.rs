@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serenity::all::{ChannelId, Context, CreateEmbed, CreateEmbedFooter, CreateMessage};
+
+use crate::{
+  commands::bus,
+  cta::{
+    analysis::bunching::{self, BunchingTracker},
+    bustracker::Pattern,
+  },
+  db, CTAShared, CTASharedData,
+};
+
+pub async fn watch(ctx: Context) {
+  static INTERVAL_SECS: u64 = 120;
+  println!("Bus bunching watcher task spawned. Polling every {INTERVAL_SECS} seconds.");
+  let mut tracker = BunchingTracker::new();
+  // Patterns rarely change, so they're only fetched the first time a bus is seen running one.
+  let mut patterns: HashMap<i32, Pattern> = HashMap::new();
+  loop {
+    check(&ctx, &mut tracker, &mut patterns).await;
+    tokio::time::sleep(Duration::from_secs(INTERVAL_SECS)).await;
+  }
+}
+
+#[allow(clippy::cast_sign_loss)]
+async fn check(ctx: &Context, tracker: &mut BunchingTracker, patterns: &mut HashMap<i32, Pattern>) {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+  let now = chrono::Utc::now().timestamp();
+
+  let guilds = match db::get_bunching_guilds(&data.db).await {
+    Ok(guilds) => guilds,
+    Err(e) => {
+      println!("Error getting bunching guilds from database: {e}");
+      return;
+    }
+  };
+  let mut routes: Vec<String> = guilds
+    .iter()
+    .flat_map(|g| g.bunching_routes.iter().flatten().cloned())
+    .collect();
+  routes.sort();
+  routes.dedup();
+  if routes.is_empty() {
+    return;
+  }
+
+  // Routes that couldn't be fetched have no buses, so they aren't observed this time.
+  let (vehicles, _) = bus::route_vehicles(data, &routes).await;
+  let unknown: Vec<i32> = vehicles
+    .iter()
+    .map(|v| v.pid)
+    .filter(|pid| !patterns.contains_key(pid))
+    .collect();
+  if !unknown.is_empty() {
+    patterns.extend(bus::patterns_by_id(data, unknown).await);
+  }
+  let lengths: HashMap<i32, f64> = patterns.iter().map(|(pid, p)| (*pid, p.ln)).collect();
+
+  // Each pattern belongs to a single route, so every route's buses can be looked at together.
+  let spacing = bunching::spacing(&vehicles, &lengths);
+  let mut summaries: HashMap<&str, Vec<String>> = HashMap::new();
+  for pattern in &spacing {
+    summaries
+      .entry(pattern.buses[0].0.rt.as_str())
+      .or_default()
+      .extend(
+        pattern
+          .bunches
+          .iter()
+          .map(|bunch| bus::bunch_summary(bunch, patterns.get(&pattern.pid))),
+      );
+  }
+  // Routes with no buses out aren't observed, so they don't count against bunching.
+  for (route, bunches) in &summaries {
+    tracker.observe(route, !bunches.is_empty(), now);
+  }
+
+  for route in tracker.newly_chronic(now) {
+    let embed = bunching_embed(
+      data,
+      &route,
+      summaries.get(route.as_str()).map_or(&[], Vec::as_slice),
+    );
+    for guild in &guilds {
+      let (Some(channel), Some(guild_routes)) = (guild.bunching_channel, &guild.bunching_routes)
+      else {
+        continue;
+      };
+      if !guild_routes.contains(&route) {
+        continue;
+      }
+      if let Err(e) = ChannelId::new(channel as u64)
+        .send_message(&ctx.http, CreateMessage::new().embed(embed.clone()))
+        .await
+      {
+        println!("Could not post bus bunching to channel {channel}: {e}");
+      }
+    }
+  }
+}
+
+fn bunching_embed(data: &CTASharedData, route: &str, bunches: &[String]) -> CreateEmbed {
+  let route_name = data
+    .gtfs
+    .gtfs_data
+    .get_route(route)
+    .ok()
+    .and_then(|r| r.long_name.clone())
+    .map(|name| format!(" ({name})"))
+    .unwrap_or_default();
+  let mut description = format!(
+    "Buses on Route {route} have been running in bunches for most of the last {} minutes, \
+    so expect long waits followed by several buses at once.",
+    bunching::CHRONIC_WINDOW_SECS / 60
+  );
+  if !bunches.is_empty() {
+    description.push_str("\n\n**Right now**\n");
+    description.push_str(&bunches.join("\n"));
+  }
+  CreateEmbed::new()
+    .title(format!("Buses bunching on Route {route}{route_name}"))
+    .description(description)
+    .footer(CreateEmbedFooter::new(
+      "Based on bus positions from Bus Tracker. See /bus bunching for the latest spacing.",
+    ))
+}
@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+use serenity::all::{ChannelId, Context, CreateMessage};
+
+use crate::{
+  commands::bus,
+  cta::bustracker::DetoursParameters,
+  db::{self, DBBusDetour},
+  CTAShared,
+};
+
+/// Detours no longer in effect are forgotten after this long.
+const FORGET_AFTER_SECS: i64 = 24 * 60 * 60;
+
+pub async fn watch(ctx: Context) {
+  static INTERVAL_SECS: u64 = 300;
+  println!("Bus detour watcher task spawned. Polling every {INTERVAL_SECS} seconds.");
+  loop {
+    check(&ctx).await;
+    tokio::time::sleep(Duration::from_secs(INTERVAL_SECS)).await;
+  }
+}
+
+/// Posts detours that are new, or have a new version, to guilds following an affected route.
+/// Detours go to the same guilds and channels as bus service bulletins.
+#[allow(clippy::cast_sign_loss)]
+async fn check(ctx: &Context) {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+  let now = chrono::Utc::now().timestamp();
+
+  let guilds = match db::get_bulletin_guilds(&data.db).await {
+    Ok(guilds) if guilds.is_empty() => return,
+    Ok(guilds) => guilds,
+    Err(e) => {
+      println!("Error getting bulletin guilds from database: {e}");
+      return;
+    }
+  };
+  let detours = match data
+    .bustracker
+    .get_detours(DetoursParameters::default())
+    .await
+  {
+    Ok(detours) => detours,
+    Err(e) => {
+      println!("Error getting bus detours: {e}");
+      return;
+    }
+  };
+  let followed = |rt: &String| {
+    guilds.iter().any(|g| {
+      g.bus_routes
+        .as_ref()
+        .is_some_and(|routes| routes.contains(rt))
+    })
+  };
+  let detours: Vec<_> = detours
+    .into_iter()
+    .filter(|d| d.is_active() && d.rtdirs.iter().any(|rtdir| followed(&rtdir.rt)))
+    .collect();
+  let ids: Vec<String> = detours.iter().map(|d| d.id.clone()).collect();
+  let known = match db::get_bus_detours_with_ids(&data.db, &ids).await {
+    Ok(known) => known,
+    Err(e) => {
+      println!("Error getting bus detours from database: {e}");
+      return;
+    }
+  };
+
+  for detour in &detours {
+    let previous = known.iter().find(|k| k.detour_id == detour.id);
+    if previous.is_some_and(|k| k.version >= detour.ver) {
+      continue;
+    }
+    let mut routes: Vec<String> = detour.rtdirs.iter().map(|d| d.rt.clone()).collect();
+    routes.sort();
+    routes.dedup();
+    let (embed, map) = bus::detour_message(data, detour).await;
+    let mut message = CreateMessage::new()
+      .embed(embed)
+      .content(if previous.is_some() {
+        "A detour has been updated."
+      } else {
+        "A new detour is in effect."
+      });
+    if let Some(map) = map {
+      message = message.add_file(map);
+    }
+    let mut published_to = 0;
+    for guild in &guilds {
+      let (Some(channel), Some(guild_routes)) = (guild.bulletin_channel, &guild.bus_routes) else {
+        continue;
+      };
+      if !guild_routes.iter().any(|rt| routes.contains(rt)) {
+        continue;
+      }
+      match ChannelId::new(channel as u64)
+        .send_message(&ctx.http, message.clone())
+        .await
+      {
+        Ok(_) => published_to += 1,
+        Err(e) => println!("Could not post bus detour to channel {channel}: {e}"),
+      }
+    }
+    let record = DBBusDetour {
+      detour_id: detour.id.clone(),
+      version: detour.ver,
+      routes,
+      published_to,
+      first_seen: previous.map_or(now, |k| k.first_seen),
+      last_seen: now,
+    };
+    if let Err(e) = db::save_bus_detour(&data.db, &record).await {
+      println!("Error saving bus detour: {e}");
+    }
+  }
+
+  if let Err(e) = db::touch_bus_detours(&data.db, &ids, now).await {
+    println!("Error updating bus detours: {e}");
+  }
+  if let Err(e) = db::prune_bus_detours(&data.db, now - FORGET_AFTER_SECS).await {
+    println!("Error pruning bus detours: {e}");
+  }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serenity::all::{ChannelId, Context, EditMessage, MessageId};
+
+use crate::{
+  commands::{
+    bus,
+    get_train::{self, FollowStatus},
+  },
+  cta::bustracker::{Prediction, Vehicle},
+  db::{self, DBFollowedBus, DBFollowedTrain},
+  CTAShared, CTASharedData,
+};
+
+/// Follows are dropped after this long, whether or not the train is still running.
+const FOLLOW_TIMEOUT_SECS: i64 = 2 * 60 * 60;
+/// Number of consecutive polls a train or bus can be missing from the trackers before the follow
+/// ends.
+const MAX_MISSED_POLLS: i32 = 4;
+
+pub async fn watch(ctx: Context) {
+  static INTERVAL_SECS: u64 = 30;
+  println!("Follow task spawned. Updating every {INTERVAL_SECS} seconds.");
+  loop {
+    check(&ctx).await;
+    tokio::time::sleep(Duration::from_secs(INTERVAL_SECS)).await;
+  }
+}
+
+async fn check(ctx: &Context) {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+  match db::get_followed_trains(&data.db).await {
+    Ok(follows) => {
+      for follow in follows {
+        update(ctx, data, follow).await;
+      }
+    }
+    Err(e) => {
+      println!("Error getting followed trains from database: {e}");
+    }
+  }
+  match db::get_followed_buses(&data.db).await {
+    Ok(follows) => update_buses(ctx, data, follows).await,
+    Err(e) => {
+      println!("Error getting followed buses from database: {e}");
+    }
+  }
+}
+
+#[allow(
+  clippy::cast_sign_loss,
+  clippy::cast_possible_truncation,
+  clippy::cast_possible_wrap
+)]
+async fn update(ctx: &Context, data: &CTASharedData, follow: DBFollowedTrain) {
+  let run = follow.run_number;
+  if chrono::Utc::now().timestamp() - follow.started_at > FOLLOW_TIMEOUT_SECS {
+    finish(ctx, data, &follow, FollowStatus::TimedOut).await;
+    return;
+  }
+  let etas = data
+    .traintracker
+    .follow_train(run)
+    .await
+    .unwrap_or_default();
+  if let Some(passed) = get_train::passed_count(&follow.stations, &etas) {
+    let position = get_train::current_position(&data.traintracker, run, &etas).await;
+    let embed = get_train::follow_embed(&follow.stations, passed, &etas);
+    let edit =
+      match get_train::strip_map(run, &follow.stations, passed, &etas, position.as_ref()) {
+        Some(attachment) => EditMessage::new()
+          .new_attachment(attachment)
+          .embed(embed.image(format!("attachment://{}", get_train::STRIP_MAP_FILE))),
+        None => EditMessage::new().remove_all_attachments().embed(embed),
+      }
+      .content(get_train::follow_content(run, FollowStatus::Live))
+      .components(get_train::follow_components(FollowStatus::Live));
+    if let Err(why) = edit_follow(ctx, follow.channel_id, follow.message_id, edit).await {
+      println!("Could not update followed train #{run}, dropping it: {why}");
+      let _ = db::remove_followed_train(&data.db, follow.message_id).await;
+      return;
+    }
+    let _ = db::update_followed_train(&data.db, follow.message_id, passed as i32, 0).await;
+  } else if follow.last_seen_index as usize + 1 >= follow.stations.len() {
+    finish(ctx, data, &follow, FollowStatus::Finished).await;
+  } else if follow.missed_polls + 1 >= MAX_MISSED_POLLS {
+    finish(ctx, data, &follow, FollowStatus::Lost).await;
+  } else {
+    let _ = db::update_followed_train(
+      &data.db,
+      follow.message_id,
+      follow.last_seen_index,
+      follow.missed_polls + 1,
+    )
+    .await;
+  }
+}
+
+#[allow(clippy::cast_sign_loss)]
+async fn finish(
+  ctx: &Context,
+  data: &CTASharedData,
+  follow: &DBFollowedTrain,
+  status: FollowStatus,
+) {
+  let passed = match status {
+    FollowStatus::Finished => follow.stations.len(),
+    _ => follow.last_seen_index as usize,
+  };
+  let edit = EditMessage::new()
+    .content(get_train::follow_content(follow.run_number, status))
+    .remove_all_attachments()
+    .embed(get_train::follow_embed(&follow.stations, passed, &[]))
+    .components(get_train::follow_components(status));
+  if let Err(why) = edit_follow(ctx, follow.channel_id, follow.message_id, edit).await {
+    println!(
+      "Could not finish followed train #{}: {why}",
+      follow.run_number
+    );
+  }
+  if let Err(why) = db::remove_followed_train(&data.db, follow.message_id).await {
+    println!("Error removing followed train: {why}");
+  }
+}
+
+/// Bus Tracker's daily request limit is tight, so every followed bus is looked up together, ten
+/// to a request.
+async fn update_buses(ctx: &Context, data: &CTASharedData, follows: Vec<DBFollowedBus>) {
+  let now = chrono::Utc::now().timestamp();
+  let (expired, follows): (Vec<_>, Vec<_>) = follows
+    .into_iter()
+    .partition(|follow| now - follow.started_at > FOLLOW_TIMEOUT_SECS);
+  for follow in &expired {
+    finish_bus(ctx, data, follow, FollowStatus::TimedOut).await;
+  }
+
+  let mut vids: Vec<String> = follows.iter().map(|follow| follow.vid.clone()).collect();
+  vids.sort();
+  vids.dedup();
+  let mut buses: HashMap<String, (Vehicle, Vec<Prediction>)> = HashMap::new();
+  for chunk in vids.chunks(bus::MAX_REQUEST_IDS) {
+    match bus::vehicle_details(data, chunk).await {
+      Ok(found) => buses.extend(found),
+      Err(e) => println!("Error getting followed buses {}: {e}", chunk.join(",")),
+    }
+  }
+  for follow in follows {
+    update_bus(ctx, data, &follow, buses.get(&follow.vid)).await;
+  }
+}
+
+async fn update_bus(
+  ctx: &Context,
+  data: &CTASharedData,
+  follow: &DBFollowedBus,
+  details: Option<&(Vehicle, Vec<Prediction>)>,
+) {
+  let Some((vehicle, predictions)) = details else {
+    if follow.missed_polls + 1 >= MAX_MISSED_POLLS {
+      finish_bus(ctx, data, follow, FollowStatus::Lost).await;
+    } else {
+      let _ = db::update_followed_bus(&data.db, follow.message_id, follow.missed_polls + 1).await;
+    }
+    return;
+  };
+  let edit = EditMessage::new()
+    .embed(bus::describe_vehicle(data, vehicle, predictions))
+    .content(bus::follow_content(&follow.vid, FollowStatus::Live))
+    .components(bus::follow_components(FollowStatus::Live));
+  if let Err(why) = edit_follow(ctx, follow.channel_id, follow.message_id, edit).await {
+    println!(
+      "Could not update followed bus #{}, dropping it: {why}",
+      follow.vid
+    );
+    let _ = db::remove_followed_bus(&data.db, follow.message_id).await;
+    return;
+  }
+  let _ = db::update_followed_bus(&data.db, follow.message_id, 0).await;
+}
+
+async fn finish_bus(
+  ctx: &Context,
+  data: &CTASharedData,
+  follow: &DBFollowedBus,
+  status: FollowStatus,
+) {
+  let edit = EditMessage::new()
+    .content(bus::follow_content(&follow.vid, status))
+    .components(bus::follow_components(status));
+  if let Err(why) = edit_follow(ctx, follow.channel_id, follow.message_id, edit).await {
+    println!("Could not finish followed bus #{}: {why}", follow.vid);
+  }
+  if let Err(why) = db::remove_followed_bus(&data.db, follow.message_id).await {
+    println!("Error removing followed bus: {why}");
+  }
+}
+
+#[allow(clippy::cast_sign_loss)]
+async fn edit_follow(
+  ctx: &Context,
+  channel_id: i64,
+  message_id: i64,
+  edit: EditMessage,
+) -> Result<(), serenity::Error> {
+  ChannelId::new(channel_id as u64)
+    .edit_message(&ctx.http, MessageId::new(message_id as u64), edit)
+    .await
+    .map(|_| ())
+}
@@ -0,0 +1,349 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{NaiveDate, NaiveTime, Timelike};
+use serenity::all::{
+  ChannelId, Context, CreateEmbed, CreateEmbedFooter, CreateMessage, EditMessage, MessageId,
+};
+
+use crate::{
+  cta::{
+    self,
+    alerts::{AlertsOptions, ServiceType},
+    analysis::{
+      gaps::{self, DarkStation, GapTracker},
+      reliability::service_date,
+    },
+    traintracker::{LRouteCode, LRouteName},
+  },
+  db::{self, DBServiceGap},
+  util::chicago_timestamp,
+  CTAShared, CTASharedData,
+};
+
+/// A direction of a line needs this many dark stations before it's reported, so a station the
+/// poller happened to miss doesn't count as a gap on its own.
+const MIN_DARK_STATIONS: usize = 2;
+/// New gaps are only reported between these hours, since lines thin out and shut down overnight.
+const ACTIVE_HOURS: std::ops::Range<u32> = 5..23;
+/// Official alerts are matched to gaps detected within this long...
+const LINK_WINDOW_SECS: i64 = 3 * 60 * 60;
+/// ...as long as the alert didn't start more than this long before the gap was detected.
+const ALERT_SLACK_SECS: i64 = 30 * 60;
+/// Resolved gaps are deleted after this long.
+const FORGET_AFTER_SECS: i64 = 24 * 60 * 60;
+
+/// Scheduled arrivals at each platform over one service day, from the GTFS schedule.
+#[derive(Default)]
+struct Schedule {
+  date: Option<NaiveDate>,
+  /// Midnight at the start of the service day, which schedule times count from.
+  midnight: i64,
+  arrivals: HashMap<(LRouteCode, i32), Vec<u32>>,
+}
+
+impl Schedule {
+  fn new(data: &CTASharedData, today: NaiveDate) -> Self {
+    let mut arrivals = HashMap::new();
+    for route in LRouteCode::ALL {
+      for (stop_id, times) in data.gtfs.stop_schedule(&route.to_string(), today) {
+        if let Ok(stop_id) = stop_id.parse() {
+          arrivals.insert((route, stop_id), times);
+        }
+      }
+    }
+    Self {
+      date: Some(today),
+      midnight: chicago_timestamp(today.and_time(NaiveTime::MIN)),
+      arrivals,
+    }
+  }
+
+  /// The scheduled headway on `route` at a platform around `now`.
+  fn headway(&self, route: LRouteCode, stop_id: i32, now: i64) -> Option<i64> {
+    let at = u32::try_from(now - self.midnight).ok()?;
+    gaps::scheduled_headway(self.arrivals.get(&(route, stop_id))?, at)
+  }
+}
+
+pub async fn watch(ctx: Context) {
+  static INTERVAL_SECS: u64 = 60;
+  println!("Service gap watcher task spawned. Polling every {INTERVAL_SECS} seconds.");
+  let mut tracker = GapTracker::new();
+  let mut schedule = Schedule::default();
+  loop {
+    check(&ctx, &mut tracker, &mut schedule).await;
+    tokio::time::sleep(Duration::from_secs(INTERVAL_SECS)).await;
+  }
+}
+
+async fn check(ctx: &Context, tracker: &mut GapTracker, schedule: &mut Schedule) {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+  let now = chrono::Utc::now().timestamp();
+  let today = service_date(now);
+  if schedule.date != Some(today) {
+    *schedule = Schedule::new(data, today);
+  }
+
+  let fleet = match data.traintracker.fleet(&LRouteCode::ALL).await {
+    Ok(fleet) => fleet,
+    Err(e) => {
+      println!("Error getting train positions for service gap check: {e}");
+      return;
+    }
+  };
+  tracker.observe(&fleet, now);
+
+  let open_gaps = match db::get_open_service_gaps(&data.db).await {
+    Ok(gaps) => gaps,
+    Err(e) => {
+      println!("Error reading service gaps from database: {e}");
+      return;
+    }
+  };
+  let active = ACTIVE_HOURS.contains(&chicago_time(now).hour());
+  for route in LRouteCode::ALL {
+    for (direction, toward) in tracker.directions(route) {
+      let dark = tracker.dark_stations(route, direction, now, |station, stop| {
+        if data.stations.serves_all_day(station, route) != Some(true) {
+          return None;
+        }
+        schedule.headway(route, stop, now)
+      });
+      let open_gap = open_gaps
+        .iter()
+        .find(|g| g.route == route.to_string() && g.direction == i16::from(direction));
+      match open_gap {
+        Some(gap) if dark.len() < MIN_DARK_STATIONS => {
+          if let Err(e) = db::resolve_service_gap(&data.db, gap.gap_id, now).await {
+            println!("Error resolving service gap {}: {e}", gap.gap_id);
+            continue;
+          }
+          let gap = DBServiceGap {
+            resolved_at: Some(now),
+            ..gap.clone()
+          };
+          update_messages(ctx, data, &gap).await;
+        }
+        // Trains still have to be running in this direction, or the line has just shut down.
+        None
+          if active
+            && dark.len() >= MIN_DARK_STATIONS
+            && fleet
+              .on_route(route)
+              .any(|t| t.train_direction == direction) =>
+        {
+          report(ctx, data, route, direction, toward, &dark, now).await;
+        }
+        _ => {}
+      }
+    }
+  }
+
+  link_alerts(ctx, data, now).await;
+  if let Err(e) = db::prune_service_gaps(&data.db, now - FORGET_AFTER_SECS).await {
+    println!("Error pruning old service gaps: {e}");
+  }
+}
+
+#[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+async fn report(
+  ctx: &Context,
+  data: &CTASharedData,
+  route: LRouteCode,
+  direction: i8,
+  toward: &str,
+  dark: &[DarkStation],
+  now: i64,
+) {
+  let station_names: Vec<String> = dark.iter().map(|s| s.station_name.clone()).collect();
+  let last_seen = dark.iter().map(|s| s.last_seen).max().unwrap_or(now);
+  let scheduled_headway = dark
+    .iter()
+    .map(|s| s.scheduled_headway)
+    .max()
+    .unwrap_or_default();
+  let gap = match db::add_service_gap(
+    &data.db,
+    &route.to_string(),
+    i16::from(direction),
+    toward,
+    &station_names,
+    last_seen,
+    scheduled_headway,
+    now,
+  )
+  .await
+  {
+    Ok(Some(gap)) => gap,
+    Ok(None) => return,
+    Err(e) => {
+      println!("Error saving service gap: {e}");
+      return;
+    }
+  };
+  let guilds = match db::get_gap_guilds(&data.db).await {
+    Ok(guilds) => guilds,
+    Err(e) => {
+      println!("Error reading service gap guilds from database: {e}");
+      return;
+    }
+  };
+  let embed = gap_embed(&gap);
+  for chan_id in guilds.iter().filter_map(|g| g.gap_channel) {
+    match ChannelId::new(chan_id as u64)
+      .send_message(&ctx.http, CreateMessage::new().add_embed(embed.clone()))
+      .await
+    {
+      Ok(message) => {
+        if let Err(e) =
+          db::add_service_gap_message(&data.db, gap.gap_id, chan_id, message.id.get() as i64).await
+        {
+          println!("Error saving service gap message: {e}");
+        }
+      }
+      Err(e) => println!("Failed to post service gap to channel {chan_id}: {e}"),
+    }
+  }
+}
+
+/// Matches recent gaps with official alerts for the same line, and links them once found.
+async fn link_alerts(ctx: &Context, data: &CTASharedData, now: i64) {
+  let gaps = match db::get_unlinked_service_gaps(&data.db, now - LINK_WINDOW_SECS).await {
+    Ok(gaps) if !gaps.is_empty() => gaps,
+    Ok(_) => return,
+    Err(e) => {
+      println!("Error reading unlinked service gaps: {e}");
+      return;
+    }
+  };
+  let mut route_ids: Vec<String> = gaps.iter().map(|g| g.route.to_lowercase()).collect();
+  route_ids.sort();
+  route_ids.dedup();
+  let alerts = match cta::alerts::get_alerts(AlertsOptions {
+    route_ids,
+    active_only: Some(true),
+    accessibility: Some(false),
+    planned: Some(false),
+    by_start_date: None,
+    recent_days: None,
+  })
+  .await
+  {
+    Ok(alerts) => alerts,
+    Err(e) => {
+      println!("Error getting alerts for service gaps: {e}");
+      return;
+    }
+  };
+  for gap in gaps {
+    let Some(alert) = alerts.iter().find(|alert| {
+      alert.event_start.timestamp() >= gap.detected_at - ALERT_SLACK_SECS
+        && alert.impacted_services.impacted_services.iter().any(|s| {
+          matches!(s.stype, ServiceType::TrainRoute) && s.id.eq_ignore_ascii_case(&gap.route)
+        })
+    }) else {
+      continue;
+    };
+    if let Err(e) = db::link_service_gap(
+      &data.db,
+      gap.gap_id,
+      alert.id,
+      &alert.headline,
+      &alert.alert_url,
+    )
+    .await
+    {
+      println!(
+        "Error linking service gap {} to alert {}: {e}",
+        gap.gap_id, alert.id
+      );
+      continue;
+    }
+    let gap = DBServiceGap {
+      alert_id: Some(alert.id),
+      alert_headline: Some(alert.headline.clone()),
+      alert_url: Some(alert.alert_url.inner.clone()),
+      ..gap
+    };
+    update_messages(ctx, data, &gap).await;
+  }
+}
+
+#[allow(clippy::cast_sign_loss)]
+async fn update_messages(ctx: &Context, data: &CTASharedData, gap: &DBServiceGap) {
+  let messages = match db::get_service_gap_messages(&data.db, gap.gap_id).await {
+    Ok(messages) => messages,
+    Err(e) => {
+      println!("Error reading messages for service gap {}: {e}", gap.gap_id);
+      return;
+    }
+  };
+  let embed = gap_embed(gap);
+  for message in messages {
+    if let Err(e) = ChannelId::new(message.channel_id as u64)
+      .edit_message(
+        &ctx.http,
+        MessageId::new(message.message_id as u64),
+        EditMessage::new().embed(embed.clone()),
+      )
+      .await
+    {
+      println!(
+        "Failed to update service gap message {}: {e}",
+        message.message_id
+      );
+    }
+  }
+}
+
+fn gap_embed(gap: &DBServiceGap) -> CreateEmbed {
+  let line = gap.route.parse::<LRouteCode>().map(LRouteName::from);
+  let line_name = line
+    .as_ref()
+    .map_or_else(|_| gap.route.clone(), ToString::to_string);
+  let stations = gap.station_names.join(", ");
+  let mut embed = CreateEmbed::new()
+    .title(format!("Possible disruption on the {line_name}"))
+    .description(format!(
+      "**Unconfirmed.** No trains toward {} have been seen at {stations} since {}, \
+      though they're scheduled every {} min. This is based on train positions and hasn't \
+      been confirmed by the CTA.",
+      gap.toward,
+      chicago_time(gap.last_seen).format("%-I:%M %p"),
+      (gap.usual_headway + 59) / 60
+    ))
+    .footer(CreateEmbedFooter::new(match gap.alert_id {
+      Some(alert_id) => format!(
+        "Detected at {} | CTA alert #{alert_id}",
+        chicago_time(gap.detected_at).format("%-I:%M %p")
+      ),
+      None => format!(
+        "Detected at {}",
+        chicago_time(gap.detected_at).format("%-I:%M %p")
+      ),
+    }));
+  if let Ok(line) = &line {
+    embed = embed.color(line.color_value());
+  }
+  if let (Some(headline), Some(url)) = (&gap.alert_headline, &gap.alert_url) {
+    embed = embed.field("Official alert", format!("[{headline}]({url})"), false);
+  }
+  if let Some(resolved_at) = gap.resolved_at {
+    embed = embed.field(
+      "Update",
+      format!(
+        "Trains were seen at these stations again at {}.",
+        chicago_time(resolved_at).format("%-I:%M %p")
+      ),
+      false,
+    );
+  }
+  embed
+}
+
+fn chicago_time(timestamp: i64) -> chrono::DateTime<chrono_tz::Tz> {
+  chrono::DateTime::from_timestamp(timestamp, 0)
+    .unwrap_or_default()
+    .with_timezone(&chrono_tz::America::Chicago)
+}
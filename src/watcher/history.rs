@@ -0,0 +1,214 @@
+use std::env;
+use std::time::Duration;
+
+use serenity::all::Context;
+
+use crate::{
+  cta::{
+    analysis::reliability::{
+      average_scheduled_headway, service_date, service_day_bounds, MAX_HEADWAY_SECS,
+    },
+    bustracker::{VehiclesParameters, VidOrRt},
+    traintracker::LRouteCode,
+  },
+  db::{self, DBLineReliability},
+  CTAShared, CTASharedData,
+};
+
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+/// Bus Tracker only accepts this many routes per request.
+const ROUTES_PER_REQUEST: usize = 10;
+/// Partitions are created this far ahead, so a slow poll never writes into a missing one.
+const PARTITION_LEAD_SECS: i64 = 24 * 60 * 60;
+const PRUNE_INTERVAL_SECS: i64 = 60 * 60;
+const SUMMARY_INTERVAL_SECS: i64 = 60 * 60;
+/// Finished service days this far back are summarized into reliability figures if they haven't
+/// been already.
+const SUMMARY_LOOKBACK_DAYS: u64 = 7;
+/// Days where the recorder stopped for longer than this, like a restart or an outage, aren't
+/// summarized, since partial figures would stand for the whole day.
+const MAX_RECORDING_GAP_SECS: i64 = 15 * 60;
+
+/// Settings for the history recorder, read from the environment.
+///
+/// - `RECORD_HISTORY`: set to `1` or `true` to turn the recorder on.
+/// - `HISTORY_RETENTION_DAYS`: how many days of samples to keep. Defaults to 30.
+/// - `HISTORY_BUS_ROUTES`: comma-separated bus routes to record. Buses aren't recorded if unset,
+///   since polling every route would use up the Bus Tracker request limit.
+#[derive(Debug)]
+struct HistoryConfig {
+  retention_days: i64,
+  bus_routes: Vec<String>,
+}
+
+impl HistoryConfig {
+  fn from_env() -> Option<Self> {
+    match env::var("RECORD_HISTORY").as_deref() {
+      Ok("1" | "true") => {}
+      _ => return None,
+    }
+    let retention_days = match env::var("HISTORY_RETENTION_DAYS") {
+      Ok(val) => val.parse().unwrap_or_else(|_| {
+        println!("HISTORY_RETENTION_DAYS is not a number. Keeping {DEFAULT_RETENTION_DAYS} days.");
+        DEFAULT_RETENTION_DAYS
+      }),
+      Err(_) => DEFAULT_RETENTION_DAYS,
+    };
+    let bus_routes = env::var("HISTORY_BUS_ROUTES")
+      .map(|val| {
+        val
+          .split(',')
+          .map(str::trim)
+          .filter(|r| !r.is_empty())
+          .map(ToString::to_string)
+          .collect()
+      })
+      .unwrap_or_default();
+    Some(Self {
+      retention_days,
+      bus_routes,
+    })
+  }
+}
+
+pub async fn watch(ctx: Context) {
+  static INTERVAL_SECS: u64 = 60;
+  let Some(config) = HistoryConfig::from_env() else {
+    println!("RECORD_HISTORY not set. Not recording position history.");
+    return;
+  };
+  println!(
+    "History recorder task spawned. Recording every {INTERVAL_SECS} seconds, keeping {} days.",
+    config.retention_days
+  );
+  let mut partitions_until = 0;
+  let mut last_pruned = 0;
+  let mut last_summarized = 0;
+  loop {
+    let now = chrono::Utc::now().timestamp();
+    {
+      let data = ctx.data.read().await;
+      let data = data.get::<CTAShared>().expect("no shared data");
+      if now + PARTITION_LEAD_SECS / 2 > partitions_until {
+        match db::create_history_partitions(&data.db, now, now + PARTITION_LEAD_SECS).await {
+          Ok(()) => partitions_until = now + PARTITION_LEAD_SECS,
+          Err(e) => println!("Error creating history partitions: {e}"),
+        }
+      }
+      if now - last_pruned > PRUNE_INTERVAL_SECS {
+        match db::drop_history_partitions(&data.db, now - config.retention_days * 24 * 60 * 60)
+          .await
+        {
+          Ok(dropped) => {
+            last_pruned = now;
+            if !dropped.is_empty() {
+              println!("Dropped old history partitions: {}", dropped.join(", "));
+            }
+          }
+          Err(e) => println!("Error dropping old history partitions: {e}"),
+        }
+      }
+      record(data, &config, now).await;
+      if now - last_summarized > SUMMARY_INTERVAL_SECS {
+        summarize_reliability(data, now).await;
+        last_summarized = now;
+      }
+    }
+    tokio::time::sleep(Duration::from_secs(INTERVAL_SECS)).await;
+  }
+}
+
+async fn record(data: &CTASharedData, config: &HistoryConfig, now: i64) {
+  match data.traintracker.fleet(&LRouteCode::ALL).await {
+    Ok(fleet) => {
+      if let Err(e) = db::record_train_positions(&data.db, now, &fleet.trains).await {
+        println!("Error recording train positions: {e}");
+      }
+    }
+    Err(e) => println!("Error getting train positions for history: {e}"),
+  }
+  for routes in config.bus_routes.chunks(ROUTES_PER_REQUEST) {
+    match data
+      .bustracker
+      .get_vehicles(VehiclesParameters {
+        search: VidOrRt::Rt {
+          route_codes: routes.to_vec(),
+        },
+      })
+      .await
+    {
+      Ok(vehicles) => {
+        if let Err(e) = db::record_bus_vehicles(&data.db, now, &vehicles).await {
+          println!("Error recording bus vehicles: {e}");
+        }
+      }
+      Err(e) => println!("Error getting vehicles for history: {e}"),
+    }
+  }
+}
+
+/// Works out daily reliability figures for each line from the recorded positions and the GTFS
+/// schedule. Days the recorder didn't cover all of are skipped.
+async fn summarize_reliability(data: &CTASharedData, now: i64) {
+  let today = service_date(now);
+  let Some(first_day) = today.checked_sub_days(chrono::Days::new(SUMMARY_LOOKBACK_DAYS)) else {
+    return;
+  };
+  let done = match db::get_line_reliability(&data.db, service_day_bounds(first_day).0).await {
+    Ok(days) => days,
+    Err(e) => {
+      println!("Error reading line reliability: {e}");
+      return;
+    }
+  };
+  for date in first_day.iter_days().take_while(|d| *d < today) {
+    let (start, end) = service_day_bounds(date);
+    if LRouteCode::ALL.iter().all(|route| {
+      let route_id = route.to_string();
+      done
+        .iter()
+        .any(|d| d.service_day == start && d.route == route_id)
+    }) {
+      continue;
+    }
+    match db::get_longest_recording_gap(&data.db, start, end).await {
+      Ok(gap) if gap <= MAX_RECORDING_GAP_SECS => {}
+      Ok(_) => continue,
+      Err(e) => {
+        println!("Error checking recorded positions on {date}: {e}");
+        continue;
+      }
+    }
+    for route in LRouteCode::ALL {
+      let route_id = route.to_string();
+      if done
+        .iter()
+        .any(|d| d.service_day == start && d.route == route_id)
+      {
+        continue;
+      }
+      let observed =
+        match db::get_observed_service(&data.db, &route_id, start, end, MAX_HEADWAY_SECS).await {
+          Ok(observed) if observed.samples > 0 => observed,
+          Ok(_) => continue,
+          Err(e) => {
+            println!("Error summarizing {route_id} service on {date}: {e}");
+            continue;
+          }
+        };
+      let trips_scheduled = data.gtfs.scheduled_trips(&route_id, date);
+      let day = DBLineReliability {
+        service_day: start,
+        route: route_id.clone(),
+        trips_scheduled: i32::try_from(trips_scheduled).unwrap_or(i32::MAX),
+        trips_run: i32::try_from(observed.trips_run).unwrap_or(i32::MAX),
+        avg_headway: observed.avg_headway,
+        scheduled_headway: average_scheduled_headway(&data.gtfs.stop_schedule(&route_id, date)),
+        delayed_share: observed.delayed_share,
+      };
+      if let Err(e) = db::add_line_reliability(&data.db, &day).await {
+        println!("Error saving line reliability: {e}");
+      }
+    }
+  }
+}
@@ -1,7 +1,16 @@
+pub mod accuracy;
+pub mod anomalies;
+pub mod bulletins;
+pub mod bunching;
+pub mod detours;
+pub mod follow;
+pub mod gaps;
+pub mod history;
+pub mod watchlist;
+
 use std::thread::sleep;
 use std::time::Duration;
 
-use chrono::NaiveTime;
 use serenity::all::{ChannelId, Context, CreateEmbed, CreateEmbedAuthor, CreateMessage, Timestamp};
 use sqlx::{Executor, Postgres};
 use thiserror::Error;
@@ -136,19 +145,7 @@ async fn trigger(ctx: &Context, alert: Alert) -> Result<(), PublishError> {
   let data = ctx.data.read().await;
   let data = data.get::<CTAShared>().expect("no shared data");
   let mut publish_count = 0;
-  let start_time: i64 = match alert.event_start {
-    cta::alerts::DateOrDateTime::DateTime(naive_date_time) => naive_date_time
-      .and_local_timezone(chrono_tz::America::Chicago)
-      .earliest()
-      .unwrap()
-      .timestamp(),
-    cta::alerts::DateOrDateTime::Date(naive_date) => naive_date
-      .and_time(NaiveTime::from_hms_opt(0, 0, 0).unwrap())
-      .and_local_timezone(chrono_tz::America::Chicago)
-      .earliest()
-      .unwrap()
-      .timestamp(),
-  };
+  let start_time: i64 = alert.event_start.timestamp();
 
   // send alerts via discord
   for guild in &db::get_subscribed_guilds(&data.db).await? {
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serenity::all::{ChannelId, Context, CreateEmbed, CreateMessage, UserId};
+
+use crate::{
+  cta::{
+    bustracker::{BusTracker, Vehicle, VehiclesParameters, VidOrRt},
+    traintracker::{LRouteCode, LRouteName, TTPosition},
+  },
+  db::{self, DBWatch},
+  CTAShared, CTASharedData,
+};
+
+/// A watched item missing from the feeds for this long is considered out of service again.
+const OUT_OF_SERVICE_AFTER_SECS: i64 = 30 * 60;
+/// Bus Tracker only accepts this many vehicle IDs per request.
+const VEHICLES_PER_REQUEST: usize = 10;
+
+pub async fn watch(ctx: Context) {
+  static INTERVAL_SECS: u64 = 60;
+  println!("Watchlist task spawned. Polling every {INTERVAL_SECS} seconds.");
+  loop {
+    check(&ctx).await;
+    tokio::time::sleep(Duration::from_secs(INTERVAL_SECS)).await;
+  }
+}
+
+async fn check(ctx: &Context) {
+  let data = ctx.data.read().await;
+  let data = data.get::<CTAShared>().expect("no shared data");
+  let now = chrono::Utc::now().timestamp();
+
+  let watches = match db::get_watches(&data.db).await {
+    Ok(watches) if !watches.is_empty() => watches,
+    Ok(_) => return,
+    Err(e) => {
+      println!("Error getting watchlist from database: {e}");
+      return;
+    }
+  };
+
+  let fleet = if watches.iter().any(|w| w.kind == "train") {
+    data
+      .traintracker
+      .fleet(&LRouteCode::ALL)
+      .await
+      .inspect_err(|e| println!("Error getting train positions for watchlist: {e}"))
+      .ok()
+  } else {
+    None
+  };
+  let vehicles = watched_vehicles(data, &watches).await;
+
+  for watch in watches {
+    let sighting = match watch.kind.as_str() {
+      "train" => match (&fleet, watch.identifier.parse::<i32>()) {
+        (Some(fleet), Ok(run)) => Some(fleet.train(run).map(|train| train_embed(&watch, train))),
+        _ => None,
+      },
+      "bus" => vehicles.as_ref().map(|vehicles| {
+        vehicles
+          .get(&watch.identifier)
+          .map(|v| bus_embed(data, &watch, v))
+      }),
+      _ => None,
+    };
+    // `None` means the feed couldn't be checked this time, so leave the watch alone.
+    let Some(sighting) = sighting else {
+      continue;
+    };
+    let result = match (sighting, watch.in_service) {
+      (Some(embed), false) => {
+        notify(ctx, &watch, embed).await;
+        db::set_watch_status(&data.db, watch.watch_id, true, Some(now)).await
+      }
+      (Some(_), true) => db::set_watch_status(&data.db, watch.watch_id, true, Some(now)).await,
+      (None, true) if now - watch.last_seen.unwrap_or_default() > OUT_OF_SERVICE_AFTER_SECS => {
+        db::set_watch_status(&data.db, watch.watch_id, false, watch.last_seen).await
+      }
+      _ => Ok(()),
+    };
+    if let Err(e) = result {
+      println!("Error updating watchlist entry {}: {e}", watch.watch_id);
+    }
+  }
+}
+
+/// Looks up every watched bus, or returns `None` if Bus Tracker couldn't be reached.
+async fn watched_vehicles(
+  data: &CTASharedData,
+  watches: &[DBWatch],
+) -> Option<HashMap<String, Vehicle>> {
+  let mut vids: Vec<String> = watches
+    .iter()
+    .filter(|w| w.kind == "bus")
+    .map(|w| w.identifier.clone())
+    .collect();
+  vids.sort();
+  vids.dedup();
+  let mut found = HashMap::new();
+  for chunk in vids.chunks(VEHICLES_PER_REQUEST) {
+    match data
+      .bustracker
+      .get_vehicles(VehiclesParameters {
+        search: VidOrRt::Vid {
+          vehicle_ids: chunk.to_vec(),
+        },
+      })
+      .await
+    {
+      Ok(vehicles) => found.extend(vehicles.into_iter().map(|v| (v.vid.clone(), v))),
+      Err(e) => {
+        println!("Error getting vehicles for watchlist: {e}");
+        return None;
+      }
+    }
+  }
+  Some(found)
+}
+
+#[allow(clippy::cast_sign_loss)]
+async fn notify(ctx: &Context, watch: &DBWatch, embed: CreateEmbed) {
+  let message = CreateMessage::new().add_embed(embed);
+  let result = match watch.target_type.as_str() {
+    "channel" => ChannelId::new(watch.target_id as u64)
+      .send_message(&ctx.http, message)
+      .await
+      .map(|_| ()),
+    "user" => UserId::new(watch.target_id as u64)
+      .direct_message(&ctx.http, message)
+      .await
+      .map(|_| ()),
+    _ => Ok(()),
+  };
+  if let Err(why) = result {
+    println!(
+      "Failed to send watchlist notification {}: {why}",
+      watch.watch_id
+    );
+  }
+}
+
+fn title(watch: &DBWatch, noun: &str) -> String {
+  match &watch.label {
+    Some(label) => format!("{label} ({noun} #{}) is in service", watch.identifier),
+    None => format!("{noun} #{} is in service", watch.identifier),
+  }
+}
+
+fn train_embed(watch: &DBWatch, train: &TTPosition) -> CreateEmbed {
+  let embed = CreateEmbed::new()
+    .title(title(watch, "Train"))
+    .field("Destination", &train.destination_name, true)
+    .field("Next Station", &train.next_station_name, true);
+  match train.route {
+    Some(route) => {
+      let line = LRouteName::from(route);
+      embed
+        .color(line.color_value())
+        .description(format!("Now running on the {line}."))
+    }
+    None => embed,
+  }
+}
+
+fn bus_embed(data: &CTASharedData, watch: &DBWatch, vehicle: &Vehicle) -> CreateEmbed {
+  let route_name = data
+    .gtfs
+    .gtfs_data
+    .get_route(&vehicle.rt)
+    .ok()
+    .and_then(|r| r.long_name.clone())
+    .unwrap_or_default();
+  CreateEmbed::new()
+    .title(title(watch, "Bus"))
+    .description(format!("Now running on Route {} {route_name}.", vehicle.rt))
+    .field("Destination", &vehicle.des, true)
+    .field(
+      "Garage",
+      String::from(BusTracker::tablockid_to_garage(&vehicle.tablockid)),
+      true,
+    )
+    .field("Block", &vehicle.tablockid, true)
+}